@@ -0,0 +1,137 @@
+//! Instruction counts of the hot loops, measured with Callgrind (see `iai-callgrind`).
+//!
+//! Unlike the criterion benchmarks in `kem_api.rs`, the counts do not depend on the load of the machine,
+//! hence they reveal small regressions of refactorings. The inputs are computed in the setup of each
+//! benchmark, which is not counted. Only the field arithmetic is measured with the features `encap-only`
+//! and `decap-only`, because the inputs of the other routines are computed with the full API.
+
+use std::hint::black_box;
+
+use iai_callgrind::{library_benchmark, library_benchmark_group, main};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use rand::RngCore;
+
+use classic_mceliece_rust::internals::{gf_mul, gf_mul_poly};
+use classic_mceliece_rust::Variant;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use classic_mceliece_rust::{
+    encapsulate,
+    internals::{bm, pk_gen, synd},
+    keypair_boxed, AesState, ExpandedSecretKey, PublicKey, SecretKey, Workspace,
+};
+
+/// `t` pseudo-random field elements (without a random number generator, to keep the setup cheap)
+fn field_elements(seed: usize) -> Vec<u16> {
+    let params = Variant::compiled().params();
+    let mask = (1 << params.m) - 1;
+    (0..params.t)
+        .map(|i| ((i * 0x9E37 + seed * 0x7F4B + 1) & mask) as u16)
+        .collect()
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+/// A received word (a ciphertext padded to `n` bits) and the expanded secret key to decode it
+fn received_word() -> (Vec<u8>, ExpandedSecretKey, Workspace) {
+    let mut rng = AesState::new();
+    let (pk, sk) = keypair_boxed(&mut rng).expect("keypair failed!");
+    let (ct, _) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
+
+    let params = Variant::compiled().params();
+    let mut r = vec![0u8; params.n / 8];
+    let syndrome = &ct.as_ref()[..params.m * params.t / 8];
+    r[..syndrome.len()].copy_from_slice(syndrome);
+    (r, sk.expand().expect("expand failed!"), Workspace::new())
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+/// The syndromes of a received word
+fn syndromes() -> Vec<u16> {
+    let (r, esk, mut ws) = received_word();
+    let mut s = vec![0u16; 2 * Variant::compiled().params().t];
+    synd(&mut s, &esk, &r, &mut ws).expect("synd failed!");
+    s
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+/// A secret key and random words `perm` for which the matrix has the (semi-)systematic form
+fn permutation() -> (Box<PublicKey>, Box<SecretKey>, Vec<u32>, Workspace) {
+    let mut rng = AesState::new();
+    let (mut pk, sk) = keypair_boxed(&mut rng).expect("keypair failed!");
+    let mut ws = Workspace::new();
+    let mut bytes = vec![0u8; 4 << Variant::compiled().params().m];
+
+    loop {
+        rng.fill_bytes(&mut bytes);
+        let perm: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        if pk_gen(&mut pk, &sk, &perm, &mut ws).expect("pk_gen failed!") {
+            return (pk, sk, perm, ws);
+        }
+    }
+}
+
+#[library_benchmark]
+#[bench::elements(0x0A5C, 0x1F33)]
+fn bench_gf_mul(a: u16, b: u16) -> u16 {
+    black_box(gf_mul(a, b))
+}
+
+#[library_benchmark]
+#[bench::polynomials(field_elements(1), field_elements(2))]
+fn bench_gf_mul_poly(a: Vec<u16>, b: Vec<u16>) -> Vec<u16> {
+    let mut out = vec![0u16; a.len()];
+    gf_mul_poly(&mut out, &a, &b).expect("gf_mul_poly failed!");
+    black_box(out)
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+#[library_benchmark]
+#[bench::ciphertext(received_word())]
+fn bench_synd(input: (Vec<u8>, ExpandedSecretKey, Workspace)) -> Vec<u16> {
+    let (r, esk, mut ws) = input;
+    let mut s = vec![0u16; 2 * Variant::compiled().params().t];
+    synd(&mut s, &esk, &r, &mut ws).expect("synd failed!");
+    black_box(s)
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+#[library_benchmark]
+#[bench::ciphertext(syndromes())]
+fn bench_bm(mut s: Vec<u16>) -> Vec<u16> {
+    let mut locator = vec![0u16; s.len() / 2 + 1];
+    bm(&mut locator, &mut s).expect("bm failed!");
+    black_box(locator)
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+#[library_benchmark]
+#[bench::systematic(permutation())]
+fn bench_pk_gen(input: (Box<PublicKey>, Box<SecretKey>, Vec<u32>, Workspace)) -> bool {
+    let (mut pk, sk, perm, mut ws) = input;
+    black_box(pk_gen(&mut pk, &sk, &perm, &mut ws).expect("pk_gen failed!"))
+}
+
+library_benchmark_group!(
+    name = field;
+    benchmarks = bench_gf_mul, bench_gf_mul_poly
+);
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+library_benchmark_group!(
+    name = decoder;
+    benchmarks = bench_synd, bench_bm
+);
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+library_benchmark_group!(
+    name = keygen;
+    benchmarks = bench_pk_gen
+);
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+main!(library_benchmark_groups = field, decoder, keygen);
+
+#[cfg(any(feature = "encap-only", feature = "decap-only"))]
+main!(library_benchmark_groups = field);
@@ -1,21 +1,39 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use criterion_cycles_per_byte::CyclesPerByte;
+//! Criterion benchmarks of the KEM API. They need the full API, i.e. neither the feature `encap-only` nor `decap-only`.
+#![cfg_attr(
+    any(feature = "encap-only", feature = "decap-only"),
+    allow(dead_code, unused_imports)
+)]
 
-use classic_mceliece_rust::AesState;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion};
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 use classic_mceliece_rust::{crypto_kem_dec, crypto_kem_enc, crypto_kem_keypair};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 use classic_mceliece_rust::{
-    CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_PUBLICKEYBYTES, CRYPTO_SECRETKEYBYTES,
+    decap_with, decapsulate_expanded, encapsulate, encapsulate_streaming, keypair, keypair_boxed,
+    keypair_streaming,
 };
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use classic_mceliece_rust::{AesState, Workspace, CRYPTO_PRIMITIVE};
+
+/// Clock cycles on x86 (as in the tables of the README), the wall-clock time elsewhere
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+type Measurement = criterion_cycles_per_byte::CyclesPerByte;
+/// Clock cycles on x86 (as in the tables of the README), the wall-clock time elsewhere
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+type Measurement = criterion::measurement::WallTime;
+
+/// The benchmarks of one variant (feature), named after it to keep the results of the variants apart
+type Group<'a> = BenchmarkGroup<'a, Measurement>;
 
-pub fn bench_complete_kem(criterion: &mut Criterion<CyclesPerByte>) {
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_complete_kem(group: &mut Group) {
     let mut rng = AesState::new();
-    let mut pk = [0u8; CRYPTO_PUBLICKEYBYTES];
-    let mut sk = [0u8; CRYPTO_SECRETKEYBYTES];
-    let mut ct = [0u8; CRYPTO_CIPHERTEXTBYTES];
-    let mut ss_alice = [0u8; CRYPTO_BYTES];
-    let mut ss_bob = [0u8; CRYPTO_BYTES];
+    let (mut pk, mut sk) = keypair(&mut rng).expect("keypair failed!");
+    let (mut ct, mut ss_alice) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
+    let mut ss_bob = ss_alice.clone();
 
-    criterion.bench_function("kem", |b| {
+    group.bench_function("kem", |b| {
         b.iter(|| {
             crypto_kem_keypair(&mut pk, &mut sk, &mut rng).expect("crypto_kem_keypair failed!");
             crypto_kem_enc(&mut ct, &mut ss_alice, &pk, &mut rng).expect("crypto_kem_enc failed!");
@@ -25,55 +43,126 @@ pub fn bench_complete_kem(criterion: &mut Criterion<CyclesPerByte>) {
     });
 }
 
-pub fn bench_kem_keypair(criterion: &mut Criterion<CyclesPerByte>) {
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_keypair(group: &mut Group) {
     let mut rng = AesState::new();
-    let mut pk = [0u8; CRYPTO_PUBLICKEYBYTES];
-    let mut sk = [0u8; CRYPTO_SECRETKEYBYTES];
+    let (mut pk, mut sk) = keypair(&mut rng).expect("keypair failed!");
 
-    criterion.bench_function("kem_keypair", |b| {
+    group.bench_function("kem_keypair", |b| {
         b.iter(|| {
             crypto_kem_keypair(&mut pk, &mut sk, &mut rng).expect("crypto_kem_keypair failed!");
         })
     });
 }
 
-pub fn bench_kem_enc(criterion: &mut Criterion<CyclesPerByte>) {
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_keypair_streaming(group: &mut Group) {
     let mut rng = AesState::new();
-    let mut pk = [0u8; CRYPTO_PUBLICKEYBYTES];
-    let mut sk = [0u8; CRYPTO_SECRETKEYBYTES];
-    let mut ct = [0u8; CRYPTO_CIPHERTEXTBYTES];
-    let mut ss_alice = [0u8; CRYPTO_BYTES];
 
-    crypto_kem_keypair(&mut pk, &mut sk, &mut rng).expect("crypto_kem_keypair failed!");
+    group.bench_function("kem_keypair_streaming", |b| {
+        b.iter(|| {
+            keypair_streaming(&mut rng, |row| {
+                criterion::black_box(row);
+            })
+            .expect("keypair_streaming failed!");
+        })
+    });
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_enc(group: &mut Group) {
+    let mut rng = AesState::new();
+    let (pk, _) = keypair(&mut rng).expect("keypair failed!");
+    let (mut ct, mut ss_alice) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
 
-    criterion.bench_function("kem_enc", |b| {
+    group.bench_function("kem_enc", |b| {
         b.iter(|| {
             crypto_kem_enc(&mut ct, &mut ss_alice, &pk, &mut rng).expect("crypto_kem_enc failed!");
         })
     });
 }
 
-pub fn bench_kem_dec(criterion: &mut Criterion<CyclesPerByte>) {
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_enc_streaming(group: &mut Group) {
     let mut rng = AesState::new();
-    let mut pk = [0u8; CRYPTO_PUBLICKEYBYTES];
-    let mut sk = [0u8; CRYPTO_SECRETKEYBYTES];
-    let mut ct = [0u8; CRYPTO_CIPHERTEXTBYTES];
-    let mut ss_alice = [0u8; CRYPTO_BYTES];
-    let mut ss_bob = [0u8; CRYPTO_BYTES];
+    let (pk, _) = keypair_boxed(&mut rng).expect("keypair failed!");
 
-    crypto_kem_keypair(&mut pk, &mut sk, &mut rng).expect("crypto_kem_keypair failed!");
-    crypto_kem_enc(&mut ct, &mut ss_alice, &pk, &mut rng).expect("crypto_kem_enc failed!");
+    group.bench_function("kem_enc_streaming", |b| {
+        b.iter(|| {
+            let mut reader = &pk.as_array()[..];
+            encapsulate_streaming(&mut reader, &mut rng).expect("encapsulate failed!");
+        })
+    });
+}
 
-    criterion.bench_function("kem_dec", |b| {
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_dec(group: &mut Group) {
+    let mut rng = AesState::new();
+    let (pk, sk) = keypair(&mut rng).expect("keypair failed!");
+    let (ct, mut ss_bob) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
+
+    group.bench_function("kem_dec", |b| {
         b.iter(|| {
             crypto_kem_dec(&mut ss_bob, &ct, &sk).expect("crypto_kem_dec failed!");
         })
     });
 }
 
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_dec_expanded(group: &mut Group) {
+    let mut rng = AesState::new();
+    let (pk, sk) = keypair_boxed(&mut rng).expect("keypair failed!");
+    let (ct, _) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
+
+    group.bench_function("kem_expand", |b| {
+        b.iter(|| sk.expand().expect("expand failed!"))
+    });
+
+    let esk = sk.expand().expect("expand failed!");
+    group.bench_function("kem_dec_expanded", |b| {
+        b.iter(|| decapsulate_expanded(&esk, &ct).expect("decapsulate_expanded failed!"))
+    });
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn bench_kem_dec_with(group: &mut Group) {
+    let mut rng = AesState::new();
+    let (pk, sk) = keypair_boxed(&mut rng).expect("keypair failed!");
+    let (ct, _) = encapsulate(&pk, &mut rng).expect("encapsulate failed!");
+    let mut ws = Workspace::new();
+
+    group.bench_function("kem_dec_with", |b| {
+        b.iter(|| decap_with(&sk, &ct, &mut ws).expect("decap_with failed!"))
+    });
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn benches(criterion: &mut Criterion<Measurement>) {
+    let mut group = criterion.benchmark_group(CRYPTO_PRIMITIVE);
+    group.sample_size(10);
+
+    bench_complete_kem(&mut group);
+    bench_kem_keypair(&mut group);
+    bench_kem_keypair_streaming(&mut group);
+    bench_kem_enc(&mut group);
+    bench_kem_enc_streaming(&mut group);
+    bench_kem_dec(&mut group);
+    bench_kem_dec_expanded(&mut group);
+    bench_kem_dec_with(&mut group);
+
+    group.finish();
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 criterion_group!(
-    name = benches;
-    config = Criterion::default().with_measurement(CyclesPerByte);
-    targets = bench_complete_kem, bench_kem_keypair, bench_kem_enc, bench_kem_dec
+    name = kem_api;
+    config = Criterion::default().with_measurement(Measurement {});
+    targets = benches
 );
-criterion_main!(benches);
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+criterion_main!(kem_api);
+
+#[cfg(any(feature = "encap-only", feature = "decap-only"))]
+fn main() {
+    eprintln!("the benchmarks need the key generation, encapsulation and decapsulation");
+}
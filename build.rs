@@ -1,29 +1,42 @@
-use std::collections::HashMap;
-
 fn main() {
-    let mut features = HashMap::new();
+    let features = [
+        ("mceliece348864", cfg!(feature = "mceliece348864")),
+        ("mceliece348864f", cfg!(feature = "mceliece348864f")),
+        ("mceliece460896", cfg!(feature = "mceliece460896")),
+        ("mceliece460896f", cfg!(feature = "mceliece460896f")),
+        ("mceliece6688128", cfg!(feature = "mceliece6688128")),
+        ("mceliece6688128f", cfg!(feature = "mceliece6688128f")),
+        ("mceliece6960119", cfg!(feature = "mceliece6960119")),
+        ("mceliece6960119f", cfg!(feature = "mceliece6960119f")),
+        ("mceliece8192128", cfg!(feature = "mceliece8192128")),
+        ("mceliece8192128f", cfg!(feature = "mceliece8192128f")),
+    ];
+
+    let selected: Vec<&str> = features
+        .iter()
+        .filter(|(_, used)| *used)
+        .map(|(feature, _)| *feature)
+        .collect();
 
-    features.insert("mceliece348864", cfg!(feature = "mceliece348864"));
-    features.insert("mceliece348864f", cfg!(feature = "mceliece348864f"));
-    features.insert("mceliece460896", cfg!(feature = "mceliece460896"));
-    features.insert("mceliece460896f", cfg!(feature = "mceliece460896f"));
-    features.insert("mceliece6688128", cfg!(feature = "mceliece6688128"));
-    features.insert("mceliece6688128f", cfg!(feature = "mceliece6688128f"));
-    features.insert("mceliece6960119", cfg!(feature = "mceliece6960119"));
-    features.insert("mceliece6960119f", cfg!(feature = "mceliece6960119f"));
-    features.insert("mceliece8192128", cfg!(feature = "mceliece8192128"));
-    features.insert("mceliece8192128f", cfg!(feature = "mceliece8192128f"));
+    // The parameters are compile-time constants shared by all modules,
+    // hence one build implements exactly one variant (see `Variant::compiled`).
+    // Cargo unifies the features of all dependents, so the variant is selected once per binary.
+    if selected.len() > 1 {
+        panic!("Config error: \n\t{} cannot be used simultaneously!\n\tPlease select only one feature (one variant per binary is supported).", selected.join(", "));
+    }
 
-    let mut target_feature = "";
-    for (feature, used) in features {
-        if !target_feature.is_empty() && used {
-            panic!("Config error: \n\t{} and {} cannot be used simultaneously!\n\tPlease select only one feature.", target_feature, feature);
-        } else if used {
-            target_feature = feature;
-        }
+    if cfg!(feature = "encap-only") && cfg!(feature = "decap-only") {
+        panic!("Config error: \n\tencap-only and decap-only cannot be used simultaneously!\n\tPlease select at most one of them.");
     }
 
-    if target_feature == "" {
+    if selected.is_empty() {
         println!("cargo:rustc-cfg=feature=\"mceliece348864\"");
     }
+
+    // The f-variants differ from their counterparts by the semi-systematic form of the public key only.
+    // The code tests the single `semi_systematic` cfg instead of listing the five features at every site.
+    println!("cargo:rustc-check-cfg=cfg(semi_systematic)");
+    if selected.iter().any(|feature| feature.ends_with('f')) {
+        println!("cargo:rustc-cfg=semi_systematic");
+    }
 }
@@ -85,6 +85,26 @@ pub const CRYPTO_PRIMITIVE: &str = "mceliece8192128";
 /// Name of the variant
 pub const CRYPTO_PRIMITIVE: &str = "mceliece8192128f";
 
+#[cfg(any(
+    feature = "mceliece348864",
+    feature = "mceliece460896",
+    feature = "mceliece6688128",
+    feature = "mceliece6960119",
+    feature = "mceliece8192128"
+))]
+/// The number of bytes required to store the compact secret key (i.e. the seed)
+pub const CRYPTO_COMPACTSECRETKEYBYTES: usize = 32;
+#[cfg(semi_systematic)]
+/// The number of bytes required to store the compact secret key (i.e. the seed and the pivots)
+pub const CRYPTO_COMPACTSECRETKEYBYTES: usize = 40;
+
+/// The number of bytes required to store the error vector, i.e. the randomness of the encapsulation
+pub const CRYPTO_ERRORVECTORBYTES: usize = crate::params::SYS_N / 8;
+
+/// The number of bytes required to store the public key in column-major layout (see `PublicKeyColumns`),
+/// i.e. `n - m · t` columns of `⌈m · t / 8⌉` bytes each
+pub const CRYPTO_PUBLICKEYCOLUMNSBYTES: usize = crate::params::PK_NCOLS * crate::params::SYND_BYTES;
+
 /// The number of bytes required to store the shared secret negotiated between both parties
 // this value is uniform
 pub const CRYPTO_BYTES: usize = 32;
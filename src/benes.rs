@@ -4,13 +4,19 @@
 //! “McBits Revisited” by Tung Chou (2017)
 //! <https://eprint.iacr.org/2017/793.pdf>
 
+use crate::ct::cswap;
+use crate::error::Error;
+#[cfg(any(test, not(feature = "decap-only")))]
 use crate::gf::Gf;
 use crate::macros::sub;
+#[cfg(any(test, not(feature = "decap-only")))]
 use crate::params::SYS_N;
 use crate::params::{COND_BYTES, GFBITS};
 use crate::transpose;
 use crate::util;
-use std::error;
+use bytemuck::Zeroable;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 /// Layers of the Beneš network. The required size of `data` and `bits` depends on the value `lgs`.
 /// NOTE const expressions are not sophisticated enough in rust yet to represent this relationship.
@@ -22,53 +28,46 @@ use std::error;
 /// | 6 | 128 | 64 |
 /// | 5 | 64 | 32 |
 /// | 4 | 64 | 32 |
-#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+///
+/// Within each block of `2 * s` elements (`s = 2^lgs`), the elements `j` and `j + s` are swapped
+/// if the next bit of `bits` is set. The blocks are iterated instead of indexed,
+/// so no bounds checks (i.e. panics) remain.
 fn layer(data: &mut [u64], bits: &[u64], lgs: usize) {
-    let mut index = 0;
-
-    let s = 1 << lgs;
-
-    let mut i = 0usize;
-    while i < 64 {
-        for j in i..(i + s) {
-            let mut d = data[j + 0] ^ data[j + s];
-            d &= bits[index];
-            index += 1;
+    // blocks of 64 elements are the largest ones, which also proves the chunk size non-zero
+    let s: usize = (1 << lgs).min(32);
 
-            data[j + 0] ^= d;
-            data[j + s] ^= d;
+    for (block, cond) in data[..64].chunks_exact_mut(2 * s).zip(bits.chunks_exact(s)) {
+        let (lo, hi) = block.split_at_mut(block.len() / 2);
+        for ((x, y), &b) in lo.iter_mut().zip(hi.iter_mut()).zip(cond) {
+            cswap(x, y, b);
         }
-        i += s * 2;
     }
 }
 
 /// Inner layers of the Beneš network. The required size of `data` and `bits` depends on the value `lgs`.
 /// `data[0]`, `data[1]` and `bits` must have the same length; namely `2^(lgs + 1)` with `lgs ≥ 5`.
+/// The bits are used alternately for `data[0]` and `data[1]`.
 #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
 fn layer_in<const L: usize>(data: &mut [[u64; L]; 2], bits: &[u64; L], lgs: usize) {
-    let mut d: u64;
-    let mut index = 0;
-
-    let s = 1 << lgs;
-
-    let mut i = 0usize;
-    while i < 64 {
-        for j in i..(i + s) {
-            d = data[0][j + 0] ^ data[0][j + s];
-            d &= bits[index];
-            index += 1;
-
-            data[0][j + 0] ^= d;
-            data[0][j + s] ^= d;
-
-            d = data[1][j + 0] ^ data[1][j + s];
-            d &= bits[index];
-            index += 1;
-
-            data[1][j + 0] ^= d;
-            data[1][j + s] ^= d;
+    let s: usize = (1 << lgs).min(32);
+    let [data0, data1] = data;
+
+    for ((block0, block1), cond) in data0[..64]
+        .chunks_exact_mut(2 * s)
+        .zip(data1[..64].chunks_exact_mut(2 * s))
+        .zip(bits.chunks_exact(2 * s))
+    {
+        let (lo0, hi0) = block0.split_at_mut(block0.len() / 2);
+        let (lo1, hi1) = block1.split_at_mut(block1.len() / 2);
+        for (((x0, y0), (x1, y1)), b) in lo0
+            .iter_mut()
+            .zip(hi0.iter_mut())
+            .zip(lo1.iter_mut().zip(hi1.iter_mut()))
+            .zip(cond.chunks_exact(2))
+        {
+            cswap(x0, y0, b[0]);
+            cswap(x1, y1, b[1]);
         }
-        i += s * 2;
     }
 }
 
@@ -89,165 +88,167 @@ fn layer_in<const L: usize>(data: &mut [[u64; L]; 2], bits: &[u64; L], lgs: usiz
 /// The C implementation uses 128 as upper bound (because the array contains 128 elements),
 /// but this implementation has 64 elements per subarray and needs case distinctions at different places.
 #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
-fn layer_ex(data: &mut [[u64; 64]; 2], bits: &[u64], lgs: usize) {
-    let mut data0_idx = 0;
-    let mut data1_idx = 32;
-
-    let s = 1 << lgs;
-    if s == 64 {
-        // in this case where `s` has the highest possible value,
-        // we need to access both subarrays in one expression.
-        for j in 0..64 {
-            let mut d = data[0][j + 0] ^ data[1][j];
-            d &= bits[data0_idx];
-            data0_idx += 1;
-
-            data[0][j + 0] ^= d;
-            data[1][j] ^= d;
+fn layer_ex(data: &mut [[u64; 64]; 2], bits: &[u64; 64], lgs: usize) {
+    let [data0, data1] = data;
+
+    if lgs >= 6 {
+        // in this case where `s` has the highest possible value (64),
+        // the pairs consist of one element of each subarray.
+        for ((x, y), &b) in data0.iter_mut().zip(data1.iter_mut()).zip(bits) {
+            cswap(x, y, b);
         }
     } else {
-        // in this case, we can run computations in both subarrays consecutively
-        // within one iteration of loop over `j`
-        let mut i: usize = 0;
-        while i < 64 {
-            for j in i..(i + s) {
-                // data[0] computations
-                let mut d = data[0][j + 0] ^ data[0][j + s];
-                d &= bits[data0_idx];
-                data0_idx += 1;
-
-                data[0][j + 0] ^= d;
-                data[0][j + s] ^= d;
-
-                // data[1] computations
-                d = data[1][j + 0] ^ data[1][j + s];
-                d &= bits[data1_idx];
-                data1_idx += 1;
-
-                data[1][j + 0] ^= d;
-                data[1][j + s] ^= d;
-            }
-            i += s * 2;
-        }
+        // in this case, both subarrays are independent layers
+        // with the first and second half of `bits` respectively
+        let (bits0, bits1) = bits.split_at(32);
+        layer(data0, bits0, lgs);
+        layer(data1, bits1, lgs);
     }
 }
 
-/// Apply Beneš network in-place to array `r` based on configuration `bits` and `rev`.
-/// Here, `r` is a sequence of bits to be permuted.
-/// `bits` defines the condition bits configuring the Beneš network and
-/// `rev` toggles between normal application (0) or its inverse (!0).
-#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
-fn apply_benes(
-    r: &mut [u8; 512],
-    bits: &[u8; COND_BYTES],
-    rev: usize,
-) -> Result<(), Box<dyn error::Error>> {
-    let mut bs = [0u64; 64];
-    let mut cond = [0u64; 64];
+/// The number of layers of the Beneš network, each configured by `COND_BYTES / LAYERS` bytes of the control bits
+const LAYERS: usize = 2 * GFBITS - 1;
 
-    if rev == 0 {
-        for i in 0..64 {
-            bs[i] = util::load8(sub!(r, i * 8, 8));
-        }
+/// The number of outer layers at either end of the network. Their condition bits are transposed,
+/// like the bits of `r` they are applied to.
+#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+const OUTER: usize = 6;
+/// The number of outer layers at either end of the network. Their condition bits are transposed,
+/// like the bits of `r` they are applied to.
+#[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
+const OUTER: usize = 7;
 
-        transpose::transpose_64x64_inplace(&mut bs);
+/// Whether layer `k` is one of the outer layers
+fn is_outer(k: usize) -> bool {
+    !(OUTER..LAYERS - OUTER).contains(&k)
+}
 
-        for low in 0..6 {
-            for i in 0..64 {
-                cond[i] = util::load4(sub!(bits, low * 256 + i * 4, 4)) as u64;
-            }
-            transpose::transpose_64x64_inplace(&mut cond);
-            layer(&mut bs, &cond, low);
-        }
+/// The parameter `lgs` of each layer (see `layer`). The network is symmetric,
+/// hence the inverse network applies the same values to the layers in reverse order.
+const LGS: [usize; LAYERS] = {
+    let mut lgs = [0; LAYERS];
+    let mut i = 0;
+    while i < LAYERS {
+        lgs[i] = if i < OUTER {
+            i
+        } else if i >= LAYERS - OUTER {
+            LAYERS - 1 - i
+        } else if i - OUTER < 6 {
+            i - OUTER
+        } else {
+            OUTER + 10 - i
+        };
+        i += 1;
+    }
+    lgs
+};
+
+/// The condition bits of a Beneš network, i.e. the control bits of the secret key
+/// or the words of all layers computed from them (see `Network`)
+pub(crate) trait Conditions {
+    /// Returns the condition words of layer `k` as applied by `apply_benes`, computed in `buf` if necessary
+    fn layer<'a>(&'a self, k: usize, buf: &'a mut [u64; 64]) -> Result<&'a [u64; 64], Error>;
+}
 
-        transpose::transpose_64x64_inplace(&mut bs);
+impl Conditions for [u8; COND_BYTES] {
+    fn layer<'a>(&'a self, k: usize, buf: &'a mut [u64; 64]) -> Result<&'a [u64; 64], Error> {
+        let block = self
+            .chunks_exact(COND_BYTES / LAYERS)
+            .nth(k)
+            .ok_or(Error::Internal(
+                "Beneš network without enough condition bits",
+            ))?;
+        load_layer(buf, block, is_outer(k));
+        Ok(buf)
+    }
+}
 
-        for low in 0..6 {
-            for i in 0..32 {
-                cond[i] = util::load8(sub!(bits, (low + 6) * 256 + i * 8, 8));
-            }
-            layer(&mut bs, &cond, low);
+/// Loads the condition words of one layer from its `block` of the control bits
+#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+fn load_layer(out: &mut [u64; 64], block: &[u8], outer: bool) {
+    if outer {
+        for (w, chunk) in out.iter_mut().zip(block.chunks_exact(4)) {
+            *w = util::load8_partial(chunk);
         }
-        for low in (0..5).rev() {
-            for i in 0..32 {
-                cond[i] = util::load8(sub!(bits, (4 - low + 6 + 6) * 256 + i * 8, 8));
-            }
-            layer(&mut bs, &cond, low);
+        transpose::transpose_64x64_inplace(out);
+    } else {
+        for (w, chunk) in out.iter_mut().zip(block.chunks_exact(8)) {
+            *w = util::load8_partial(chunk);
         }
+    }
+}
 
-        transpose::transpose_64x64_inplace(&mut bs);
-
-        for low in (0..6).rev() {
-            for i in 0..64 {
-                cond[i] = util::load4(sub!(bits, (5 - low + 6 + 6 + 5) * 256 + i * 4, 4)) as u64;
-            }
-            transpose::transpose_64x64_inplace(&mut cond);
-            layer(&mut bs, &cond, low);
-        }
+/// Loads the condition words of one layer from its `block` of the control bits
+#[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
+fn load_layer(out: &mut [u64; 64], block: &[u8], outer: bool) {
+    for (w, chunk) in out.iter_mut().zip(block.chunks_exact(8)) {
+        *w = util::load8_partial(chunk);
+    }
+    if outer {
+        transpose::transpose_64x64_inplace(out);
+    }
+}
 
-        transpose::transpose_64x64_inplace(&mut bs);
+/// The condition words of all layers of a Beneš network, computed from its control bits once.
+/// Applying the network with them loads and transposes no condition bits, which is most of the work
+/// of applying it with the control bits. They take `512 · (2m - 1)` bytes.
+#[derive(Zeroable)]
+pub(crate) struct Network {
+    layers: [[u64; 64]; LAYERS],
+}
 
-        for i in 0..64 {
-            util::store8(sub!(mut r, i * 8, 8), bs[i]);
-        }
-    } else {
-        for i in 0..64 {
-            bs[i] = util::load8(sub!(r, i * 8, 8));
+impl Network {
+    /// Computes the condition words of the network with control bits `c` in place
+    pub(crate) fn load(&mut self, c: &[u8; COND_BYTES]) -> Result<(), Error> {
+        for (k, layer) in self.layers.iter_mut().enumerate() {
+            let mut buf = [0u64; 64];
+            *layer = *c.layer(k, &mut buf)?;
         }
+        Ok(())
+    }
+}
 
-        transpose::transpose_64x64_inplace(&mut bs);
+impl Conditions for Network {
+    fn layer<'a>(&'a self, k: usize, _buf: &'a mut [u64; 64]) -> Result<&'a [u64; 64], Error> {
+        self.layers.get(k).ok_or(Error::Internal(
+            "Beneš network without enough condition bits",
+        ))
+    }
+}
 
-        for low in 0..6 {
-            for i in 0..64 {
-                cond[i] =
-                    util::load4(sub!(bits, (2 * GFBITS - 2) * 256 - low * 256 + i * 4, 4)) as u64;
-            }
-            transpose::transpose_64x64_inplace(&mut cond);
-            layer(&mut bs, &cond, low);
-        }
+#[cfg(feature = "zeroize")]
+impl Drop for Network {
+    fn drop(&mut self) {
+        self.layers.zeroize();
+    }
+}
 
-        transpose::transpose_64x64_inplace(&mut bs);
-
-        for low in 0..6 {
-            for i in 0..32 {
-                cond[i] = util::load8(sub!(
-                    bits,
-                    (2 * GFBITS - 2 - 6) * 256 - low * 256 + i * 8,
-                    8
-                ));
-            }
-            layer(&mut bs, &cond, low);
-        }
-        for low in (0..5).rev() {
-            for i in 0..32 {
-                cond[i] = util::load8(sub!(
-                    bits,
-                    (2 * GFBITS - 2 - 6 - 6) * 256 - (4 - low) * 256 + i * 8,
-                    8
-                ));
-                layer(&mut bs, &cond, low);
-            }
-        }
+/// Apply Beneš network in-place to array `r` based on configuration `bits` and `rev`.
+/// Here, `r` is a sequence of bits to be permuted.
+/// `bits` defines the condition bits configuring the Beneš network and
+/// `rev` toggles between normal application (0) or its inverse (!0).
+#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+fn apply_benes(r: &mut [u8; 512], bits: &impl Conditions, rev: usize) -> Result<(), Error> {
+    let mut bs = [0u64; 64];
+    let mut buf = [0u64; 64];
+
+    for (b, chunk) in bs.iter_mut().zip(r.chunks_exact(8)) {
+        *b = util::load8(sub!(chunk, 0, 8));
+    }
 
-        transpose::transpose_64x64_inplace(&mut bs);
-
-        for low in (0..6).rev() {
-            for i in 0..64 {
-                cond[i] = util::load4(sub!(
-                    bits,
-                    (2 * GFBITS - 2 - 6 - 6 - 5) * 256 - (5 - low) * 256 + i * 4,
-                    4
-                )) as u64;
-            }
-            transpose::transpose_64x64_inplace(&mut cond);
-            layer(&mut bs, &cond, low);
+    // the outer layers swap bits within words, which the transposition turns into swaps of words
+    for (i, &lgs) in LGS.iter().enumerate() {
+        if i == 0 || i == OUTER || i == LAYERS - OUTER {
+            transpose::transpose_64x64_inplace(&mut bs);
         }
+        let k = if rev == 0 { i } else { LAYERS - 1 - i };
+        layer(&mut bs, bits.layer(k, &mut buf)?, lgs);
+    }
 
-        transpose::transpose_64x64_inplace(&mut bs);
+    transpose::transpose_64x64_inplace(&mut bs);
 
-        for i in 0..64 {
-            util::store8(sub!(mut r, i * 8, 8), bs[i]);
-        }
+    for (b, chunk) in bs.iter().zip(r.chunks_exact_mut(8)) {
+        util::store8(sub!(mut chunk, 0, 8), *b);
     }
 
     Ok(())
@@ -258,134 +259,145 @@ fn apply_benes(
 /// `bits` defines the condition bits configuring the Beneš network and
 /// `rev` toggles between normal application (0) or its inverse (!0).
 #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
-fn apply_benes(
-    r: &mut [u8; 1024],
-    bits: &[u8; COND_BYTES],
-    rev: usize,
-) -> Result<(), Box<dyn error::Error>> {
+fn apply_benes(r: &mut [u8; 1024], bits: &impl Conditions, rev: usize) -> Result<(), Error> {
     let mut r_int_v = [[0u64; 64]; 2];
     let mut r_int_h = [[0u64; 64]; 2];
-    let mut b_int_v = [0u64; 64];
-    let mut b_int_h = [0u64; 64];
-
-    let mut calc_index = if rev == 0 { 0 } else { 12288 };
-
-    for (i, chunk) in r.chunks(16).enumerate() {
-        r_int_v[0][i] = util::load8(sub!(chunk, 0, 8));
-        r_int_v[1][i] = util::load8(sub!(chunk, 8, 8));
+    let mut buf = [0u64; 64];
+
+    let [r_int_v0, r_int_v1] = &mut r_int_v;
+    for ((v0, v1), chunk) in r_int_v0
+        .iter_mut()
+        .zip(r_int_v1.iter_mut())
+        .zip(r.chunks_exact(16))
+    {
+        *v0 = util::load8(sub!(chunk, 0, 8));
+        *v1 = util::load8(sub!(chunk, 8, 8));
     }
 
-    transpose::transpose(&mut r_int_h[0], r_int_v[0]);
-    transpose::transpose(&mut r_int_h[1], r_int_v[1]);
-
-    for iter in 0..=6 {
-        for (i, chunk) in bits[calc_index..(calc_index + 512)].chunks(8).enumerate() {
-            b_int_v[i] = util::load8(sub!(chunk, 0, 8));
+    // the outer layers (with transposed condition bits) apply to the transposed bits of `r`
+    for (i, &lgs) in LGS.iter().enumerate() {
+        if i == 0 || i == LAYERS - OUTER {
+            transpose::transpose(&mut r_int_h[0], r_int_v[0]);
+            transpose::transpose(&mut r_int_h[1], r_int_v[1]);
+        } else if i == OUTER {
+            transpose::transpose(&mut r_int_v[0], r_int_h[0]);
+            transpose::transpose(&mut r_int_v[1], r_int_h[1]);
         }
 
-        calc_index = if rev == 0 {
-            calc_index + 512
+        let k = if rev == 0 { i } else { LAYERS - 1 - i };
+        let cond = bits.layer(k, &mut buf)?;
+        if is_outer(i) {
+            layer_ex(&mut r_int_h, cond, lgs);
         } else {
-            calc_index - 512
-        };
-
-        transpose::transpose(&mut b_int_h, b_int_v);
-
-        layer_ex(&mut r_int_h, &mut b_int_h, iter);
+            layer_in(&mut r_int_v, cond, lgs);
+        }
     }
 
     transpose::transpose(&mut r_int_v[0], r_int_h[0]);
     transpose::transpose(&mut r_int_v[1], r_int_h[1]);
 
-    for iter in 0..=5 {
-        for (i, chunk) in bits[calc_index..(calc_index + 512)].chunks(8).enumerate() {
-            b_int_v[i] = util::load8(sub!(chunk, 0, 8));
-        }
-
-        calc_index = if rev == 0 {
-            calc_index + 512
-        } else {
-            calc_index - 512
-        };
-
-        layer_in(&mut r_int_v, &mut b_int_v, iter);
+    for ((v0, v1), chunk) in r_int_v[0]
+        .iter()
+        .zip(r_int_v[1].iter())
+        .zip(r.chunks_exact_mut(16))
+    {
+        util::store8(sub!(mut chunk, 0, 8), *v0);
+        util::store8(sub!(mut chunk, 8, 8), *v1);
     }
 
-    for iter in (0..=4).rev() {
-        for (i, chunk) in bits[calc_index..(calc_index + 512)].chunks(8).enumerate() {
-            b_int_v[i] = util::load8(sub!(chunk, 0, 8));
-        }
-        calc_index = if rev == 0 {
-            calc_index + 512
-        } else {
-            calc_index - 512
-        };
-
-        layer_in(&mut r_int_v, &mut b_int_v, iter);
-    }
-
-    transpose::transpose(&mut r_int_h[0], r_int_v[0]);
-    transpose::transpose(&mut r_int_h[1], r_int_v[1]);
+    Ok(())
+}
 
-    for iter in (0..=6).rev() {
-        for (i, chunk) in bits[calc_index..(calc_index + 512)].chunks(8).enumerate() {
-            b_int_v[i] = util::load8(sub!(chunk, 0, 8));
-        }
-        // NOTE the second condition prevents a trailing integer underflow
-        //      (recognize that calc_index is not used after the last subtraction)
-        calc_index = if rev == 0 || iter == 0 {
-            calc_index + 512
-        } else {
-            calc_index - 512
-        };
+/// Computes the support `s` (i.e. the first `SYS_N` field elements permuted by the
+/// Beneš network with control bits `c`)
+#[cfg(any(test, not(feature = "decap-only")))]
+pub(crate) fn support_gen(s: &mut [Gf; SYS_N], c: &[u8; COND_BYTES]) -> Result<(), Error> {
+    let mut full = [0 as Gf; 1 << GFBITS];
+    permuted_field_elements(&mut full, c)?;
 
-        transpose::transpose(&mut b_int_h, b_int_v);
+    s.copy_from_slice(&full[..SYS_N]);
 
-        layer_ex(&mut r_int_h, &mut b_int_h, iter);
-    }
+    Ok(())
+}
 
-    transpose::transpose(&mut r_int_v[0], r_int_h[0]);
-    transpose::transpose(&mut r_int_v[1], r_int_h[1]);
+/// Computes the permutation `pi` encoded by control bits `c`,
+/// i.e. the inverse of `controlbitsfrompermutation`
+#[cfg(not(feature = "decap-only"))]
+pub(crate) fn permutation_from_controlbits(
+    pi: &mut [i16; 1 << GFBITS],
+    c: &[u8; COND_BYTES],
+) -> Result<(), Error> {
+    let mut full = [0 as Gf; 1 << GFBITS];
+    permuted_field_elements(&mut full, c)?;
 
-    for (i, chunk) in r.chunks_mut(16).enumerate() {
-        util::store8(sub!(mut chunk, 0, 8), r_int_v[0][i]);
-        util::store8(sub!(mut chunk, 8, 8), r_int_v[1][i]);
+    for i in 0..(1 << GFBITS) {
+        pi[i] = util::bitrev(full[i]) as i16;
     }
 
     Ok(())
 }
 
-pub(crate) fn support_gen(
-    s: &mut [Gf; SYS_N],
-    c: &[u8; COND_BYTES],
-) -> Result<(), Box<dyn error::Error>> {
-    let mut a: Gf;
-    let mut l = [[0u8; (1 << GFBITS) / 8]; GFBITS];
+/// Applies the inverse of the Beneš network with control bits `c` to the `2^GFBITS` bits of `r`.
+/// A bit vector indexed by the support (see `support_gen`, padded with zeros) is thus indexed
+/// by the field elements in bit-reversed order (see `fft`).
+pub(crate) fn unpermute_bits(
+    r: &mut [u8; (1 << GFBITS) / 8],
+    c: &impl Conditions,
+) -> Result<(), Error> {
+    apply_benes(r, c, 1)
+}
 
-    for i in 0..(1 << GFBITS) {
-        a = util::bitrev(i as Gf);
+/// Applies the Beneš network with control bits `c` to the `2^GFBITS` bits of `r`, i.e. the inverse of
+/// `unpermute_bits`. A bit vector indexed by the field elements in bit-reversed order is thus indexed
+/// by the support.
+pub(crate) fn permute_bits(
+    r: &mut [u8; (1 << GFBITS) / 8],
+    c: &impl Conditions,
+) -> Result<(), Error> {
+    apply_benes(r, c, 0)
+}
 
-        for j in 0..GFBITS {
-            l[j][i / 8] |= (((a >> j) & 1) << (i % 8)) as u8;
-        }
+/// All field elements in bit-reversed order (computed at compile time)
+#[cfg(any(test, not(feature = "decap-only")))]
+const BITREV_ELEMENTS: [Gf; 1 << GFBITS] = {
+    let mut elements = [0 as Gf; 1 << GFBITS];
+    let mut i = 0;
+    while i < 1 << GFBITS {
+        elements[i] = util::bitrev(i as Gf);
+        i += 1;
     }
+    elements
+};
+
+/// Applies the Beneš network with control bits `c` to all field elements (in bit-reversed order)
+#[cfg(any(test, not(feature = "decap-only")))]
+fn permuted_field_elements(s: &mut [Gf; 1 << GFBITS], c: &[u8; COND_BYTES]) -> Result<(), Error> {
+    *s = BITREV_ELEMENTS;
+
+    permute(s, c)
+}
+
+/// Permutes the `2^GFBITS` field elements `s` with the Beneš network configured by control bits `c`.
+/// Applied to the field elements in bit-reversed order, this yields the support (see `support_gen`).
+///
+/// The network permutes the bit planes (bit `j` of all elements) independently. They are processed
+/// one at a time, so that only one plane of `2^GFBITS` bits is stored besides `s`. The condition words
+/// are computed once for all planes (see `Network`), instead of being loaded and transposed per plane.
+#[cfg(any(test, not(feature = "decap-only")))]
+pub(crate) fn permute(s: &mut [Gf; 1 << GFBITS], c: &[u8; COND_BYTES]) -> Result<(), Error> {
+    let mut plane = [0u8; (1 << GFBITS) / 8];
+    let mut net = Network::zeroed();
+    net.load(c)?;
 
     for j in 0..GFBITS {
-        #[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
-        {
-            apply_benes(&mut l[j], c, 0)?;
-        }
-        #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
-        {
-            apply_benes(&mut l[j], c, 0)?;
+        for (w, chunk) in plane.chunks_exact_mut(8).zip(s.chunks_exact(64)) {
+            util::store8(sub!(mut w, 0, 8), util::pack_bits(chunk, j));
         }
-    }
 
-    for i in 0..SYS_N {
-        s[i] = 0;
-        for j in (0..=(GFBITS - 1)).rev() {
-            s[i] <<= 1;
-            s[i] |= ((l[j][i / 8] >> (i % 8)) & 1) as u16;
+        apply_benes(&mut plane, &net, 0)?;
+
+        for (w, chunk) in plane.chunks_exact(8).zip(s.chunks_exact_mut(64)) {
+            util::unpack_bits(chunk, j, util::load8(sub!(w, 0, 8)));
         }
     }
 
@@ -397,6 +409,7 @@ mod tests {
     use super::*;
     use crate::api::CRYPTO_PRIMITIVE;
     use std::convert::TryFrom;
+    use std::error;
 
     fn get(name: &str) -> Vec<u64> {
         let fullname = format!("{}_{}", CRYPTO_PRIMITIVE, name);
@@ -407,16 +420,15 @@ mod tests {
         <[u64; 64]>::try_from(get(name).as_slice()).unwrap()
     }
 
-    #[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
     #[test]
     fn test_layer() {
         let mut data = [0u64; 64];
         let mut bits = [0u64; 32];
-        for i in 0..64 {
-            data[i] = 0xAAAA ^ (i as u64 * 17);
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = 0xAAAA ^ (i as u64 * 17);
         }
-        for i in 0..32 {
-            bits[i] = (i as u64) << 3;
+        for (i, b) in bits.iter_mut().enumerate() {
+            *b = (i as u64) << 3;
         }
         layer(&mut data, &bits, 4);
         assert_eq!(
@@ -469,12 +481,13 @@ mod tests {
         let mut data = [[0u64; 64]; 2];
         let mut bits = [0u64; 64];
 
-        for i in 0..64 {
-            data[0][i] = 0xFC81 ^ (i as u64 * 17);
-            data[1][i] = 0x9837 ^ (i as u64 * 3);
+        let [low, high] = &mut data;
+        for (i, (l, h)) in low.iter_mut().zip(high.iter_mut()).enumerate() {
+            *l = 0xFC81 ^ (i as u64 * 17);
+            *h = 0x9837 ^ (i as u64 * 3);
         }
-        for i in 0..64 {
-            bits[i] = (i as u64) << 3;
+        for (i, b) in bits.iter_mut().enumerate() {
+            *b = (i as u64) << 3;
         }
         layer_ex(&mut data, &bits, 5);
 
@@ -537,4 +550,46 @@ mod tests {
         assert_eq!(actual_r, expected_r);
         Ok(())
     }
+
+    #[test]
+    fn test_unpermute_bits() -> Result<(), Error> {
+        let mut c = [0u8; COND_BYTES];
+        for (i, ci) in c.iter_mut().enumerate() {
+            *ci = (i * 89 + 7) as u8;
+        }
+        let mut r = [0u8; (1 << GFBITS) / 8];
+        for (i, ri) in r.iter_mut().enumerate() {
+            *ri = (i * 37 + 101) as u8;
+        }
+
+        let expected = r;
+        permute_bits(&mut r, &c)?;
+        unpermute_bits(&mut r, &c)?;
+        assert_eq!(r, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network() -> Result<(), Error> {
+        let mut c = [0u8; COND_BYTES];
+        for (i, ci) in c.iter_mut().enumerate() {
+            *ci = (i * 53 + 11) as u8;
+        }
+        let mut net: Box<Network> = bytemuck::zeroed_box();
+        net.load(&c)?;
+
+        let mut r = [0u8; (1 << GFBITS) / 8];
+        for (i, ri) in r.iter_mut().enumerate() {
+            *ri = (i * 29 + 3) as u8;
+        }
+        for rev in [0, 1] {
+            let (mut expected, mut actual) = (r, r);
+            apply_benes(&mut expected, &c, rev)?;
+            apply_benes(&mut actual, &*net, rev)?;
+            assert_eq!(actual, expected);
+        }
+
+        Ok(())
+    }
 }
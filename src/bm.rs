@@ -1,87 +1,207 @@
 //! This file implements the Berlekamp-Massey algorithm
 //! see <http://crypto.stanford.edu/~mironov/cs359/massey.pdf>
 
-use crate::gf::{gf_frac, gf_mul, Gf};
-use crate::params::SYS_T;
-
-fn min(a: usize, b: usize) -> usize {
-    let c = (a < b) as isize;
-    let d = c << (isize::BITS - 1);
-    let e = (d >> (isize::BITS - 1)) as usize;
-    (a & e) | (b & !e)
+use crate::ct::{bit_mask, le_mask, nonzero_mask};
+use crate::gf::{gf_frac, Gf};
+use crate::params::{GFBITS, SYS_T};
+use crate::vec_gf::{vec_mul, vec_unpack, VecGf};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// The number of bitsliced blocks of 64 coefficients of a polynomial of degree `SYS_T`
+const BLOCKS: usize = (SYS_T + 1).div_ceil(64);
+
+/// The lanes of the last block that hold coefficients of degree at most `SYS_T`
+const LAST_LANES: u64 = u64::MAX >> (64 * BLOCKS - (SYS_T + 1));
+
+/// A polynomial of degree at most `SYS_T` in bitsliced form, coefficient `64 * k + j` in lane `j` of block `k`
+type VecPoly = [VecGf; BLOCKS];
+
+/// Returns `a` in all 64 lanes
+fn splat(a: Gf) -> VecGf {
+    let mut out = [0u64; GFBITS];
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = bit_mask(a as u64, i);
+    }
+    out
+}
+
+/// Multiplies `p` by x, i.e. moves each coefficient to the next lane, and adds `a` as the constant coefficient.
+/// The coefficient of degree `SYS_T` is dropped.
+fn shift_up(p: &mut VecPoly, a: Gf) {
+    for i in 0..GFBITS {
+        let mut carry = ((a >> i) & 1) as u64;
+        for block in p.iter_mut() {
+            let w = block[i];
+            block[i] = (w << 1) | carry;
+            carry = w >> 63;
+        }
+    }
+
+    if let Some(last) = p.last_mut() {
+        for w in last.iter_mut() {
+            *w &= LAST_LANES;
+        }
+    }
 }
 
 /// The Berlekamp-Massey algorithm.
 /// Uses `s` as input (sequence of field elements)
 /// and `out` as output (minimal polynomial of `s`)
+///
+/// The polynomials are bitsliced (see `vec_gf`), thus each iteration processes all coefficients at once
+/// with `vec_mul`, like the `vec` implementation of the submission. The discrepancy, the sum of the products
+/// of the coefficients of `c` and the last syndromes, is the parity of each bit of the products of all lanes.
 pub(crate) fn bm(out: &mut [Gf; SYS_T + 1], s: &mut [Gf; 2 * SYS_T]) {
     let mut l: u16 = 0;
-    let mut mle: u16;
-    let mut mne: u16;
 
-    let mut t = [0u16; SYS_T + 1];
-    let mut c = [0u16; SYS_T + 1];
-    let mut b = [0u16; SYS_T + 1];
+    let mut t: VecPoly = [[0u64; GFBITS]; BLOCKS];
+    let mut c: VecPoly = [[0u64; GFBITS]; BLOCKS];
+    let mut b: VecPoly = [[0u64; GFBITS]; BLOCKS];
+    // s[n - i] in lane i, the syndromes multiplied with the coefficients c[i]
+    let mut window: VecPoly = [[0u64; GFBITS]; BLOCKS];
+    let mut prod: VecGf = [0u64; GFBITS];
 
     let mut base: Gf = 1;
 
-    b[1] = 1;
-    c[0] = 1;
+    // b = x, c = 1
+    b[0][0] = 1 << 1;
+    c[0][0] = 1;
 
-    for n in 0..(2 * SYS_T) {
-        let mut d: Gf = 0;
-        for i in 0..=min(n, SYS_T) {
-            d ^= gf_mul(c[i], s[n - i]);
+    for (n, &sn) in s.iter().enumerate() {
+        shift_up(&mut window, sn);
+
+        prod.fill(0);
+        for (ck, wk) in c.iter().zip(window.iter()) {
+            for (p, q) in prod.iter_mut().zip(vec_mul(ck, wk).iter()) {
+                *p ^= q;
+            }
         }
-        mne = d;
-        mne = mne.wrapping_sub(1);
-        mne >>= 15;
-        mne = mne.wrapping_sub(1);
-
-        mle = n as u16;
-        mle = mle.wrapping_sub(l.wrapping_mul(2));
-        mle >>= 15;
-        mle = mle.wrapping_sub(1);
-        mle &= mne;
-
-        for i in 0..=SYS_T {
-            t[i] = c[i];
+
+        let mut d: Gf = 0;
+        for (i, &p) in prod.iter().enumerate() {
+            d |= ((p.count_ones() & 1) as Gf) << i;
         }
 
-        let f: Gf = gf_frac(base, d);
+        let mne_w = nonzero_mask(d as u64);
+        let mle_w = le_mask(2 * l as u64, n as u64) & mne_w;
+        let mle = mle_w as u16;
+
+        t.copy_from_slice(&c);
+
+        let f = splat(gf_frac(base, d));
 
-        for i in 0..=SYS_T {
-            c[i] ^= gf_mul(f, b[i]) & mne;
+        for (ck, bk) in c.iter_mut().zip(b.iter()) {
+            for (x, y) in ck.iter_mut().zip(vec_mul(&f, bk).iter()) {
+                *x ^= y & mne_w;
+            }
         }
 
-        l = (l & !mle) | ((n as u16 + 1 - l) & mle);
+        l = (l & !mle) | ((n as u16).wrapping_add(1).wrapping_sub(l) & mle);
 
-        for i in 0..=SYS_T {
-            b[i] = (b[i] & !mle) | (t[i] & mle);
+        for (bk, tk) in b.iter_mut().zip(t.iter()) {
+            for (x, &y) in bk.iter_mut().zip(tk.iter()) {
+                *x = (*x & !mle_w) | (y & mle_w);
+            }
         }
 
         base = (base & !mle) | (d & mle);
 
-        for i in (1..=SYS_T).rev() {
-            b[i] = b[i - 1];
-        }
+        shift_up(&mut b, 0);
+    }
+
+    let mut coefs = [0 as Gf; 64 * BLOCKS];
+    for (chunk, ck) in coefs.chunks_exact_mut(64).zip(c.iter()) {
+        vec_unpack(ck, chunk);
+    }
 
-        b[0] = 0;
+    for (o, &ci) in out.iter_mut().zip(coefs[..=SYS_T].iter().rev()) {
+        *o = ci;
     }
 
-    for i in 0..=SYS_T {
-        out[i] = c[SYS_T - i];
+    #[cfg(feature = "zeroize")]
+    {
+        t.zeroize();
+        c.zeroize();
+        b.zeroize();
+        window.zeroize();
+        prod.zeroize();
+        coefs.zeroize();
     }
 }
 
 #[cfg(test)]
-#[cfg(feature = "mceliece8192128f")]
 mod tests {
     use super::*;
+    use crate::gf::gf_mul;
+    #[cfg(feature = "mceliece8192128f")]
     use crate::macros::sub;
+    #[cfg(feature = "mceliece8192128f")]
     use std::error;
 
+    /// The Berlekamp-Massey algorithm of the reference implementation, one coefficient at a time
+    fn bm_ref(out: &mut [Gf; SYS_T + 1], s: &[Gf; 2 * SYS_T]) {
+        let mut l: u16 = 0;
+        let mut t = [0u16; SYS_T + 1];
+        let mut c = [0u16; SYS_T + 1];
+        let mut b = [0u16; SYS_T + 1];
+        let mut base: Gf = 1;
+
+        b[1] = 1;
+        c[0] = 1;
+
+        for n in 0..(2 * SYS_T) {
+            let mut d: Gf = 0;
+            for (&ci, &si) in c.iter().zip(s[..=n].iter().rev()) {
+                d ^= gf_mul(ci, si);
+            }
+            let mne = ((d.wrapping_sub(1)) >> 15).wrapping_sub(1);
+            let mle = (((n as u16).wrapping_sub(l.wrapping_mul(2)) >> 15).wrapping_sub(1)) & mne;
+
+            t.copy_from_slice(&c);
+            let f: Gf = gf_frac(base, d);
+            for i in 0..=SYS_T {
+                c[i] ^= gf_mul(f, b[i]) & mne;
+            }
+            l = (l & !mle) | ((n as u16).wrapping_add(1).wrapping_sub(l) & mle);
+            for i in 0..=SYS_T {
+                b[i] = (b[i] & !mle) | (t[i] & mle);
+            }
+            base = (base & !mle) | (d & mle);
+            for i in (1..=SYS_T).rev() {
+                b[i] = b[i - 1];
+            }
+            b[0] = 0;
+        }
+
+        for (o, &ci) in out.iter_mut().zip(c.iter().rev()) {
+            *o = ci;
+        }
+    }
+
+    #[test]
+    fn test_bm_matches_reference() {
+        let mut x = 0x2545F4914F6CDD1Du64;
+        for _ in 0..8 {
+            let mut s = [0u16; 2 * SYS_T];
+            for si in s.iter_mut() {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                *si = (x as usize & crate::params::GFMASK) as Gf;
+            }
+
+            let mut expected = [0u16; SYS_T + 1];
+            bm_ref(&mut expected, &s);
+
+            let mut locator = [0u16; SYS_T + 1];
+            bm(&mut locator, &mut s);
+            assert_eq!(locator, expected);
+        }
+    }
+
     #[test]
+    #[cfg(feature = "mceliece8192128f")]
     fn test_simple_bm() {
         assert_eq!(SYS_T + 1, 129);
 
@@ -101,8 +221,8 @@ mod tests {
         let mut locator = [0u16; SYS_T + 1];
         let mut s = [0u16; SYS_T * 2];
 
-        for i in 0..s.len() {
-            s[i] = i as u16;
+        for (i, x) in s.iter_mut().enumerate() {
+            *x = i as u16;
         }
 
         bm(&mut locator, &mut s);
@@ -111,6 +231,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mceliece8192128f")]
     fn test_first_round_bm() -> Result<(), Box<dyn error::Error>> {
         let compare_array =
             crate::TestData::new().u16vec("mceliece8192128f_bm_first_round_compare_array");
@@ -4,8 +4,8 @@
 //! [^cb]: Daniel J. Bernstein "Verified fast formulas for control bits for permutation networks"
 //!        <https://cr.yp.to/papers/controlbits-20200923.pdf>
 
-use crate::int32_sort::int32_sort;
 use crate::params::GFBITS;
+use crate::sort::{int32_minmax, int32_sort};
 
 // controlbits returns ((2*m - 1) * 2^(m - 1)) control bits for a permutation pi over 2^m indices
 // equals ((2*m - 1) * 2^(m - 1))/8 control bytes, which is what this macro computes.
@@ -16,6 +16,11 @@ macro_rules! CONTROLBYTES {
     };
 }
 
+/// Returns the minimum of `a` and `b` in constant time, since they depend on the (secret) permutation
+fn int32_min(a: i32, b: i32) -> i32 {
+    int32_minmax(a, b).0
+}
+
 /// Layer implements one layer of the Beneš network.
 ///
 /// It permutes elements `p` according to control bits `cb` in-place.
@@ -34,12 +39,12 @@ fn layer(p: &mut [i16], cb: &[u8], s: i32, n: i32) {
 
     for i in (0..n as usize).step_by(stride * 2) {
         for j in 0..stride {
-            let mut d = (p[(i + j) as usize] ^ p[(i + j + stride) as usize]) as i16;
+            let mut d = p[i + j] ^ p[i + j + stride];
             let mut m = ((cb[index >> 3] >> (index & 7)) & 1) as i16;
             m = -m;
             d &= m;
-            p[(i + j) as usize] ^= d as i16;
-            p[(i + j + stride) as usize] ^= d as i16;
+            p[i + j] ^= d;
+            p[i + j + stride] ^= d;
             index += 1;
         }
     }
@@ -63,6 +68,7 @@ fn layer(p: &mut [i16], cb: &[u8], s: i32, n: i32) {
 ///
 /// But the following descriptions still hold true:
 ///   out is filled with (2m-1)n/2 control bits at positions pos, pos+step, …
+#[allow(clippy::too_many_arguments)]
 fn cbrecursion(
     out: &mut [u8],
     mut pos: usize,
@@ -107,16 +113,13 @@ fn cbrecursion(
     for x in 0..n {
         let ax: i32 = temp[x];
         let px: i32 = ax & 0xffff;
-        let mut cx: i32 = px;
-        if (x as i32) < cx {
-            cx = x as i32;
-        }
+        let cx: i32 = int32_min(px, x as i32);
         temp[n + x] = (px << 16) | cx;
     }
     /* B = (p<<16)+c */
 
-    for x in 0..n {
-        temp[x] = (temp[x] << 16) | (x as i32); /* A = (pibar<<16)+id */
+    for (x, a) in temp[..n].iter_mut().enumerate() {
+        *a = (*a << 16) | (x as i32); /* A = (pibar<<16)+id */
     }
     int32_sort(&mut temp[0..n]); /* A = (id<<16)+pibar^-1 */
 
@@ -145,11 +148,8 @@ fn cbrecursion(
 
             for x in 0..n {
                 let ppcpx: i32 = temp[x] & 0xfffff;
-                let mut ppcx: i32 = (temp[x] & 0xffc00) | (temp[n + x] & 0x3ff);
-                if ppcpx < ppcx {
-                    ppcx = ppcpx;
-                }
-                temp[n + x] = ppcx;
+                let ppcx: i32 = (temp[x] & 0xffc00) | (temp[n + x] & 0x3ff);
+                temp[n + x] = int32_min(ppcx, ppcpx);
             }
         }
         for x in 0..n {
@@ -188,9 +188,7 @@ fn cbrecursion(
             /* A = id<<16+cp */
             for x in 0..n {
                 let cpx: i32 = (temp[n + x] & (0xffff << 16)) | (temp[x] & 0xffff);
-                if cpx < temp[n + x] {
-                    temp[n + x] = cpx;
-                }
+                temp[n + x] = int32_min(temp[n + x], cpx);
             }
         }
         for x in 0..n {
@@ -287,10 +285,9 @@ fn cbrecursion(
 pub(crate) fn controlbitsfrompermutation(out: &mut [u8], pi: &[i16], w: usize, n: usize) {
     assert_eq!(n, 1 << w);
     assert_eq!(pi.len(), n);
-    assert_eq!(out.len(), (((2 * w - 1) * n / 2) + 7) / 8);
+    assert_eq!(out.len(), ((2 * w - 1) * n / 2).div_ceil(8));
 
     let mut temp = [0i32; 2 * (1 << GFBITS)];
-    let mut diff: i16 = 0;
 
     // reinterpret pi as i32 array
     assert_eq!(pi.len(), 1 << GFBITS);
@@ -299,28 +296,29 @@ pub(crate) fn controlbitsfrompermutation(out: &mut [u8], pi: &[i16], w: usize, n
         pi_as_i32[i] = pi[2 * i] as i32 | ((pi[2 * i + 1] as i32) << 16);
     }
 
-    let mut sub = out;
-
     loop {
-        sub.fill(0);
-        cbrecursion(sub, 0, 1, 0, w, n, &mut temp, &pi_as_i32);
+        out.fill(0);
+        cbrecursion(out, 0, 1, 0, w, n, &mut temp, &pi_as_i32);
 
         let mut pi_test = [0i16; 1 << GFBITS];
-        for i in 0..n {
-            pi_test[i] = i as i16;
+        for (i, p) in pi_test[..n].iter_mut().enumerate() {
+            *p = i as i16;
         }
 
-        for i in 0..w as usize {
+        // each retry checks all control bits from the start again
+        let mut sub = &out[..];
+        for i in 0..w {
             layer(&mut pi_test, sub, i as i32, n as i32);
-            sub = &mut sub[(n >> 4) as usize..];
+            sub = &sub[(n >> 4)..];
         }
 
         for i in (0..w - 1).rev() {
             layer(&mut pi_test, sub, i as i32, n as i32);
-            sub = &mut sub[(n >> 4) as usize..];
+            sub = &sub[(n >> 4)..];
         }
 
-        for i in 0..n as usize {
+        let mut diff: i16 = 0;
+        for i in 0..n {
             diff |= pi[i] ^ pi_test[i];
         }
 
@@ -334,6 +332,14 @@ pub(crate) fn controlbitsfrompermutation(out: &mut [u8], pi: &[i16], w: usize, n
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_int32_min() {
+        assert_eq!(int32_min(3, 5), 3);
+        assert_eq!(int32_min(5, 3), 3);
+        assert_eq!(int32_min(-1, 0x7fffffff), -1);
+        assert_eq!(int32_min(0x1234 << 16, 0x1234 << 16), 0x1234 << 16);
+    }
+
     // A simple testcase for layer().
     // The input/output mapping was retrieved from the C implementation.
     #[test]
@@ -1,33 +1,33 @@
 //! Hash function implementations (only SHAKE)
+//!
+//! All hashing of the crate runs through this module. SHAKE256 is computed by the `sha3` crate,
+//! whose Keccak permutation is portable Rust by default. With feature `keccak-asm`, it uses the
+//! SHA3 instructions of ARMv8.2 (and later) processors if the CPU supports them (detected at runtime),
+//! which are written in assembly in the `keccak` crate. On other architectures, the feature has no effect.
 
-use std::error;
-use std::fmt;
-use std::io::Read;
-
-use sha3::digest::{ExtendableOutput, Update};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
 use sha3::Shake256;
 
-#[derive(Debug)]
-struct ShakeIOError(String);
-
-impl error::Error for ShakeIOError {}
-
-impl fmt::Display for ShakeIOError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "shake failed to read data: {}", self.0)
-    }
-}
+use crate::error::Error;
 
 /// Utilizes the SHAKE256 hash function. Input and output is of arbitrary length.
-pub(crate) fn shake256(output: &mut [u8], input: &[u8]) -> Result<(), Box<dyn error::Error>> {
+pub(crate) fn shake256(output: &mut [u8], input: &[u8]) -> Result<(), Error> {
     let mut shake_hash_fn = Shake256::default();
     shake_hash_fn.update(input);
 
-    let mut result_shake = shake_hash_fn.finalize_xof();
-    match result_shake.read(output) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(Box::new(ShakeIOError(e.to_string()))),
+    shake_hash_fn.finalize_xof().read(output);
+    Ok(())
+}
+
+/// Utilizes the SHAKE256 hash function on the concatenation of `inputs`.
+pub(crate) fn shake256_concat(output: &mut [u8], inputs: &[&[u8]]) -> Result<(), Error> {
+    let mut shake_hash_fn = Shake256::default();
+    for input in inputs {
+        shake_hash_fn.update(input);
     }
+
+    shake_hash_fn.finalize_xof().read(output);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -48,4 +48,16 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_shake256_concat() -> Result<(), Box<dyn error::Error>> {
+        let mut expected = [0u8; 100];
+        shake256(&mut expected, b"classic mceliece")?;
+
+        let mut output = [0u8; 100];
+        shake256_concat(&mut output, &[b"classic", b" ", b"mceliece"])?;
+        assert_eq!(output, expected);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,143 @@
+//! Constant-time masks and conditional swaps
+//!
+//! Decisions on secret data are computed as masks, which have all bits set or none, and applied
+//! with AND and XOR instead of branches. The masks are 64-bit words; casting them to a smaller
+//! unsigned type keeps them all-ones or zero. Each bit trick is written here once, so that
+//! it can be reviewed once.
+
+use crate::util::load8_partial;
+
+/// Returns `u64::MAX` if `x == y`, else 0
+pub(crate) fn eq_mask(x: u64, y: u64) -> u64 {
+    zero_mask(x ^ y)
+}
+
+/// Returns `u64::MAX` if `x == 0`, else 0
+pub(crate) fn zero_mask(x: u64) -> u64 {
+    !nonzero_mask(x)
+}
+
+/// Returns `u64::MAX` if `x != 0`, else 0
+pub(crate) fn nonzero_mask(x: u64) -> u64 {
+    // the most significant bit of `x | -x` is set iff `x` is not zero
+    0u64.wrapping_sub((x | x.wrapping_neg()) >> 63)
+}
+
+/// Returns `u64::MAX` if bit `i` of `x` is set, else 0
+pub(crate) fn bit_mask(x: u64, i: usize) -> u64 {
+    0u64.wrapping_sub((x >> i) & 1)
+}
+
+/// Returns `u64::MAX` if `x <= y`, else 0. Both values must be less than 2^63.
+pub(crate) fn le_mask(x: u64, y: u64) -> u64 {
+    // `y - x` is negative iff `x > y`
+    (y.wrapping_sub(x) >> 63).wrapping_sub(1)
+}
+
+/// Returns the Hamming weight of `bytes`, accumulated over 64-bit words.
+/// `count_ones` is the POPCNT instruction if the target has it, else a branch-free sequence of shifts,
+/// masks and additions, hence the time only depends on the length of `bytes`.
+pub(crate) fn weight(bytes: &[u8]) -> u32 {
+    let mut words = bytes.chunks_exact(8);
+    let full: u32 = words
+        .by_ref()
+        .map(|word| load8_partial(word).count_ones())
+        .sum();
+    full + load8_partial(words.remainder()).count_ones()
+}
+
+/// Swaps `a` and `b` if `mask` is `u64::MAX`, keeps them if it is 0
+pub(crate) fn cswap(a: &mut u64, b: &mut u64, mask: u64) {
+    let d = (*a ^ *b) & mask;
+    *a ^= d;
+    *b ^= d;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eq_mask() {
+        const EXPECTED: [u64; 25] = [
+            0xFFFFFFFFFFFFFFFF,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0xFFFFFFFFFFFFFFFF,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0xFFFFFFFFFFFFFFFF,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0xFFFFFFFFFFFFFFFF,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0xFFFFFFFFFFFFFFFF,
+        ];
+        for i in 0..5 {
+            for j in 0..5 {
+                assert_eq!(eq_mask(i as u64, j as u64), EXPECTED[i * 5 + j]);
+            }
+        }
+        assert_eq!(eq_mask(u64::MAX, u64::MAX), u64::MAX);
+        assert_eq!(eq_mask(1 << 63, 0), 0);
+    }
+
+    #[test]
+    fn test_zero_mask() {
+        for x in [1, 2, 0xFF, 1 << 62, 1 << 63, u64::MAX] {
+            assert_eq!(zero_mask(x), 0);
+            assert_eq!(nonzero_mask(x), u64::MAX);
+        }
+        assert_eq!(zero_mask(0), u64::MAX);
+        assert_eq!(nonzero_mask(0), 0);
+    }
+
+    #[test]
+    fn test_bit_mask() {
+        assert_eq!(bit_mask(0b1010, 1), u64::MAX);
+        assert_eq!(bit_mask(0b1010, 2), 0);
+        assert_eq!(bit_mask(1 << 63, 63), u64::MAX);
+    }
+
+    #[test]
+    fn test_le_mask() {
+        for (x, y) in [(0, 0), (3, 5), (7, 7), (0, (1 << 63) - 1)] {
+            assert_eq!(le_mask(x, y), u64::MAX);
+        }
+        for (x, y) in [(1, 0), (6, 5), ((1 << 63) - 1, 0)] {
+            assert_eq!(le_mask(x, y), 0);
+        }
+    }
+
+    #[test]
+    fn test_weight() {
+        let bytes: Vec<u8> = (0..437).map(|i| (i * 89 + 7) as u8).collect();
+        for len in [0, 1, 7, 8, 9, 436, 437] {
+            let expected: u32 = bytes[..len].iter().map(|b| b.count_ones()).sum();
+            assert_eq!(weight(&bytes[..len]), expected);
+        }
+        assert_eq!(weight(&[0xFF; 20]), 160);
+    }
+
+    #[test]
+    fn test_cswap() {
+        let (mut a, mut b) = (3, 5);
+        cswap(&mut a, &mut b, 0);
+        assert_eq!((a, b), (3, 5));
+        cswap(&mut a, &mut b, u64::MAX);
+        assert_eq!((a, b), (5, 3));
+    }
+}
@@ -0,0 +1,174 @@
+//! A pool of threads decapsulating with one expanded secret key
+//!
+//! Servers terminating many handshakes (e.g. VPN or TLS terminators) receive ciphertexts in bursts.
+//! A `DecapPool` expands the secret key once (see `SecretKey::expand`) and shares it between its
+//! worker threads, each of which owns the buffer of its decryptions. Ciphertexts are submitted through
+//! a channel and every submission returns a `PendingSecret`, which receives the shared secret.
+
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::error::Error;
+use crate::operations::decapsulate_expanded_in;
+use crate::types::{Ciphertext, ExpandedSecretKey, SharedSecret};
+use crate::workspace::Workspace;
+
+/// A ciphertext and the channel of its shared secret
+struct Job {
+    c: Ciphertext,
+    reply: mpsc::Sender<Result<SharedSecret, Error>>,
+}
+
+/// Worker threads decapsulating submitted ciphertexts with the same expanded secret key.
+///
+/// Dropping the pool finishes the submitted ciphertexts and joins the workers.
+pub struct DecapPool {
+    jobs: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+/// The shared secret of a ciphertext submitted to a `DecapPool`
+pub struct PendingSecret(mpsc::Receiver<Result<SharedSecret, Error>>);
+
+impl DecapPool {
+    /// Starts `workers` threads (at least 1) decapsulating with `esk`.
+    /// Fails with `Error::Io` if a thread cannot be spawned.
+    pub fn new(esk: ExpandedSecretKey, workers: usize) -> Result<Self, Error> {
+        let esk = Arc::new(esk);
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let esk = Arc::clone(&esk);
+                let queue = Arc::clone(&queue);
+                thread::Builder::new()
+                    .name(format!("decap-pool-{}", i))
+                    .spawn(move || {
+                        let mut ws = Workspace::new();
+                        loop {
+                            // the lock is released before the decapsulation
+                            let job = match queue.lock() {
+                                Ok(queue) => queue.recv(),
+                                Err(_) => return,
+                            };
+                            let Ok(job) = job else { return };
+                            let result = decapsulate_expanded_in(&esk, &job.c, ws.buffer());
+                            // the receiver may have dropped its `PendingSecret`
+                            let _ = job.reply.send(result);
+                        }
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            jobs: Some(jobs),
+            workers,
+        })
+    }
+
+    /// Starts one worker per available thread (see `std::thread::available_parallelism`)
+    pub fn with_available_parallelism(esk: ExpandedSecretKey) -> Result<Self, Error> {
+        let workers = thread::available_parallelism().map_or(1, |t| t.get());
+        Self::new(esk, workers)
+    }
+
+    /// The number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues the ciphertext `c` for decapsulation by the next idle worker.
+    ///
+    /// Like `decapsulate_expanded`, invalid ciphertexts result in a pseudo-random shared secret.
+    pub fn submit(&self, c: Ciphertext) -> PendingSecret {
+        let (reply, receiver) = mpsc::channel();
+        if let Some(jobs) = &self.jobs {
+            // if all workers are gone, `reply` is dropped and `wait` reports it
+            let _ = jobs.send(Job { c, reply });
+        }
+        PendingSecret(receiver)
+    }
+
+    /// Decapsulates all ciphertexts `cs` on the workers, returning the shared secrets in the order of `cs`
+    pub fn decapsulate_all(&self, cs: &[Ciphertext]) -> Result<Vec<SharedSecret>, Error> {
+        let pending: Vec<PendingSecret> = cs.iter().map(|c| self.submit(c.clone())).collect();
+        pending.into_iter().map(PendingSecret::wait).collect()
+    }
+}
+
+impl Drop for DecapPool {
+    fn drop(&mut self) {
+        // closing the channel ends the loops of the workers after the queued ciphertexts
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl PendingSecret {
+    /// Blocks until the shared secret has been computed.
+    /// Fails with `Error::Internal` if the worker panicked.
+    pub fn wait(self) -> Result<SharedSecret, Error> {
+        self.0
+            .recv()
+            .unwrap_or(Err(Error::Internal("decapsulation worker panicked")))
+    }
+
+    /// Returns the shared secret if it has been computed already, without blocking
+    pub fn try_wait(&self) -> Option<Result<SharedSecret, Error>> {
+        match self.0.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(Error::Internal("decapsulation worker panicked")))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DecapPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecapPool")
+            .field("workers", &self.workers.len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for PendingSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PendingSecret(…)")
+    }
+}
+
+#[cfg(all(test, not(feature = "decap-only")))]
+mod tests {
+    use super::*;
+    use crate::operations::encapsulate;
+
+    #[test]
+    fn test_decap_pool() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+
+        let encapsulations: Vec<_> = (0..5)
+            .map(|_| encapsulate(pk, &mut rng_state))
+            .collect::<Result<_, _>>()?;
+        let cs: Vec<Ciphertext> = encapsulations.iter().map(|(c, _)| c.clone()).collect();
+
+        let pool = DecapPool::new(sk.expand()?, 2)?;
+        assert_eq!(pool.workers(), 2);
+
+        let pending = pool.submit(cs[0].clone());
+        assert_eq!(pending.wait()?, encapsulations[0].1);
+
+        let secrets = pool.decapsulate_all(&cs)?;
+        assert!(secrets
+            .iter()
+            .zip(&encapsulations)
+            .all(|(ss, (_, expected))| ss == expected));
+        Ok(())
+    }
+}
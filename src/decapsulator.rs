@@ -0,0 +1,82 @@
+//! Abstraction over the holder of a secret key
+//!
+//! Code which only decapsulates can be written against `Decapsulator` instead of `SecretKey`.
+//! Then, secret keys kept in an HSM, a TPM, or a remote service can be used in place of the
+//! software implementation of this crate.
+
+use crate::error::Error;
+use crate::operations::{decapsulate, decapsulate_expanded};
+use crate::types::{Ciphertext, ExpandedSecretKey, SecretKey, SharedSecret};
+
+/// A secret key which decapsulates ciphertexts of the variant of this build
+pub trait Decapsulator {
+    /// The error of a failing decapsulation (e.g. an unreachable device)
+    type Error;
+
+    /// KEM Decapsulation returning the shared secret.
+    ///
+    /// Like `decapsulate`, invalid ciphertexts must be rejected implicitly,
+    /// i.e. result in a pseudo-random shared secret instead of an error.
+    fn decapsulate(&self, c: &Ciphertext) -> Result<SharedSecret, Self::Error>;
+}
+
+/// The software implementation, see `decapsulate`
+impl Decapsulator for SecretKey {
+    type Error = Error;
+
+    fn decapsulate(&self, c: &Ciphertext) -> Result<SharedSecret, Self::Error> {
+        decapsulate(self, c)
+    }
+}
+
+/// The software implementation with the cached tables, see `decapsulate_expanded`
+impl Decapsulator for ExpandedSecretKey {
+    type Error = Error;
+
+    fn decapsulate(&self, c: &Ciphertext) -> Result<SharedSecret, Self::Error> {
+        decapsulate_expanded(self, c)
+    }
+}
+
+#[cfg(all(test, not(feature = "decap-only")))]
+mod tests {
+    use super::*;
+    use crate::operations::encapsulate;
+    use std::cell::Cell;
+
+    /// Backend which counts its decapsulations
+    struct CountingBackend {
+        sk: &'static SecretKey,
+        calls: Cell<usize>,
+    }
+
+    impl Decapsulator for CountingBackend {
+        type Error = &'static str;
+
+        fn decapsulate(&self, c: &Ciphertext) -> Result<SharedSecret, Self::Error> {
+            self.calls.set(self.calls.get() + 1);
+            self.sk.decapsulate(c).map_err(|_| "backend failed")
+        }
+    }
+
+    fn receive<D: Decapsulator>(decapsulator: &D, c: &Ciphertext) -> Option<SharedSecret> {
+        decapsulator.decapsulate(c).ok()
+    }
+
+    #[test]
+    fn test_decapsulator() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let (c, ss_bob) = encapsulate(pk, &mut crate::test_rng(0x2B))?;
+
+        assert_eq!(receive(&**sk, &c), Some(ss_bob.clone()));
+
+        let backend = CountingBackend {
+            sk,
+            calls: Cell::new(0),
+        };
+        assert_eq!(receive(&backend, &c), Some(ss_bob));
+        assert_eq!(backend.calls.get(), 1);
+
+        Ok(())
+    }
+}
@@ -1,90 +1,145 @@
 //! Decryption function to turn ciphertext into a ciphertext using the secret key
 
+use bytemuck::Zeroable;
+
 use crate::{
-    benes::support_gen,
+    benes::{permute_bits, Conditions},
     bm::bm,
-    gf::gf_iszero,
+    ct::{nonzero_mask, weight},
+    error::Error,
+    gf::Gf,
     macros::sub,
-    params::{COND_BYTES, IRR_BYTES, SYND_BYTES, SYS_N, SYS_T},
-    root::root,
-    synd::synd,
+    params::{GFBITS, IRR_BYTES, SYND_BYTES, SYS_N, SYS_T},
+    root::root_bits,
+    synd::{inv_squares, synd, synd_bits},
     util::load_gf,
 };
-use std::error;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
-/// Niederreiter decryption with the Berlekamp decoder.
+/// The secret key in the form used by the decryption, i.e. the inverted squares of the Goppa polynomial g
+/// at all field elements. The control bits of the Beneš network (to move between the order of the support L
+/// and the order of the field elements) are used as they are, hence `decrypt` reads them from the secret key
+/// instead of a copy.
 ///
-/// It takes as input the secret key `sk` and a ciphertext `c`.
-/// It returns an error vector in `e` and the return value indicates success (0) or failure (1)
-pub(crate) fn decrypt(
-    e: &mut [u8; SYS_N / 8],
-    sk: &[u8; IRR_BYTES + COND_BYTES],
-    c: &[u8; SYND_BYTES],
-) -> Result<u8, Box<dyn error::Error>> {
-    let mut t: u16;
-    let mut w: i32 = 0;
-
-    let mut r = [0u8; SYS_N / 8];
-
-    let mut g = [0u16; SYS_T + 1];
-    let mut l = [0u16; SYS_N];
-
-    let mut s = [0u16; SYS_T * 2];
-    let mut s_cmp = [0u16; SYS_T * 2];
-    let mut locator = [0u16; SYS_T + 1];
-    let mut images = [0u16; SYS_N];
-
-    for i in 0..SYND_BYTES {
-        r[i] = c[i];
-    }
-
-    r[SYND_BYTES..SYS_N / 8].fill(0);
+/// Computing the inverted squares evaluates g at all field elements and inverts them, which dominates the cost
+/// of small decryptions. Decrypting several ciphertexts with the same key thus computes them once only.
+#[derive(Zeroable)]
+pub(crate) struct DecryptionKey {
+    inv_sq: [Gf; 1 << GFBITS],
+}
 
-    for (i, chunk) in sk.chunks(2).take(SYS_T).enumerate() {
-        g[i] = load_gf(sub!(chunk, 0, 2));
+impl DecryptionKey {
+    /// Loads g from the secret key segment `sk` of the Goppa polynomial and computes its inverted squares
+    pub(crate) fn new(sk: &[u8; IRR_BYTES]) -> Result<Self, Error> {
+        let mut key = DecryptionKey::zeroed();
+        key.load(sk)?;
+        Ok(key)
     }
-    g[SYS_T] = 1;
-
-    support_gen(&mut l, sub!(sk, IRR_BYTES, COND_BYTES))?;
-
-    synd(&mut s, &mut g, &mut l, &r);
 
-    bm(&mut locator, &mut s);
+    /// Like `new`, but overwrites this key in place (e.g. the key of a `Workspace`)
+    pub(crate) fn load(&mut self, sk: &[u8; IRR_BYTES]) -> Result<(), Error> {
+        let mut g = [0u16; SYS_T + 1];
 
-    root(&mut images, &mut locator, &mut l);
+        for (gi, chunk) in g[..SYS_T].iter_mut().zip(sk.chunks_exact(2)) {
+            *gi = load_gf(sub!(chunk, 0, 2));
+        }
+        g[SYS_T] = 1;
 
-    e[0..SYS_N / 8].fill(0);
+        inv_squares(&mut self.inv_sq, &g);
 
-    for i in 0..SYS_N {
-        t = gf_iszero(images[i]) & 1;
+        #[cfg(feature = "zeroize")]
+        g.zeroize();
 
-        e[i / 8] |= (t << (i % 8)) as u8;
-        w += t as i32;
+        Ok(())
     }
 
-    synd(&mut s_cmp, &mut g, &mut l, e);
-
-    let mut check = w as u16;
-    check ^= SYS_T as u16;
-
-    for i in 0..SYS_T * 2 {
-        check |= s[i] ^ s_cmp[i];
+    /// The inverted squares of g at all field elements in the order of `fft`
+    #[cfg(feature = "internals")]
+    pub(crate) fn inv_squares(&self) -> &[Gf; 1 << GFBITS] {
+        &self.inv_sq
     }
 
-    check = check.wrapping_sub(1);
-    check >>= 15;
+    /// Niederreiter decryption with the Berlekamp decoder.
+    ///
+    /// It takes as input a ciphertext `c` and the control bits `cond` of the secret key
+    /// (or the `Network` prepared from them).
+    /// It returns an error vector in `e` and the return value indicates success (0) or failure (1).
+    /// `buf` is the memory for the summands of the syndromes and the evaluations of the error locator
+    /// (e.g. of a `Workspace`), it is overwritten and zeroized if feature `zeroize` is enabled.
+    ///
+    /// The Beneš network runs twice, on bit vectors only: it moves the received word to the order
+    /// of the field elements and the roots of the error locator back to the order of the support.
+    /// The evaluations of the locator remain in the order of the field elements, and so does
+    /// the syndrome of the roots. It equals the syndrome of the error vector whenever the decryption
+    /// succeeds, because the weight check ensures that all roots are in the support then.
+    pub(crate) fn decrypt(
+        &self,
+        e: &mut [u8; SYS_N / 8],
+        c: &[u8; SYND_BYTES],
+        cond: &impl Conditions,
+        buf: &mut [Gf; 1 << GFBITS],
+    ) -> Result<u8, Error> {
+        let mut r = [0u8; SYS_N / 8];
+
+        let mut s = [0u16; SYS_T * 2];
+        let mut s_cmp = [0u16; SYS_T * 2];
+        let mut locator = [0u16; SYS_T + 1];
+        let mut roots = [0u8; (1 << GFBITS) / 8];
+
+        r[0..SYND_BYTES].copy_from_slice(c);
+
+        r[SYND_BYTES..SYS_N / 8].fill(0);
+
+        synd(&mut s, &self.inv_sq, cond, &r, buf)?;
+
+        bm(&mut locator, &mut s);
+
+        let images = buf;
+        root_bits(&mut roots, &locator, images);
+
+        synd_bits(&mut s_cmp, &self.inv_sq, &roots, images);
+
+        permute_bits(&mut roots, cond)?;
+        e.copy_from_slice(sub!(roots, 0, SYS_N / 8));
+
+        let w = weight(e);
+
+        let mut check = w as u16;
+        check ^= SYS_T as u16;
+
+        for i in 0..SYS_T * 2 {
+            check |= s[i] ^ s_cmp[i];
+        }
+
+        #[cfg(feature = "zeroize")]
+        {
+            s.zeroize();
+            s_cmp.zeroize();
+            locator.zeroize();
+            roots.zeroize();
+            images.zeroize();
+        }
+
+        Ok((nonzero_mask(check as u64) & 1) as u8)
+    }
+}
 
-    Ok((check ^ 1) as u8)
+#[cfg(feature = "zeroize")]
+impl Drop for DecryptionKey {
+    fn drop(&mut self) {
+        self.inv_sq.zeroize();
+    }
 }
 
 #[cfg(test)]
-#[cfg(any(feature = "mceliece8192128", feature = "mceliece8192128f"))]
 mod tests {
     use super::*;
-    use std::error;
+    use crate::params::COND_BYTES;
 
     #[test]
-    fn test_decrypt() -> Result<(), Box<dyn error::Error>> {
+    #[cfg(any(feature = "mceliece8192128", feature = "mceliece8192128f"))]
+    fn test_decrypt() -> Result<(), Error> {
         let sk = crate::TestData::new().u8vec("mceliece8192128f_sk1"); // TODO: sk has wrong size … IRR_BYTES + COND_BYTES required
         let mut c = crate::TestData::new().u8vec("mceliece8192128f_ct1");
         let expected_error_vector = crate::TestData::new().u8vec("mceliece8192128f_decrypt_errvec");
@@ -92,10 +147,11 @@ mod tests {
         let mut actual_error_vector = [0u8; 1 + SYS_N / 8];
         actual_error_vector[0] = 2;
 
-        decrypt(
+        DecryptionKey::new(sub!(sk, 40, IRR_BYTES))?.decrypt(
             sub!(mut actual_error_vector, 1, SYS_N / 8),
-            sub!(sk, 40, IRR_BYTES + COND_BYTES),
             sub!(mut c, 0, SYND_BYTES),
+            sub!(sk, 40 + IRR_BYTES, COND_BYTES),
+            &mut [0; 1 << GFBITS],
         )?;
 
         assert_eq!(
@@ -105,4 +161,42 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_decryption_key_borrows_control_bits() {
+        // the control bits are read from the secret key, not copied into the decryption key
+        assert_eq!(
+            std::mem::size_of::<DecryptionKey>(),
+            std::mem::size_of::<[Gf; 1 << GFBITS]>()
+        );
+    }
+
+    /// The decryption must not panic for any ciphertext (and secret key).
+    /// `no_panic` fails to link if the compiler cannot prove this,
+    /// which requires optimizations (i.e. `cargo test --release`).
+    #[no_panic::no_panic]
+    fn decrypt_without_panic(
+        e: &mut [u8; SYS_N / 8],
+        sk: &[u8; IRR_BYTES + COND_BYTES],
+        c: &[u8; SYND_BYTES],
+    ) -> Result<u8, Error> {
+        DecryptionKey::new(sub!(sk, 0, IRR_BYTES))?.decrypt(
+            e,
+            c,
+            sub!(sk, IRR_BYTES, COND_BYTES),
+            &mut [0; 1 << GFBITS],
+        )
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_decrypt_no_panic() -> Result<(), Error> {
+        let mut e = [0u8; SYS_N / 8];
+        decrypt_without_panic(
+            &mut e,
+            &[0xFFu8; IRR_BYTES + COND_BYTES],
+            &[0xFFu8; SYND_BYTES],
+        )?;
+        Ok(())
+    }
 }
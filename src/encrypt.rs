@@ -1,36 +1,47 @@
 //! Encryption function to compute error vector and syndrome to get ciphertext
 
-use std::error;
-
 use crate::{
-    api::CRYPTO_CIPHERTEXTBYTES,
+    api::{CRYPTO_CIPHERTEXTBYTES, CRYPTO_PUBLICKEYCOLUMNSBYTES},
+    ct::{bit_mask, eq_mask},
+    error::Error,
     macros::sub,
     params::{PK_NROWS, PK_ROW_BYTES, SYND_BYTES, SYS_N, SYS_T},
-    randombytes::RNGState,
-    util::load_gf,
+    sort::uint64_sort,
+    util::{load8_partial, load_gf},
 };
+use rand_core::CryptoRngCore;
+use std::io::Read;
 
-/// Takes two 16-bit integers and determines whether they are equal (u8::MAX) or different (0)
-fn same_mask_u8(x: u16, y: u16) -> u8 {
-    let mut mask = (x ^ y) as u32;
-    mask = mask.wrapping_sub(1);
-    mask = mask.wrapping_shr(31);
-    mask = 0u32.wrapping_sub(mask);
+/// Does `ind` contain some index more than once?
+///
+/// Like the vectorized C implementations, the indices are sorted with a sorting network and adjacent indices
+/// are compared. This takes the same time for all attempts, and fewer comparisons than all pairs.
+fn has_repetition(ind: &[u16; SYS_T]) -> bool {
+    let mut sorted = [0u64; SYS_T];
+    for (s, &i) in sorted.iter_mut().zip(ind) {
+        *s = i as u64;
+    }
+    uint64_sort(&mut sorted);
 
-    (mask & 0xFF) as u8 // ∈ {0, u8::MAX}
+    let mut eq = 0u64;
+    for pair in sorted.windows(2) {
+        eq |= eq_mask(pair[0], pair[1]);
+    }
+
+    eq != 0
 }
 
 /// Generation of `e`, an error vector of weight `t`.
 /// Does not take any input arguments.
 /// If generation of pseudo-random numbers fails, an error is returned.
 #[cfg(not(any(feature = "mceliece8192128", feature = "mceliece8192128f")))]
-fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl RNGState) -> Result<(), Box<dyn error::Error>> {
+pub(crate) fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl CryptoRngCore) -> Result<(), Error> {
     let mut ind = [0u16; SYS_T];
     let mut val = [0u8; SYS_T];
 
     loop {
         let mut bytes = [0u8; SYS_T * 4];
-        rng.randombytes(&mut bytes)?;
+        rng.try_fill_bytes(&mut bytes)?;
 
         let mut nums = [0u16; SYS_T * 2];
         for (i, chunk) in bytes.chunks(2).enumerate() {
@@ -40,12 +51,12 @@ fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl RNGState) -> Result<(), Box<dyn
         // moving and counting indices in the correct range
 
         let mut count = 0;
-        for i in 0..(SYS_T * 2) {
+        for &num in nums.iter() {
             if count >= SYS_T {
                 break;
             }
-            if nums[i] < SYS_N as u16 {
-                ind[count] = nums[i];
+            if num < SYS_N as u16 {
+                ind[count] = num;
                 count += 1;
             }
         }
@@ -54,19 +65,7 @@ fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl RNGState) -> Result<(), Box<dyn
             continue;
         }
 
-        // check for repetition
-
-        let mut eq = 0;
-
-        for i in 1..SYS_T {
-            for j in 0..i {
-                if ind[i] == ind[j] {
-                    eq = 1;
-                }
-            }
-        }
-
-        if eq == 0 {
+        if !has_repetition(&ind) {
             break;
         }
     }
@@ -75,13 +74,13 @@ fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl RNGState) -> Result<(), Box<dyn
         val[j] = 1 << (ind[j] & 7);
     }
 
-    for i in 0..SYS_N / 8 {
-        e[i] = 0;
+    for (i, byte) in e.iter_mut().enumerate() {
+        *byte = 0;
 
         for j in 0..SYS_T {
-            let mask: u8 = same_mask_u8(i as u16, ind[j] >> 3);
+            let mask = eq_mask(i as u64, (ind[j] >> 3) as u64) as u8;
 
-            e[i] |= val[j] & mask;
+            *byte |= val[j] & mask;
         }
     }
 
@@ -92,31 +91,19 @@ fn gen_e(e: &mut [u8; SYS_N / 8], rng: &mut impl RNGState) -> Result<(), Box<dyn
 /// Does not take any input arguments.
 /// If generation of pseudo-random numbers fails, an error is returned.
 #[cfg(any(feature = "mceliece8192128", feature = "mceliece8192128f"))]
-fn gen_e(e: &mut [u8], rng: &mut impl RNGState) -> Result<(), Box<dyn error::Error>> {
+pub(crate) fn gen_e(e: &mut [u8], rng: &mut impl CryptoRngCore) -> Result<(), Error> {
     let mut ind = [0u16; SYS_T];
     let mut bytes = [0u8; SYS_T * 2];
     let mut val = [0u8; SYS_T];
 
     loop {
-        rng.randombytes(&mut bytes)?;
+        rng.try_fill_bytes(&mut bytes)?;
 
         for (i, chunk) in bytes.chunks(2).enumerate() {
             ind[i] = load_gf(sub!(chunk, 0, 2));
         }
 
-        // check for repetition
-
-        let mut eq = 0;
-
-        for i in 1..SYS_T {
-            for j in 0..i {
-                if ind[i] == ind[j] {
-                    eq = 1;
-                }
-            }
-        }
-
-        if eq == 0 {
+        if !has_repetition(&ind) {
             break;
         }
     }
@@ -125,127 +112,214 @@ fn gen_e(e: &mut [u8], rng: &mut impl RNGState) -> Result<(), Box<dyn error::Err
         val[j] = 1 << (ind[j] & 7);
     }
 
-    for i in 0..SYS_N / 8 {
-        e[i] = 0;
+    for (i, byte) in e[..SYS_N / 8].iter_mut().enumerate() {
+        *byte = 0;
 
         for j in 0..SYS_T {
-            let mask: u8 = same_mask_u8(i as u16, ind[j] >> 3);
+            let mask = eq_mask(i as u64, (ind[j] >> 3) as u64) as u8;
 
-            e[i] |= val[j] & mask;
+            *byte |= val[j] & mask;
         }
     }
 
     Ok(())
 }
 
-/// Syndrome computation.
+/// Generation of `e`, an error vector of weight `t`, without rejection sampling.
 ///
-/// Computes syndrome `s` based on public key `pk` and error vector `e`.
-#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-fn syndrome(
-    s: &mut [u8; (PK_NROWS + 7) / 8],
-    pk: &[u8; PK_NROWS * PK_ROW_BYTES],
-    e: &[u8; SYS_N / 8],
-) {
-    let mut row = [0u8; SYS_N / 8];
+/// Each of the `n` positions gets a random 64-bit key, whose least significant bit is set for the first `t`
+/// positions only. Sorting the keys with the sorting network moves these `t` bits to uniformly random positions,
+/// unless two keys share their 63 random bits, which has a probability below 2^-37 and only affects the order.
+/// Unlike `gen_e`, this takes the same time for all random numbers, but it draws 8 · n random bytes.
+/// The error vectors differ from those of `gen_e` for the same random numbers.
+pub(crate) fn gen_e_without_rejection(
+    e: &mut [u8; SYS_N / 8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(), Error> {
+    let mut keys = [0u64; SYS_N];
+    rng.try_fill_bytes(bytemuck::cast_slice_mut(&mut keys))?;
+    for (i, key) in keys.iter_mut().enumerate() {
+        *key = (*key & !1) | (i < SYS_T) as u64;
+    }
+    uint64_sort(&mut keys);
 
-    let mut pk_segment = &pk[..];
-    let tail = PK_NROWS % 8;
+    e.fill(0);
+    for (i, key) in keys.iter().enumerate() {
+        e[i / 8] |= ((key & 1) as u8) << (i % 8);
+    }
 
-    s[0..SYND_BYTES].fill(0);
+    Ok(())
+}
 
-    for i in 0..PK_NROWS {
-        row[0..SYS_N / 8].fill(0);
+/// The bits of `e` after its first `PK_NROWS` bits (the columns of the public key) in 64-bit words
+fn error_words(e: &[u8; SYS_N / 8]) -> [u64; PK_ROW_BYTES.div_ceil(8)] {
+    // not byte-aligned for mceliece6960119
+    let shift = PK_NROWS % 8;
+    let mut e_tail = [0u8; PK_ROW_BYTES];
+    for (j, t) in e_tail.iter_mut().enumerate() {
+        let lo = e.get(PK_NROWS / 8 + j).copied().unwrap_or(0) as u16;
+        let hi = e.get(PK_NROWS / 8 + j + 1).copied().unwrap_or(0) as u16;
+        *t = (((hi << 8) | lo) >> shift) as u8;
+    }
 
-        for j in 0..PK_ROW_BYTES {
-            row[SYS_N / 8 - PK_ROW_BYTES + j] = pk_segment[j];
-        }
+    let mut e_words = [0u64; PK_ROW_BYTES.div_ceil(8)];
+    for (w, chunk) in e_words.iter_mut().zip(e_tail.chunks(8)) {
+        *w = load8_partial(chunk);
+    }
+    e_words
+}
 
-        for j in ((SYS_N / 8 - PK_ROW_BYTES)..SYS_N / 8).rev() {
-            row[j] = (row[j] << tail) | (row[j - 1] >> (8 - tail));
-        }
+/// Sets `s` to the syndrome of the identity part of H, i.e. the first `PK_NROWS` bits of `e`
+fn identity_syndrome(s: &mut [u8; PK_NROWS.div_ceil(8)], e: &[u8; SYS_N / 8]) {
+    let shift = PK_NROWS % 8;
+    s.copy_from_slice(&e[..SYND_BYTES]);
+    if shift != 0 {
+        s[SYND_BYTES - 1] &= (1 << shift) - 1;
+    }
+}
 
-        row[i / 8] |= 1 << (i % 8);
+/// The number of rows of the public key multiplied with the error vector at once, i.e. the bits of
+/// a byte of the syndrome. The rows of a block take at most 7 KB, hence they fit into the L1 cache
+/// (like the words of the error vector, at most 1 KB).
+const BLOCK_ROWS: usize = 8;
 
-        let mut b = 0u8;
-        for j in 0..SYS_N / 8 {
-            b ^= row[j] & e[j];
+/// Returns the parities of the rows `block` (at most `BLOCK_ROWS` rows of the public key) multiplied with
+/// `e_words` (see `error_words`), bit `r` for row `r`.
+///
+/// The full words of each row are loaded directly, only the last bytes of a row (if `PK_ROW_BYTES`
+/// is not a multiple of 8, i.e. the rows are not aligned to words) are assembled byte by byte.
+fn block_parities(block: &[u8], e_words: &[u64; PK_ROW_BYTES.div_ceil(8)]) -> u8 {
+    const FULL: usize = PK_ROW_BYTES / 8;
+
+    let mut acc = [0u64; BLOCK_ROWS];
+    for (a, row) in acc.iter_mut().zip(block.chunks_exact(PK_ROW_BYTES)) {
+        let (words, tail) = row.split_at(8 * FULL);
+        *a = load8_partial(tail) & e_words.get(FULL).copied().unwrap_or(0);
+        for (chunk, &w) in words.chunks_exact(8).zip(e_words.iter()) {
+            *a ^= u64::from_le_bytes([
+                chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+            ]) & w;
         }
-
-        b ^= b >> 4;
-        b ^= b >> 2;
-        b ^= b >> 1;
-        b &= 1;
-
-        s[i / 8] |= b << (i % 8);
-
-        pk_segment = &pk_segment[PK_ROW_BYTES..];
     }
+
+    acc.iter()
+        .enumerate()
+        .fold(0, |byte, (r, a)| byte | ((a.count_ones() & 1) as u8) << r)
 }
 
 /// Syndrome computation.
 ///
 /// Computes syndrome `s` based on public key `pk` and error vector `e`.
-#[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
+/// The public key is the non-identity part T of the parity-check matrix H = (I | T). Hence, bit `i` of `s`
+/// is bit `i` of `e` plus the parity of row `i` of T multiplied with the remaining bits of `e`,
+/// accumulated over 64-bit words for blocks of `BLOCK_ROWS` rows (see `block_parities`).
 fn syndrome(
-    s: &mut [u8; (PK_NROWS + 7) / 8],
+    s: &mut [u8; PK_NROWS.div_ceil(8)],
     pk: &[u8; PK_NROWS * PK_ROW_BYTES],
     e: &[u8; SYS_N / 8],
 ) {
-    let mut row = [0u8; SYS_N / 8];
-
-    let mut pk_segment = &pk[..];
+    let e_words = error_words(e);
+    identity_syndrome(s, e);
 
-    s[0..SYND_BYTES].fill(0);
-
-    for i in 0..PK_NROWS {
-        row[0..SYS_N / 8].fill(0);
-
-        for j in 0..PK_ROW_BYTES {
-            row[SYS_N / 8 - PK_ROW_BYTES + j] = pk_segment[j];
-        }
+    for (byte, block) in s.iter_mut().zip(pk.chunks(BLOCK_ROWS * PK_ROW_BYTES)) {
+        *byte ^= block_parities(block, &e_words);
+    }
+}
 
-        row[i / 8] |= 1 << (i % 8);
+/// Encryption routine.
+/// Takes a public key `pk` and an error vector `e` (e.g. from `gen_e`) to compute syndrome `s`.
+pub(crate) fn encrypt(
+    s: &mut [u8; CRYPTO_CIPHERTEXTBYTES],
+    pk: &[u8; PK_NROWS * PK_ROW_BYTES],
+    e: &[u8; SYS_N / 8],
+) -> Result<(), Error> {
+    syndrome(sub!(mut s, 0, PK_NROWS.div_ceil(8)), pk, e);
+    Ok(())
+}
 
-        let mut b = 0u8;
-        for j in 0..SYS_N / 8 {
-            b ^= row[j] & e[j];
+/// Encryption routine reading the public key from `pk`.
+/// Like `encrypt`, but the rows of the public key are read from `pk` one block after another into a buffer
+/// of `BLOCK_ROWS` rows, hence the public key is never held in memory as a whole.
+/// Fails with `Error::InvalidPadding` if the padding bits of a row are set (only mceliece6960119 has padding).
+pub(crate) fn encrypt_streaming(
+    s: &mut [u8; CRYPTO_CIPHERTEXTBYTES],
+    pk: &mut impl Read,
+    e: &[u8; SYS_N / 8],
+) -> Result<(), Error> {
+    let s = sub!(mut s, 0, PK_NROWS.div_ceil(8));
+    let e_words = error_words(e);
+    identity_syndrome(s, e);
+
+    let mut buf = [0u8; BLOCK_ROWS * PK_ROW_BYTES];
+    let mut padding = 0u8;
+    for (k, byte) in s.iter_mut().enumerate() {
+        let rows = (PK_NROWS - k * BLOCK_ROWS).min(BLOCK_ROWS);
+        let block = &mut buf[..rows * PK_ROW_BYTES];
+        pk.read_exact(block)?;
+        *byte ^= block_parities(block, &e_words);
+        for row in block.chunks_exact(PK_ROW_BYTES) {
+            padding |= row[PK_ROW_BYTES - 1];
         }
+    }
 
-        b ^= b >> 4;
-        b ^= b >> 2;
-        b ^= b >> 1;
-        b &= 1;
-
-        s[i / 8] |= b << (i % 8);
-
-        pk_segment = &pk_segment[PK_ROW_BYTES..];
+    let tail = (SYS_N - PK_NROWS) % 8;
+    if tail != 0 && padding >> tail != 0 {
+        return Err(Error::InvalidPadding);
     }
+    Ok(())
 }
 
-/// Encryption routine.
-/// Takes a public key `pk` to compute error vector `e` and syndrome `s`.
-pub(crate) fn encrypt(
+/// Encryption routine for a public key in column-major layout (see `PublicKeyColumns`).
+/// Like `encrypt`, but the syndrome is accumulated over the columns of the public key, i.e. sequentially
+/// over the bits of `e`: each column is added to the syndrome words, masked by its bit of `e`.
+pub(crate) fn encrypt_columns(
     s: &mut [u8; CRYPTO_CIPHERTEXTBYTES],
-    pk: &[u8; PK_NROWS * PK_ROW_BYTES],
-    e: &mut [u8; SYS_N / 8],
-    rng: &mut impl RNGState,
-) -> Result<(), Box<dyn error::Error>> {
-    gen_e(e, rng)?;
-    syndrome(sub!(mut s, 0, (PK_NROWS + 7) / 8), pk, e);
+    pk: &[u8; CRYPTO_PUBLICKEYCOLUMNSBYTES],
+    e: &[u8; SYS_N / 8],
+) -> Result<(), Error> {
+    let s = sub!(mut s, 0, PK_NROWS.div_ceil(8));
+    identity_syndrome(s, e);
+
+    // like in `block_parities`, only the last bytes of a column (mceliece6960119) are assembled byte by byte
+    const FULL: usize = SYND_BYTES / 8;
+
+    let mut acc = [0u64; SYND_BYTES.div_ceil(8)];
+    for (k, column) in pk.chunks_exact(SYND_BYTES).enumerate() {
+        let bit = PK_NROWS + k;
+        let mask = bit_mask(e[bit / 8] as u64, bit % 8);
+        let (words, tail) = column.split_at(8 * FULL);
+        for (a, chunk) in acc.iter_mut().zip(words.chunks_exact(8)) {
+            *a ^= u64::from_le_bytes([
+                chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+            ]) & mask;
+        }
+        if let Some(a) = acc.get_mut(FULL) {
+            *a ^= load8_partial(tail) & mask;
+        }
+    }
+
+    for (chunk, a) in s.chunks_mut(8).zip(acc) {
+        for (byte, b) in chunk.iter_mut().zip(a.to_le_bytes()) {
+            *byte ^= b;
+        }
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    #[cfg(all(feature = "mceliece8192128f", test))]
     use super::*;
     #[cfg(all(feature = "mceliece8192128f", test))]
     use crate::api::CRYPTO_CIPHERTEXTBYTES;
     #[cfg(all(feature = "mceliece8192128f", test))]
     use crate::api::CRYPTO_PUBLICKEYBYTES;
+    use crate::params::PK_NCOLS;
     #[cfg(all(feature = "mceliece8192128f", test))]
     use crate::randombytes::AesState;
+    #[cfg(all(feature = "mceliece8192128f", test))]
+    use rand_core::RngCore;
+    #[cfg(all(feature = "mceliece8192128f", test))]
+    use std::error;
 
     #[test]
     #[cfg(feature = "mceliece8192128f")]
@@ -262,7 +336,7 @@ mod tests {
         let mut second_seed = [0u8; 33];
         second_seed[0] = 64;
 
-        rng_state.randombytes(&mut second_seed[1..])?;
+        rng_state.fill_bytes(&mut second_seed[1..]);
 
         let mut two_e = [0u8; 1 + SYS_N / 8];
         two_e[0] = 2;
@@ -273,15 +347,96 @@ mod tests {
         let compare_ct = crate::TestData::new().u8vec("mceliece8192128f_encrypt_ct");
         assert_eq!(compare_ct.len(), CRYPTO_CIPHERTEXTBYTES);
 
+        gen_e(sub!(mut two_e, 1, SYS_N / 8), &mut rng_state)?;
         encrypt(
             &mut c,
             sub!(mut pk, 0, CRYPTO_PUBLICKEYBYTES),
-            sub!(mut two_e, 1, SYS_N / 8),
-            &mut rng_state,
+            sub!(two_e, 1, SYS_N / 8),
         )?;
 
         assert_eq!(compare_ct, c);
 
         Ok(())
     }
+
+    #[test]
+    fn test_syndrome() {
+        let pk: Vec<u8> = (0..PK_NROWS * PK_ROW_BYTES)
+            .map(|i| (i * 131 + 17) as u8)
+            .collect();
+        let mut e = [0u8; SYS_N / 8];
+        for (i, b) in e.iter_mut().enumerate() {
+            *b = (i * 59 + 3) as u8;
+        }
+
+        // H = (I | T) multiplied with `e` bit by bit
+        let bit = |bytes: &[u8], k: usize| (bytes[k / 8] >> (k % 8)) & 1;
+        let mut expected = [0u8; PK_NROWS.div_ceil(8)];
+        for i in 0..PK_NROWS {
+            let mut b = bit(&e, i);
+            for k in 0..SYS_N - PK_NROWS {
+                b ^= bit(&pk[i * PK_ROW_BYTES..], k) & bit(&e, PK_NROWS + k);
+            }
+            expected[i / 8] |= b << (i % 8);
+        }
+
+        let mut s = [0u8; PK_NROWS.div_ceil(8)];
+        syndrome(&mut s, pk.as_slice().try_into().unwrap(), &e);
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn test_encrypt_columns() -> Result<(), Box<dyn std::error::Error>> {
+        let mut pk: Box<crate::PublicKey> = bytemuck::zeroed_box();
+        for (i, b) in pk.0.iter_mut().enumerate() {
+            *b = (i * 131 + 17) as u8;
+        }
+        if !PK_NCOLS.is_multiple_of(8) {
+            for row in pk.0.chunks_exact_mut(PK_ROW_BYTES) {
+                row[PK_ROW_BYTES - 1] &= (1 << (PK_NCOLS % 8)) - 1;
+            }
+        }
+        let mut e = [0u8; SYS_N / 8];
+        for (i, b) in e.iter_mut().enumerate() {
+            *b = (i * 59 + 3) as u8;
+        }
+
+        let mut expected = [0u8; CRYPTO_CIPHERTEXTBYTES];
+        encrypt(&mut expected, &pk.0, &e)?;
+        let mut c = [0u8; CRYPTO_CIPHERTEXTBYTES];
+        encrypt_columns(&mut c, &pk.to_columns().0, &e)?;
+        assert_eq!(c, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gen_e_without_rejection() -> Result<(), Error> {
+        let mut rng_state = crate::test_rng(0x2B);
+
+        let mut e = [0xFFu8; SYS_N / 8];
+        gen_e_without_rejection(&mut e, &mut rng_state.clone())?;
+        let weight: u32 = e.iter().map(|b| b.count_ones()).sum();
+        assert_eq!(weight as usize, SYS_T);
+
+        // deterministic for the same random numbers, different for the next ones
+        let mut e_again = [0u8; SYS_N / 8];
+        gen_e_without_rejection(&mut e_again, &mut rng_state)?;
+        assert_eq!(e_again, e);
+        gen_e_without_rejection(&mut e_again, &mut rng_state)?;
+        assert_ne!(e_again, e);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_repetition() {
+        let mut ind = [0u16; SYS_T];
+        for (i, x) in ind.iter_mut().enumerate() {
+            *x = ((SYS_T - i) * 13) as u16;
+        }
+        assert!(!has_repetition(&ind));
+
+        ind[SYS_T - 1] = ind[3];
+        assert!(has_repetition(&ind));
+    }
 }
@@ -0,0 +1,154 @@
+//! Error type of this crate
+//!
+//! All fallible operations return `Error`. It distinguishes failures the caller can act upon
+//! (e.g. a failing RNG or a byte slice of wrong length) from internal errors, which indicate a bug.
+
+use std::array::TryFromSliceError;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::io;
+
+use crate::operations::KEYGEN_MAX_ATTEMPTS;
+use crate::params::SYS_T;
+use crate::variant::Variant;
+
+/// Errors returned by the operations of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// The random number generator failed to provide random bytes
+    Rng(rand_core::Error),
+    /// A byte slice does not have the length of the type or buffer it is used for
+    WrongLength { expected: usize, got: usize },
+    /// Padding bits which must be zero are set (only mceliece6960119 has padding)
+    InvalidPadding,
+    /// No valid key pair was found within `KEYGEN_MAX_ATTEMPTS` attempts of the key generation,
+    /// i.e. no public key in systematic form
+    RetryBoundExceeded,
+    /// The key generation was cancelled by its `should_continue` callback
+    Cancelled,
+    /// The error vector provided for encapsulation does not have weight `SYS_T`
+    InvalidErrorVector,
+    /// The public key is malformed
+    InvalidPublicKey(&'static str),
+    /// The secret key is malformed or does not match other key material
+    InvalidSecretKey(&'static str),
+    /// The requested variant is not part of this build (see `Variant::kem`)
+    UnsupportedVariant(Variant),
+    /// Reading the input of an operation failed (see `encapsulate_streaming`)
+    Io(io::Error),
+    /// An internal computation failed, which indicates a bug in this crate
+    Internal(&'static str),
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Rng(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Rng(e) => write!(f, "random number generator failed: {}", e),
+            Error::WrongLength { expected, got } => {
+                write!(f, "wrong length: expected {} bytes, got {}", expected, got)
+            }
+            Error::InvalidPadding => write!(f, "padding bits are not zero"),
+            Error::RetryBoundExceeded => write!(
+                f,
+                "no valid key pair found within {} attempts",
+                KEYGEN_MAX_ATTEMPTS
+            ),
+            Error::Cancelled => write!(f, "key generation was cancelled"),
+            Error::InvalidErrorVector => write!(f, "error vector does not have weight {}", SYS_T),
+            Error::InvalidPublicKey(reason) => write!(f, "invalid public key: {}", reason),
+            Error::InvalidSecretKey(reason) => write!(f, "invalid secret key: {}", reason),
+            Error::UnsupportedVariant(variant) => {
+                write!(f, "variant {} is not part of this build", variant)
+            }
+            Error::Io(e) => write!(f, "reading failed: {}", e),
+            Error::Internal(reason) => write!(f, "internal error: {}", reason),
+        }
+    }
+}
+
+impl From<rand_core::Error> for Error {
+    fn from(e: rand_core::Error) -> Self {
+        Error::Rng(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Slice conversions within the implementation operate on lengths fixed by the variant.
+/// User-provided slices are checked with `array_ref`/`array_mut` instead, which report the lengths.
+impl From<TryFromSliceError> for Error {
+    fn from(_: TryFromSliceError) -> Self {
+        Error::Internal("slice of unexpected length")
+    }
+}
+
+/// Views the user-provided slice `bytes` as array of length `N`
+pub(crate) fn array_ref<const N: usize>(bytes: &[u8]) -> Result<&[u8; N], Error> {
+    <&[u8; N]>::try_from(bytes).map_err(|_| Error::WrongLength {
+        expected: N,
+        got: bytes.len(),
+    })
+}
+
+/// Views the user-provided mutable slice `bytes` as array of length `N`
+pub(crate) fn array_mut<const N: usize>(bytes: &mut [u8]) -> Result<&mut [u8; N], Error> {
+    let got = bytes.len();
+    <&mut [u8; N]>::try_from(bytes).map_err(|_| Error::WrongLength { expected: N, got })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_ref() {
+        let mut bytes = [0u8; 4];
+        assert!(array_ref::<4>(&bytes).is_ok());
+        assert!(matches!(
+            array_ref::<3>(&bytes),
+            Err(Error::WrongLength {
+                expected: 3,
+                got: 4
+            })
+        ));
+        assert!(matches!(
+            array_mut::<5>(&mut bytes),
+            Err(Error::WrongLength {
+                expected: 5,
+                got: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let e = Error::WrongLength {
+            expected: 32,
+            got: 31,
+        };
+        assert_eq!(e.to_string(), "wrong length: expected 32 bytes, got 31");
+        assert_eq!(
+            Error::InvalidPublicKey("row is zero").to_string(),
+            "invalid public key: row is zero"
+        );
+        assert_eq!(
+            Error::UnsupportedVariant(Variant::Mceliece460896f).to_string(),
+            "variant mceliece460896f is not part of this build"
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Additive FFT to evaluate a polynomial at all field elements, and its transpose
+//!
+//! For the algorithm, see
+//! “Additive Fast Fourier Transforms Over Finite Fields” by Shuhong Gao and Todd Mateer (2010)
+//! and its application in “McBits: fast constant-time code-based cryptography”
+//! by Daniel J. Bernstein, Tung Chou, and Peter Schwabe (2013).
+
+use crate::gf::{gf_inv, gf_mul, Gf};
+use crate::params::{GFBITS, SYS_T};
+
+/// The number of coefficients of the polynomials evaluated by `fft`
+const LEN: usize = SYS_T + 1;
+
+/// The number of coefficients (i.e. power sums) computed by `fft_tr`
+const LEN_TR: usize = 2 * SYS_T;
+
+// the polynomials become constant before the subspaces become points
+const _: () = assert!(LEN <= LEN_TR && LEN_TR <= 1 << (GFBITS - 1));
+
+/// The steps of the FFT for polynomials with a given number of coefficients.
+/// They only depend on the parameters, hence all loops run in constant time.
+struct Steps {
+    /// The number of splits until the polynomials are constant
+    depth: usize,
+    /// The number of coefficients of the polynomials of each depth
+    lens: [usize; GFBITS + 1],
+    /// The basis element `β` substituted (`x ↦ β x`) at each depth
+    betas: [Gf; GFBITS],
+    /// The scaled basis of each depth (without its last element, which is scaled to 1)
+    scaled: [[Gf; GFBITS]; GFBITS],
+}
+
+impl Steps {
+    fn new(len: usize) -> Self {
+        // basis[i] is the field element of bit i of the index (i.e. bit GFBITS - 1 - i)
+        let mut basis = [0 as Gf; GFBITS];
+        for (i, b) in basis.iter_mut().enumerate() {
+            *b = 1 << (GFBITS - 1 - i);
+        }
+
+        let mut steps = Steps {
+            depth: 0,
+            lens: [1; GFBITS + 1],
+            betas: [1; GFBITS],
+            scaled: [[0; GFBITS]; GFBITS],
+        };
+
+        let mut len = len;
+        for ((l, beta), scaled) in steps
+            .lens
+            .iter_mut()
+            .zip(steps.betas.iter_mut())
+            .zip(steps.scaled.iter_mut())
+        {
+            *l = len;
+            if len <= 1 {
+                break;
+            }
+
+            let dim = GFBITS - steps.depth;
+            *beta = basis.get(dim - 1).copied().unwrap_or(1);
+            let beta_inv = gf_inv(*beta);
+
+            for (s, b) in scaled.iter_mut().zip(basis.iter_mut()).take(dim - 1) {
+                *s = gf_mul(*b, beta_inv);
+                *b = gf_mul(*s, *s) ^ *s;
+            }
+
+            len = len.div_ceil(2);
+            steps.depth += 1;
+        }
+
+        steps
+    }
+}
+
+/// Evaluates polynomial `f` at all field elements, i.e. `out[i] = f(bitrev(i))`.
+/// This is the order of the field elements permuted by the Beneš network (see `benes::permute`).
+///
+/// The field elements are the subspace spanned by a basis (one basis element per bit of `i`).
+/// Substituting `x ↦ β x` for the last basis element `β` and splitting `f(x) = g0(x² + x) + x · g1(x² + x)`
+/// reduces the evaluation to `g0` and `g1` at the image of the remaining basis under `x ↦ x² + x`,
+/// since `α` and `α + 1` have the same image.
+/// The splitting continues until the polynomials are constant.
+/// Then, the evaluations are combined back in `m · 2^(m-1)` multiplications in total, instead of
+/// `n · t` for the Horner scheme.
+pub(crate) fn fft(out: &mut [Gf; 1 << GFBITS], f: &[Gf; SYS_T + 1]) {
+    let steps = Steps::new(LEN);
+
+    // the polynomials of a depth are stored consecutively with `len` coefficients each
+    let mut cur = [0 as Gf; 2 * LEN_TR];
+    let mut next = [0 as Gf; 2 * LEN_TR];
+    cur[..LEN].copy_from_slice(f);
+
+    // the maxima prove the chunk sizes non-zero (i.e. no panics)
+    let mut count = 1;
+    for ((&len, &half), &beta) in steps
+        .lens
+        .iter()
+        .zip(steps.lens.iter().skip(1))
+        .zip(steps.betas.iter())
+        .take(steps.depth)
+    {
+        for poly in cur.chunks_exact_mut(len.max(1)).take(count) {
+            scale(poly, beta);
+        }
+
+        next.fill(0);
+        for (poly, children) in cur
+            .chunks_exact(len.max(1))
+            .take(count)
+            .zip(next.chunks_exact_mut((2 * half).max(2)))
+        {
+            let (g0, g1) = children.split_at_mut(children.len() / 2);
+            taylor_split(poly, g0, g1);
+        }
+
+        cur = next;
+        count *= 2;
+    }
+
+    // the polynomials are constant now
+    for (block, &c) in out
+        .chunks_exact_mut(((1 << GFBITS) >> steps.depth).max(1))
+        .zip(cur.iter())
+    {
+        block.fill(c);
+    }
+
+    for (d, basis) in steps.scaled.iter().enumerate().take(steps.depth).rev() {
+        // the scaled basis has GFBITS - d - 1 elements (followed by zeros)
+        // g(α) = g0(α² + α) + α · g1(α² + α) and g(α + 1) = g(α) + g1(α² + α)
+        for block in out.chunks_exact_mut(((1 << GFBITS) >> d).max(2)) {
+            let (lo, hi) = block.split_at_mut(block.len() / 2);
+            for ((x, y), alpha) in lo.iter_mut().zip(hi.iter_mut()).zip(span(basis)) {
+                *x ^= gf_mul(alpha, *y);
+                *y ^= *x;
+            }
+        }
+    }
+}
+
+/// The transpose of `fft` for polynomials of `2 * SYS_T` coefficients,
+/// i.e. computes the power sums `out[j] = Σ values[i] · bitrev(i)^j`.
+///
+/// It runs the linear steps of `fft` in reverse order, each transposed.
+/// Thus, it costs about as much as `fft` instead of `2 · t · 2^m` multiplications.
+/// The steps run in place, i.e. `values` is overwritten.
+pub(crate) fn fft_tr(out: &mut [Gf; 2 * SYS_T], values: &mut [Gf; 1 << GFBITS]) {
+    let steps = Steps::new(LEN_TR);
+
+    for (d, basis) in steps.scaled.iter().enumerate().take(steps.depth) {
+        for block in values.chunks_exact_mut(((1 << GFBITS) >> d).max(2)) {
+            let (lo, hi) = block.split_at_mut(block.len() / 2);
+            for ((x, y), alpha) in lo.iter_mut().zip(hi.iter_mut()).zip(span(basis)) {
+                *x ^= *y;
+                *y ^= gf_mul(alpha, *x);
+            }
+        }
+    }
+
+    let mut cur = [0 as Gf; 2 * LEN_TR];
+    let mut prev = [0 as Gf; 2 * LEN_TR];
+    for (c, block) in cur
+        .iter_mut()
+        .zip(values.chunks_exact(((1 << GFBITS) >> steps.depth).max(1)))
+    {
+        *c = block.iter().fold(0, |acc, &x| acc ^ x);
+    }
+
+    for (d, ((&len, &half), &beta)) in steps
+        .lens
+        .iter()
+        .zip(steps.lens.iter().skip(1))
+        .zip(steps.betas.iter())
+        .enumerate()
+        .take(steps.depth)
+        .rev()
+    {
+        prev.fill(0);
+        for (poly, children) in prev
+            .chunks_exact_mut(len.max(1))
+            .take(1 << d)
+            .zip(cur.chunks_exact((2 * half).max(2)))
+        {
+            let (g0, g1) = children.split_at(children.len() / 2);
+            taylor_split_tr(poly, g0, g1);
+            scale(poly, beta);
+        }
+
+        cur = prev;
+    }
+
+    out.copy_from_slice(&cur[..LEN_TR]);
+}
+
+/// Substitutes `x ↦ β x` in polynomial `f`
+fn scale(f: &mut [Gf], beta: Gf) {
+    let mut power: Gf = 1;
+    for c in f.iter_mut() {
+        *c = gf_mul(*c, power);
+        power = gf_mul(power, beta);
+    }
+}
+
+/// Splits `f` into `g0` and `g1` such that `f(x) = g0(x² + x) + x · g1(x² + x)`
+/// (the Taylor expansion of `f` at `x² + x`). Missing coefficients are set to zero.
+fn taylor_split(f: &[Gf], g0: &mut [Gf], g1: &mut [Gf]) {
+    let mut q = [0 as Gf; LEN_TR];
+    for (qi, &fi) in q.iter_mut().zip(f) {
+        *qi = fi;
+    }
+
+    let mut rest: &mut [Gf] = &mut q[..f.len().min(LEN_TR)];
+    for (a, b) in g0.iter_mut().zip(g1.iter_mut()) {
+        // rest = quotient · (x² + x) + remainder, with the quotient in rest[2..] afterwards
+        let mut acc = 0;
+        for c in rest.iter_mut().skip(1).rev() {
+            acc ^= *c;
+            *c = acc;
+        }
+
+        *a = rest.first().copied().unwrap_or(0);
+        *b = rest.get(1).copied().unwrap_or(0);
+        rest = std::mem::take(&mut rest).get_mut(2..).unwrap_or_default();
+    }
+}
+
+/// The transpose of `taylor_split`: the suffix sums of the divisions become prefix sums
+/// in reverse order. Coefficients of `g1` beyond the length of `f` are ignored.
+fn taylor_split_tr(f: &mut [Gf], g0: &[Gf], g1: &[Gf]) {
+    let n = f.len().min(LEN_TR);
+    let mut q = [0 as Gf; LEN_TR];
+
+    for (pair, (&a, &b)) in q[..n].chunks_mut(2).zip(g0.iter().zip(g1)) {
+        if let Some(x) = pair.first_mut() {
+            *x = a;
+        }
+        if let Some(y) = pair.get_mut(1) {
+            *y = b;
+        }
+    }
+
+    for i in (0..g0.len()).rev() {
+        if let Some(rest) = q[..n].get_mut(2 * i + 1..) {
+            let mut acc = 0;
+            for c in rest.iter_mut() {
+                acc ^= *c;
+                *c = acc;
+            }
+        }
+    }
+
+    for (fi, &qi) in f.iter_mut().zip(q.iter()) {
+        *fi = qi;
+    }
+}
+
+/// Iterates over all `Σ k_i · basis[i]` (with `k_i` the bits of `k`) for `k = 0, 1, …`.
+///
+/// From `k - 1` to `k`, the bits up to the lowest set bit of `k` flip, hence each element is
+/// the previous one plus a prefix sum of the basis. Thus, no table of all `2^(m-1)` elements is needed.
+fn span(basis: &[Gf; GFBITS]) -> impl Iterator<Item = Gf> {
+    let mut prefix = [0 as Gf; GFBITS];
+    let mut acc = 0;
+    for (p, &b) in prefix.iter_mut().zip(basis) {
+        acc ^= b;
+        *p = acc;
+    }
+
+    (0usize..).scan(0 as Gf, move |x, k| {
+        if k > 0 {
+            *x ^= prefix
+                .get(k.trailing_zeros() as usize)
+                .copied()
+                .unwrap_or(0);
+        }
+        Some(*x)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::root::eval;
+    use crate::util::bitrev;
+
+    #[test]
+    fn test_fft() {
+        let mut f = [0 as Gf; SYS_T + 1];
+        for (i, c) in f.iter_mut().enumerate() {
+            *c = ((i * 2731 + 17) & ((1 << GFBITS) - 1)) as Gf;
+        }
+
+        let mut out = [0 as Gf; 1 << GFBITS];
+        fft(&mut out, &f);
+
+        for (i, &value) in out.iter().enumerate() {
+            assert_eq!(value, eval(&f, bitrev(i as Gf)), "point {}", i);
+        }
+    }
+
+    #[test]
+    fn test_fft_tr() {
+        let mut values = [0 as Gf; 1 << GFBITS];
+        for (i, v) in values.iter_mut().enumerate() {
+            *v = ((i * 1559 + 3) & ((1 << GFBITS) - 1)) as Gf;
+        }
+
+        let mut out = [0 as Gf; 2 * SYS_T];
+        fft_tr(&mut out, &mut values.clone());
+
+        let mut expected = [0 as Gf; 2 * SYS_T];
+        for (i, &v) in values.iter().enumerate() {
+            let mut power = v;
+            for e in expected.iter_mut() {
+                *e ^= power;
+                power = gf_mul(power, bitrev(i as Gf));
+            }
+        }
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_span() {
+        let mut basis = [0 as Gf; GFBITS];
+        for (i, b) in basis.iter_mut().enumerate() {
+            *b = (i * 977 + 1) as Gf;
+        }
+
+        for (k, x) in span(&basis).take(1 << GFBITS).enumerate() {
+            let expected = (0..GFBITS)
+                .filter(|i| (k >> i) & 1 == 1)
+                .fold(0, |acc, i| acc ^ basis[i]);
+            assert_eq!(x, expected, "element {}", k);
+        }
+    }
+
+    #[test]
+    fn test_taylor_split() {
+        // x³ + x² + 1 = 1 · (x² + x)⁰ + x · (x² + x)
+        let mut g0 = [0xFF as Gf; 2];
+        let mut g1 = [0xFF as Gf; 2];
+        taylor_split(&[1, 0, 1, 1], &mut g0, &mut g1);
+        assert_eq!(g0, [1, 0]);
+        assert_eq!(g1, [0, 1]);
+
+        // the transpose of the matrix with rows a0 = f0, b0 = f1 + f2 + f3, a1 = f2 + f3, b1 = f3
+        let mut f = [0 as Gf; 4];
+        taylor_split_tr(&mut f, &[1, 2], &[4, 8]);
+        assert_eq!(f, [1, 4, 4 ^ 2, 4 ^ 2 ^ 8]);
+    }
+}
@@ -0,0 +1,125 @@
+//! Field arithmetic with logarithm and exponential tables (NOT constant-time)
+//!
+//! The tables allow to multiply and invert elements of the Galois field GF(2^GFBITS) of the compiled variant
+//! with a few table lookups, e.g. for bulk arithmetic on public data in tooling or research.
+//! The lookups are indexed by the operands, hence the timing depends on the (cached) memory accesses.
+//! Never use these functions for secret data. The operations of this crate do not use them,
+//! i.e. enabling the feature `gf-tables` does not change the implementation of the KEM.
+
+use crate::gf::{gf_mul, Gf};
+use crate::params::{GFBITS, GFMASK};
+
+/// The order of the multiplicative group of the field
+const ORDER: usize = (1 << GFBITS) - 1;
+
+/// Logarithm and exponential tables of the field of the compiled variant.
+/// Field elements are the `GFBITS` lower bits of `u16` values (higher bits are ignored).
+pub struct GfTables {
+    /// `log[a]` is the discrete logarithm of `a ≠ 0` to base `generator`
+    log: Box<[u16]>,
+    /// `exp[i] = generator^i` for `0 ≤ i < 2 · ORDER`, so that the sum of two logarithms needs no reduction
+    exp: Box<[u16]>,
+    generator: u16,
+}
+
+impl GfTables {
+    /// Computes the tables (with the constant-time field multiplication) for the smallest generator
+    /// of the multiplicative group
+    pub fn new() -> Self {
+        let mut log = vec![0u16; 1 << GFBITS].into_boxed_slice();
+        let mut exp = vec![0u16; 2 * ORDER].into_boxed_slice();
+
+        let mut generator: Gf = 2;
+        loop {
+            let mut x: Gf = 1;
+            let mut order = 0;
+            for (i, e) in exp.iter_mut().take(ORDER).enumerate() {
+                if i > 0 && x == 1 {
+                    break;
+                }
+                *e = x;
+                x = gf_mul(x, generator);
+                order = i + 1;
+            }
+
+            if order == ORDER && x == 1 {
+                break;
+            }
+            generator += 1;
+        }
+
+        for i in 0..ORDER {
+            exp[ORDER + i] = exp[i];
+            log[exp[i] as usize] = i as u16;
+        }
+
+        GfTables {
+            log,
+            exp,
+            generator,
+        }
+    }
+
+    /// The generator of the multiplicative group which is the base of the logarithms
+    pub fn generator(&self) -> u16 {
+        self.generator
+    }
+
+    /// Returns the product `a · b`
+    pub fn mul(&self, a: u16, b: u16) -> u16 {
+        let (a, b) = (a & GFMASK as u16, b & GFMASK as u16);
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    /// Returns the inverse `1/a`, or 0 for `a = 0` (like the constant-time field inversion)
+    pub fn inv(&self, a: u16) -> u16 {
+        let a = a & GFMASK as u16;
+        if a == 0 {
+            return 0;
+        }
+        self.exp[ORDER - self.log[a as usize] as usize]
+    }
+
+    /// Returns the power `a^e` (with `0^0 = 1`)
+    pub fn pow(&self, a: u16, e: u64) -> u16 {
+        let a = a & GFMASK as u16;
+        if e == 0 {
+            return 1;
+        }
+        if a == 0 {
+            return 0;
+        }
+        let i = (self.log[a as usize] as u64 * (e % ORDER as u64)) % ORDER as u64;
+        self.exp[i as usize]
+    }
+}
+
+impl Default for GfTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gf::gf_inv;
+
+    #[test]
+    fn test_gf_tables() {
+        let tables = GfTables::new();
+
+        for a in (0..1u16 << GFBITS).step_by(7) {
+            for b in (0..1u16 << GFBITS).step_by(331) {
+                assert_eq!(tables.mul(a, b), gf_mul(a, b));
+            }
+            assert_eq!(tables.inv(a), gf_inv(a));
+            assert_eq!(tables.pow(a, 3), gf_mul(gf_mul(a, a), a));
+        }
+        assert_eq!(tables.pow(tables.generator(), ORDER as u64), 1);
+        assert_eq!(tables.pow(0, 0), 1);
+    }
+}
@@ -0,0 +1,90 @@
+//! Internal routines for the instruction-count benchmarks in `benches/` (feature `internals`)
+//!
+//! These functions are NOT part of the stable API: they wrap hot loops of the implementation with
+//! signatures the benchmarks can call and may change with any release. The lengths of the slices
+//! are checked at runtime, slices of other lengths return `Error::Internal`.
+
+use crate::error::Error;
+use crate::macros::sub;
+#[cfg(not(feature = "encap-only"))]
+use crate::params::SYS_N;
+use crate::params::SYS_T;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::params::{GFBITS, IRR_BYTES};
+#[cfg(not(feature = "encap-only"))]
+use crate::types::ExpandedSecretKey;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::types::{PublicKey, SecretKey};
+#[cfg(not(feature = "encap-only"))]
+use crate::workspace::Workspace;
+
+/// Multiplies the field elements `a` and `b` (see `gf::gf_mul`)
+pub fn gf_mul(a: u16, b: u16) -> u16 {
+    crate::gf::gf_mul(a, b)
+}
+
+/// Multiplies the elements `a` and `b` of GF((2^m)^t), i.e. polynomials of `t` coefficients, into `out`
+/// (see `gf::gf_mul_inplace`, the `GF_mul` of the reference implementation)
+pub fn gf_mul_poly(out: &mut [u16], a: &[u16], b: &[u16]) -> Result<(), Error> {
+    crate::gf::gf_mul_inplace(
+        sub!(mut out, 0, SYS_T, u16),
+        sub!(a, 0, SYS_T, u16),
+        sub!(b, 0, SYS_T, u16),
+    );
+    Ok(())
+}
+
+/// Computes the `2t` syndromes of the received word `r` of `n` bits with the expanded secret key `esk`
+/// (see `synd::synd`), with the buffer of `ws`
+#[cfg(not(feature = "encap-only"))]
+pub fn synd(
+    out: &mut [u16],
+    esk: &ExpandedSecretKey,
+    r: &[u8],
+    ws: &mut Workspace,
+) -> Result<(), Error> {
+    let (_, values) = ws.decryption();
+    crate::synd::synd(
+        sub!(mut out, 0, 2 * SYS_T, u16),
+        esk.dk.inv_squares(),
+        &*esk.net,
+        sub!(r, 0, SYS_N / 8),
+        values,
+    )
+}
+
+/// Computes the error locator of the `2t` syndromes `s` into the `t + 1` coefficients of `out`
+/// with the Berlekamp–Massey algorithm (see `bm::bm`)
+#[cfg(not(feature = "encap-only"))]
+pub fn bm(out: &mut [u16], s: &mut [u16]) -> Result<(), Error> {
+    crate::bm::bm(
+        sub!(mut out, 0, SYS_T + 1, u16),
+        sub!(mut s, 0, 2 * SYS_T, u16),
+    );
+    Ok(())
+}
+
+/// Computes the public key of the Goppa polynomial of `sk` and the `2^m` random words `perm`
+/// with the matrix of `ws` (see `pk_gen::pk_gen`). Returns whether the matrix has the (semi-)systematic form.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn pk_gen(
+    pk: &mut PublicKey,
+    sk: &SecretKey,
+    perm: &[u32],
+    ws: &mut Workspace,
+) -> Result<bool, Error> {
+    let mut pi = [0i16; 1 << GFBITS];
+    #[cfg(semi_systematic)]
+    let mut pivots = 0u64;
+
+    let ret = crate::pk_gen::pk_gen(
+        &mut pk.0,
+        ws.matrix(),
+        sub!(sk.0, 40, IRR_BYTES),
+        sub!(perm, 0, 1 << GFBITS, u32),
+        &mut pi,
+        #[cfg(semi_systematic)]
+        &mut pivots,
+    )?;
+    Ok(ret == 0)
+}
@@ -0,0 +1,59 @@
+//! Implementations of the `Encapsulate`/`Decapsulate` traits of the RustCrypto `kem` crate
+//!
+//! This allows to use the key types of this crate in generic KEM code.
+
+#[cfg(not(feature = "encap-only"))]
+use kem::Decapsulate;
+#[cfg(not(feature = "decap-only"))]
+use kem::Encapsulate;
+#[cfg(not(feature = "decap-only"))]
+use rand_core::CryptoRngCore;
+
+use crate::error::Error;
+#[cfg(not(feature = "encap-only"))]
+use crate::operations::decapsulate;
+#[cfg(not(feature = "decap-only"))]
+use crate::operations::encapsulate;
+#[cfg(not(feature = "decap-only"))]
+use crate::types::PublicKey;
+#[cfg(not(feature = "encap-only"))]
+use crate::types::SecretKey;
+use crate::types::{Ciphertext, SharedSecret};
+
+#[cfg(not(feature = "decap-only"))]
+impl Encapsulate<Ciphertext, SharedSecret> for PublicKey {
+    type Error = Error;
+
+    fn encapsulate(
+        &self,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(Ciphertext, SharedSecret), Self::Error> {
+        encapsulate(self, rng)
+    }
+}
+
+#[cfg(not(feature = "encap-only"))]
+impl Decapsulate<Ciphertext, SharedSecret> for SecretKey {
+    type Error = Error;
+
+    fn decapsulate(&self, encapsulated_key: &Ciphertext) -> Result<SharedSecret, Self::Error> {
+        decapsulate(self, encapsulated_key)
+    }
+}
+
+#[cfg(all(test, not(any(feature = "encap-only", feature = "decap-only"))))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kem_traits_roundtrip() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+
+        let (ct, ss_bob) = pk.encapsulate(&mut rand::thread_rng())?;
+        let ss_alice = sk.decapsulate(&ct)?;
+
+        assert!(ss_alice == ss_bob);
+
+        Ok(())
+    }
+}
@@ -7,31 +7,105 @@
 #![forbid(unsafe_code)]
 
 mod api;
+#[cfg(not(feature = "encap-only"))]
 mod benes;
+#[cfg(not(feature = "encap-only"))]
 mod bm;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 mod controlbits;
 mod crypto_hash;
+#[cfg_attr(any(feature = "encap-only", feature = "decap-only"), allow(dead_code))]
+mod ct;
+#[cfg(not(feature = "encap-only"))]
+mod decap_pool;
+#[cfg(not(feature = "encap-only"))]
+mod decapsulator;
+#[cfg(not(feature = "encap-only"))]
 mod decrypt;
+#[cfg(not(feature = "decap-only"))]
 mod encrypt;
-mod gf;
-mod int32_sort;
+mod error;
+#[cfg(not(feature = "encap-only"))]
+mod fft;
+// the encapsulation needs the field element type only, the decapsulation no field element I/O
+#[cfg_attr(any(feature = "encap-only", feature = "decap-only"), allow(dead_code))]
+pub mod gf;
+#[cfg(feature = "gf-tables")]
+pub mod gf_tables;
+// NOT a stable API, see the module documentation
+#[cfg(feature = "internals")]
+#[doc(hidden)]
+pub mod internals;
+#[cfg(feature = "kem")]
+mod kem_traits;
 mod operations;
+// both functions need randomness, the decapsulation does not
+#[cfg(all(feature = "os-rng", not(feature = "decap-only")))]
+pub mod os_rng;
 mod params;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 mod pk_gen;
+#[cfg(feature = "pkcs8")]
+mod pkcs8_impls;
 mod randombytes;
+#[cfg(not(feature = "encap-only"))]
 mod root;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 mod sk_gen;
+pub mod sort;
+#[cfg(not(feature = "encap-only"))]
 mod synd;
+#[cfg_attr(feature = "encap-only", allow(dead_code))]
 mod transpose;
-mod uint64_sort;
-mod util;
+mod types;
+#[cfg_attr(any(feature = "encap-only", feature = "decap-only"), allow(dead_code))]
+pub mod util;
+mod variant;
+#[cfg(not(feature = "encap-only"))]
+mod vec_gf;
+#[cfg(not(feature = "encap-only"))]
+mod workspace;
 
 pub use api::{
-    CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_PRIMITIVE, CRYPTO_PUBLICKEYBYTES,
-    CRYPTO_SECRETKEYBYTES,
+    CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_COMPACTSECRETKEYBYTES, CRYPTO_ERRORVECTORBYTES,
+    CRYPTO_PRIMITIVE, CRYPTO_PUBLICKEYBYTES, CRYPTO_PUBLICKEYCOLUMNSBYTES, CRYPTO_SECRETKEYBYTES,
 };
-pub use operations::{crypto_kem_dec, crypto_kem_enc, crypto_kem_keypair};
-pub use randombytes::{AesState, RNGState};
+#[cfg(not(feature = "encap-only"))]
+pub use decap_pool::{DecapPool, PendingSecret};
+#[cfg(not(feature = "encap-only"))]
+pub use decapsulator::Decapsulator;
+pub use error::Error;
+pub use operations::KEYGEN_MAX_ATTEMPTS;
+#[cfg(not(feature = "encap-only"))]
+pub use operations::{
+    crypto_kem_dec, decap_into, decap_with, decapsulate, decapsulate_batch, decapsulate_expanded,
+    decapsulate_with_context, DECAP_WITH_MAX_STACK,
+};
+#[cfg(not(feature = "decap-only"))]
+pub use operations::{
+    crypto_kem_enc, crypto_kem_enc_with_coins, encap_into, encapsulate, encapsulate_batch,
+    encapsulate_columns, encapsulate_many, encapsulate_streaming, encapsulate_with_coins,
+    encapsulate_with_context, encapsulate_without_rejection,
+};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub use operations::{
+    crypto_kem_keypair, crypto_kem_keypair_cancellable, crypto_kem_keypair_from_seed,
+    crypto_kem_keypair_with_progress, keypair, keypair_batch, keypair_boxed,
+    keypair_boxed_cancellable, keypair_from_seed, keypair_into, keypair_parallel,
+    keypair_streaming, keypair_with, verify_keypair, KeygenPhase, KeygenState,
+};
+pub use randombytes::AesState;
+#[cfg(not(feature = "encap-only"))]
+pub use types::ExpandedSecretKey;
+pub use types::{
+    Ciphertext, CompactSecretKey, PublicKey, PublicKeyColumns, PublicKeyRef, SecretKey,
+    SharedSecret,
+};
+pub use variant::{Kem, Params, Variant};
+#[cfg(not(feature = "encap-only"))]
+pub use workspace::Workspace;
 
 mod macros {
     /// This macro(A, B, C, T) allows to get “&A[B..B+C]” of type “&[T]” as type “&[T; C]”.
@@ -119,12 +193,29 @@ struct TestData {
 }
 
 #[cfg(test)]
+#[cfg_attr(any(feature = "encap-only", feature = "decap-only"), allow(dead_code))]
 impl TestData {
+    /// The fixtures of `data/testdata.txt`, embedded into the test binary (feature `kat-data`)
+    #[cfg(feature = "kat-data")]
     fn new() -> TestData {
         let bytes = include_bytes!("../data/testdata.txt");
         TestData { data: bytes }
     }
 
+    /// The fixtures of `data/testdata.txt`, read from the source tree once per test binary.
+    /// The 18 MB of fixtures are only embedded with the feature `kat-data`, e.g. for test binaries
+    /// which run on a different machine.
+    #[cfg(not(feature = "kat-data"))]
+    fn new() -> TestData {
+        static DATA: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+
+        let data = DATA.get_or_init(|| {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/testdata.txt");
+            std::fs::read(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e))
+        });
+        TestData { data }
+    }
+
     impl_parser_per_type!(u8vec, 8, u8);
     impl_parser_per_type!(u16vec, 16, u16);
     impl_parser_per_type!(u32vec, 32, u32);
@@ -140,6 +231,82 @@ impl TestData {
     //impl_parser_per_type!(i64vec, 64, i64);
 }
 
+/// Returns an `AesState` seeded with 48 bytes `seed`
+#[cfg(test)]
+#[cfg_attr(feature = "decap-only", allow(dead_code))]
+fn test_rng(seed: u8) -> AesState {
+    let mut rng_state = AesState::new();
+    rng_state.randombytes_init([seed; 48]);
+    rng_state
+}
+
+/// The key pair of `keypair_boxed` for `test_rng(0x2A)`, generated once per test binary
+/// and shared by the tests which need some valid key pair
+#[cfg(test)]
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+fn test_keypair() -> &'static (Box<PublicKey>, Box<SecretKey>) {
+    static KEYPAIR: std::sync::OnceLock<(Box<PublicKey>, Box<SecretKey>)> =
+        std::sync::OnceLock::new();
+
+    KEYPAIR.get_or_init(|| keypair_boxed(&mut test_rng(0x2A)).expect("key generation"))
+}
+
+/// A test case of a NIST response (`.rsp`) file, e.g. `PQCkemKAT_935.rsp` of the reference implementation
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct RspRecord {
+    count: usize,
+    seed: Vec<u8>,
+    pk: Vec<u8>,
+    sk: Vec<u8>,
+    ct: Vec<u8>,
+    ss: Vec<u8>,
+}
+
+/// Reads the test cases of the response file at `path`. Returns `None` if its header
+/// (`# kem/<variant>`) names another variant than the compiled one.
+#[cfg(test)]
+#[cfg_attr(any(feature = "encap-only", feature = "decap-only"), allow(dead_code))]
+fn read_rsp(path: &str) -> Option<Vec<RspRecord>> {
+    let content =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e));
+
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or_default().trim();
+    if header.strip_prefix("# kem/") != Some(CRYPTO_PRIMITIVE) {
+        return None;
+    }
+
+    let mut records = Vec::new();
+    let mut record: Option<RspRecord> = None;
+    for (lineno, line) in lines.enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            records.extend(record.take());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .unwrap_or_else(|| panic!("{}:{}: expected `key = value`", path, lineno + 2));
+        let (key, value) = (key.trim(), value.trim());
+        let r = record.get_or_insert_with(RspRecord::default);
+        let bytes = || hex::decode(value).expect("invalid hex data in value");
+        match key {
+            "count" => r.count = value.parse().expect("invalid count"),
+            "seed" => r.seed = bytes(),
+            "pk" => r.pk = bytes(),
+            "sk" => r.sk = bytes(),
+            "ct" => r.ct = bytes(),
+            "ss" => r.ss = bytes(),
+            _ => panic!("{}:{}: unknown key '{}'", path, lineno + 2, key),
+        }
+    }
+    records.extend(record);
+
+    Some(records)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,74 +1,112 @@
 //! KEM API
 
-use std::error;
-
+#[cfg(not(feature = "encap-only"))]
+use crate::api::CRYPTO_SECRETKEYBYTES;
+use crate::api::{CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES};
+#[cfg(not(feature = "decap-only"))]
+use crate::api::{CRYPTO_ERRORVECTORBYTES, CRYPTO_PUBLICKEYBYTES};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 use crate::controlbits::controlbitsfrompermutation;
 #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+use crate::ct::nonzero_mask;
+#[cfg(not(feature = "decap-only"))]
+use crate::ct::weight;
+#[cfg(not(feature = "encap-only"))]
+use crate::ct::zero_mask;
+#[cfg(not(feature = "decap-only"))]
+use crate::encrypt::{encrypt, encrypt_columns, encrypt_streaming, gen_e, gen_e_without_rejection};
+use crate::error::{array_mut, array_ref, Error};
+#[cfg(not(feature = "decap-only"))]
+use crate::params::SYS_T;
+#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
 use crate::params::{PK_NCOLS, PK_NROWS, PK_ROW_BYTES};
-use crate::randombytes::RNGState;
+#[cfg(not(feature = "encap-only"))]
+use crate::types::{check_ciphertext_bytes, ExpandedSecretKey, SecretKey};
+#[cfg(not(feature = "decap-only"))]
+use crate::types::{
+    check_public_key_bytes, check_public_key_columns_bytes, PublicKey, PublicKeyColumns,
+};
+use crate::types::{Ciphertext, SharedSecret};
+#[cfg(not(feature = "encap-only"))]
 use crate::{
-    api::{CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_PUBLICKEYBYTES, CRYPTO_SECRETKEYBYTES},
-    crypto_hash::shake256,
-    decrypt::decrypt,
-    encrypt::encrypt,
+    benes::Conditions,
+    decrypt::DecryptionKey,
+    gf::Gf,
     macros::sub,
-    params::{COND_BYTES, GFBITS, IRR_BYTES, SYND_BYTES, SYS_N, SYS_T},
-    pk_gen::pk_gen,
+    params::{COND_BYTES, GFBITS, IRR_BYTES},
+    workspace::Workspace,
+};
+use crate::{
+    crypto_hash::{shake256, shake256_concat},
+    params::{SYND_BYTES, SYS_N},
+};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::{
+    pk_gen::{pk_gen, pk_gen_streaming, StreamMatrix},
     sk_gen::genpoly_gen,
     util::{load4, load_gf, store8, store_gf},
 };
+use bytemuck::TransparentWrapper;
+#[cfg(not(feature = "decap-only"))]
+use rand_core::CryptoRngCore;
+#[cfg(not(feature = "decap-only"))]
+use std::io::Read;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+#[cfg(all(feature = "zeroize", not(feature = "encap-only")))]
+use zeroize::Zeroize;
 
 /// This function determines (in a constant-time manner) whether the padding bits of `pk` are all zero.
 #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-fn check_pk_padding(pk: &[u8; PK_NROWS * PK_ROW_BYTES]) -> u8 {
+pub(crate) fn check_pk_padding(pk: &[u8; PK_NROWS * PK_ROW_BYTES]) -> u8 {
     let mut b = 0u8;
     for i in 0..PK_NROWS {
         b |= pk[i * PK_ROW_BYTES + PK_ROW_BYTES - 1];
     }
 
-    b >>= PK_NCOLS % 8;
-    b = b.wrapping_sub(1);
-    b >>= 7;
-    b.wrapping_sub(1)
+    nonzero_mask((b >> (PK_NCOLS % 8)) as u64) as u8
 }
 
 /// This function determines (in a constant-time manner) whether the padding bits of `c` are all zero.
 #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-fn check_c_padding(c: &[u8; SYND_BYTES]) -> u8 {
-    let mut b = c[SYND_BYTES - 1] >> (PK_NROWS % 8);
-    b = b.wrapping_sub(1);
-    b >>= 7;
-    b.wrapping_sub(1)
+pub(crate) fn check_c_padding(c: &[u8; SYND_BYTES]) -> u8 {
+    nonzero_mask((c[SYND_BYTES - 1] >> (PK_NROWS % 8)) as u64) as u8
 }
 
-/// KEM Encapsulation.
-///
-/// Given a public key `pk`, sample a shared key.
-/// This shared key is returned through parameter `key` whereas
-/// the ciphertext (meant to be used for decapsulation) is returned as `c`.
-#[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
-pub fn crypto_kem_enc(
+/// Checks that the error vector `e` has weight `SYS_T`
+#[cfg(not(feature = "decap-only"))]
+fn check_error_vector(e: &[u8; CRYPTO_ERRORVECTORBYTES]) -> Result<(), Error> {
+    if weight(e) as usize != SYS_T {
+        return Err(Error::InvalidErrorVector);
+    }
+    Ok(())
+}
+
+/// Completes the ciphertext `c` (whose syndrome is computed already) with the hash of the error vector `e`
+/// and derives the shared key `key` from `e`, `c`, and the context `ctx`
+#[cfg(not(feature = "decap-only"))]
+fn hash_encapsulation(
     c: &mut [u8; CRYPTO_CIPHERTEXTBYTES],
     key: &mut [u8; CRYPTO_BYTES],
-    pk: &[u8; CRYPTO_PUBLICKEYBYTES],
-    rng: &mut impl RNGState,
-) -> Result<(), Box<dyn error::Error>> {
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+    ctx: &[u8],
+) -> Result<(), Error> {
     let mut two_e = [0u8; 1 + SYS_N / 8];
     two_e[0] = 2;
+    two_e[1..].copy_from_slice(e);
 
     let mut one_ec = [0u8; 1 + SYS_N / 8 + (SYND_BYTES + 32)];
     one_ec[0] = 1;
 
-    encrypt(c, pk, sub!(mut two_e, 1, SYS_N / 8), rng)?;
-
     shake256(&mut c[SYND_BYTES..SYND_BYTES + 32], &two_e)?;
 
     one_ec[1..1 + SYS_N / 8].copy_from_slice(&two_e[1..1 + SYS_N / 8]);
     one_ec[1 + SYS_N / 8..1 + SYS_N / 8 + SYND_BYTES + 32].copy_from_slice(&c[0..SYND_BYTES + 32]);
 
-    shake256(&mut key[0..32], &one_ec)?;
-
-    Ok(())
+    shake256_concat(&mut key[0..32], &[&one_ec, ctx])
 }
 
 /// KEM Encapsulation.
@@ -76,41 +114,122 @@ pub fn crypto_kem_enc(
 /// Given a public key `pk`, sample a shared key.
 /// This shared key is returned through parameter `key` whereas
 /// the ciphertext (meant to be used for decapsulation) is returned as `c`.
-#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+#[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
+#[cfg(not(feature = "decap-only"))]
 pub fn crypto_kem_enc(
-    c: &mut [u8; CRYPTO_CIPHERTEXTBYTES],
-    key: &mut [u8; CRYPTO_BYTES],
-    pk: &[u8; CRYPTO_PUBLICKEYBYTES],
-    rng: &mut impl RNGState,
-) -> Result<u8, Box<dyn error::Error>> {
-    let mut two_e = [0u8; 1 + SYS_N / 8];
-    two_e[0] = 2;
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+
+    crypto_kem_enc_with_coins(c, key, pk, &e)
+}
 
-    let mut one_ec = [0u8; 1 + SYS_N / 8 + (SYND_BYTES + 32)];
-    one_ec[0] = 1;
+/// KEM Encapsulation with a given error vector.
+///
+/// Like `crypto_kem_enc`, but the error vector `e` (of `CRYPTO_ERRORVECTORBYTES` bytes and weight `SYS_T`),
+/// which is otherwise sampled from the RNG, is provided by the caller. This makes encapsulation
+/// deterministic and is meant for reproducing test vectors. Reusing an error vector or deriving it
+/// from predictable data breaks the security of the shared key.
+#[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
+#[cfg(not(feature = "decap-only"))]
+pub fn crypto_kem_enc_with_coins(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+) -> Result<(), Error> {
+    crypto_kem_enc_with_context(c, key, pk, e, &[])
+}
 
-    let padding_ok = check_pk_padding(pk);
+/// Like `crypto_kem_enc_with_coins`, but appends the context `ctx` to the input of the hash
+/// deriving the shared key.
+#[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
+#[cfg(not(feature = "decap-only"))]
+fn crypto_kem_enc_with_context(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+    ctx: &[u8],
+) -> Result<(), Error> {
+    let (c, key, pk) = (&mut c.0, &mut key.0, &pk.0);
+
+    check_error_vector(e)?;
+
+    encrypt(c, pk, e)?;
+    hash_encapsulation(c, key, e, ctx)
+}
 
-    encrypt(c, pk, sub!(mut two_e, 1, SYS_N / 8), rng)?;
+/// KEM Encapsulation.
+///
+/// Given a public key `pk`, sample a shared key.
+/// This shared key is returned through parameter `key` whereas
+/// the ciphertext (meant to be used for decapsulation) is returned as `c`.
+#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+#[cfg(not(feature = "decap-only"))]
+pub fn crypto_kem_enc(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    rng: &mut impl CryptoRngCore,
+) -> Result<u8, Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+
+    crypto_kem_enc_with_coins(c, key, pk, &e)
+}
 
-    shake256(&mut c[SYND_BYTES..(SYND_BYTES + 32)], &two_e)?;
+/// KEM Encapsulation with a given error vector.
+///
+/// Like `crypto_kem_enc`, but the error vector `e` (of `CRYPTO_ERRORVECTORBYTES` bytes and weight `SYS_T`),
+/// which is otherwise sampled from the RNG, is provided by the caller. This makes encapsulation
+/// deterministic and is meant for reproducing test vectors. Reusing an error vector or deriving it
+/// from predictable data breaks the security of the shared key.
+#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+#[cfg(not(feature = "decap-only"))]
+pub fn crypto_kem_enc_with_coins(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+) -> Result<u8, Error> {
+    crypto_kem_enc_with_context(c, key, pk, e, &[])
+}
 
-    one_ec[1..1 + (SYS_N / 8)].copy_from_slice(&two_e[1..(SYS_N / 8) + 1]);
-    one_ec[1 + (SYS_N / 8)..1 + (SYS_N / 8) + SYND_BYTES + 32]
-        .copy_from_slice(&c[0..SYND_BYTES + 32]);
+/// Like `crypto_kem_enc_with_coins`, but appends the context `ctx` to the input of the hash
+/// deriving the shared key.
+#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+#[cfg(not(feature = "decap-only"))]
+fn crypto_kem_enc_with_context(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+    ctx: &[u8],
+) -> Result<u8, Error> {
+    let (c, key, pk) = (&mut c.0, &mut key.0, &pk.0);
+
+    check_error_vector(e)?;
+
+    let padding_ok = check_pk_padding(pk);
 
-    shake256(&mut key[0..32], &one_ec)?;
+    encrypt(c, pk, e)?;
+    hash_encapsulation(c, key, e, ctx)?;
 
     // clear outputs (set to all 0's) if padding bits are not all zero
 
     let mask = padding_ok ^ 0xFF;
 
-    for i in 0..SYND_BYTES + 32 {
-        c[i] &= mask;
+    for b in c[..SYND_BYTES + 32].iter_mut() {
+        *b &= mask;
     }
 
-    for i in 0..32 {
-        key[i] &= mask;
+    for b in key[..32].iter_mut() {
+        *b &= mask;
     }
 
     Ok(padding_ok)
@@ -120,22 +239,52 @@ pub fn crypto_kem_enc(
 ///
 /// Given a secret key `sk` and a ciphertext `c`,
 /// determine the shared text `key` negotiated by both parties.
+#[cfg(not(feature = "encap-only"))]
+pub fn crypto_kem_dec(key: &mut SharedSecret, c: &Ciphertext, sk: &SecretKey) -> Result<u8, Error> {
+    crypto_kem_dec_with_key(
+        key,
+        c,
+        sk,
+        &decryption_key(sk)?,
+        sk.control_bits(),
+        &[],
+        &mut [0; 1 << GFBITS],
+    )
+}
+
+/// Computes the decryption key (i.e. the inverted squares of the Goppa polynomial) of `sk`
+#[cfg(not(feature = "encap-only"))]
+fn decryption_key(sk: &SecretKey) -> Result<DecryptionKey, Error> {
+    DecryptionKey::new(sub!(sk.0, 40, IRR_BYTES))
+}
+
+/// Like `crypto_kem_dec`, but with the decryption key `dk` of `sk` (see `decryption_key`),
+/// the context `ctx` appended to the input of the hash deriving the shared key
+/// and the buffer `buf` of the decryption (see `Workspace`).
 #[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
-pub fn crypto_kem_dec(
-    key: &mut [u8; CRYPTO_BYTES],
-    c: &[u8; CRYPTO_CIPHERTEXTBYTES],
-    sk: &[u8; CRYPTO_SECRETKEYBYTES],
-) -> Result<u8, Box<dyn error::Error>> {
+#[cfg(not(feature = "encap-only"))]
+fn crypto_kem_dec_with_key(
+    key: &mut SharedSecret,
+    c: &Ciphertext,
+    sk: &SecretKey,
+    dk: &DecryptionKey,
+    cond: &impl Conditions,
+    ctx: &[u8],
+    buf: &mut [Gf; 1 << GFBITS],
+) -> Result<u8, Error> {
+    let (key, c, sk) = (&mut key.0, &c.0, &sk.0);
+
     let mut conf = [0u8; 32];
     let mut two_e = [0u8; 1 + SYS_N / 8];
     two_e[0] = 2;
 
     let mut preimage = [0u8; 1 + SYS_N / 8 + (SYND_BYTES + 32)];
 
-    let ret_decrypt: u8 = decrypt(
+    let ret_decrypt: u8 = dk.decrypt(
         sub!(mut two_e, 1, SYS_N / 8),
-        sub!(sk, 40, IRR_BYTES + COND_BYTES),
         sub!(c, 0, SYND_BYTES),
+        cond,
+        buf,
     )?;
 
     shake256(&mut conf[0..32], &two_e)?;
@@ -145,35 +294,46 @@ pub fn crypto_kem_dec(
         ret_confirm |= conf[i] ^ c[SYND_BYTES + i];
     }
 
-    let mut m = (ret_decrypt | ret_confirm) as u16;
-    m = m.wrapping_sub(1);
-    m >>= 8;
+    let m = zero_mask((ret_decrypt | ret_confirm) as u64) as u8;
 
-    preimage[0] = (m & 1) as u8;
+    preimage[0] = m & 1;
 
     let s = &sk[40 + IRR_BYTES + COND_BYTES..];
 
     for i in 0..SYS_N / 8 {
-        preimage[1 + i] = (!m as u8 & s[i]) | (m as u8 & two_e[1 + i]);
+        preimage[1 + i] = (!m & s[i]) | (m & two_e[1 + i]);
     }
 
     (&mut preimage[1 + (SYS_N / 8)..])[0..SYND_BYTES + 32].copy_from_slice(&c[0..SYND_BYTES + 32]);
 
-    shake256(&mut key[0..32], &preimage)?;
+    shake256_concat(&mut key[0..32], &[&preimage, ctx])?;
+
+    #[cfg(feature = "zeroize")]
+    {
+        conf.zeroize();
+        two_e.zeroize();
+        preimage.zeroize();
+    }
 
     Ok(0)
 }
 
-/// KEM Decapsulation.
-///
-/// Given a secret key `sk` and a ciphertext `c`,
-/// determine the shared text `key` negotiated by both parties.
+/// Like `crypto_kem_dec`, but with the decryption key `dk` of `sk` (see `decryption_key`),
+/// the context `ctx` appended to the input of the hash deriving the shared key
+/// and the buffer `buf` of the decryption (see `Workspace`).
 #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-pub fn crypto_kem_dec(
-    key: &mut [u8; CRYPTO_BYTES],
-    c: &[u8; CRYPTO_CIPHERTEXTBYTES],
-    sk: &[u8; CRYPTO_SECRETKEYBYTES],
-) -> Result<u8, Box<dyn error::Error>> {
+#[cfg(not(feature = "encap-only"))]
+fn crypto_kem_dec_with_key(
+    key: &mut SharedSecret,
+    c: &Ciphertext,
+    sk: &SecretKey,
+    dk: &DecryptionKey,
+    cond: &impl Conditions,
+    ctx: &[u8],
+    buf: &mut [Gf; 1 << GFBITS],
+) -> Result<u8, Error> {
+    let (key, c, sk) = (&mut key.0, &c.0, &sk.0);
+
     let mut conf = [0u8; 32];
     let mut two_e = [0u8; 1 + SYS_N / 8];
     two_e[0] = 2;
@@ -182,10 +342,11 @@ pub fn crypto_kem_dec(
 
     let padding_ok = check_c_padding(sub!(c, 0, SYND_BYTES));
 
-    let ret_decrypt: u8 = decrypt(
+    let ret_decrypt: u8 = dk.decrypt(
         sub!(mut two_e, 1, SYS_N / 8),
-        sub!(sk, 40, IRR_BYTES + COND_BYTES),
         sub!(c, 0, SYND_BYTES),
+        cond,
+        buf,
     )?;
 
     shake256(&mut conf[0..32], &two_e)?;
@@ -195,33 +356,92 @@ pub fn crypto_kem_dec(
         ret_confirm |= conf[i] ^ c[SYND_BYTES + i];
     }
 
-    let mut m = (ret_decrypt | ret_confirm) as u16;
-    m = m.wrapping_sub(1);
-    m >>= 8;
+    let m = zero_mask((ret_decrypt | ret_confirm) as u64) as u8;
 
-    preimage[0] = (m & 1) as u8;
+    preimage[0] = m & 1;
 
     let s = &sk[40 + IRR_BYTES + COND_BYTES..];
 
     for i in 0..SYS_N / 8 {
-        preimage[1 + i] = (!m as u8 & s[i]) | (m as u8 & two_e[1 + i]);
+        preimage[1 + i] = (!m & s[i]) | (m & two_e[1 + i]);
     }
 
     (&mut preimage[1 + (SYS_N / 8)..])[0..SYND_BYTES + 32].copy_from_slice(&c[0..SYND_BYTES + 32]);
 
-    shake256(&mut key[0..32], &preimage)?;
+    shake256_concat(&mut key[0..32], &[&preimage, ctx])?;
+
+    #[cfg(feature = "zeroize")]
+    {
+        conf.zeroize();
+        two_e.zeroize();
+        preimage.zeroize();
+    }
 
     // clear outputs (set to all 1's) if padding bits are not all zero
 
     let mask = padding_ok;
 
-    for i in 0..32 {
-        key[i] |= mask;
+    for b in key[..32].iter_mut() {
+        *b |= mask;
     }
 
     Ok(padding_ok)
 }
 
+/// Like `crypto_kem_enc_with_context`, but a public key with non-zero padding bits (mceliece6960119)
+/// is rejected with `Error::InvalidPadding` instead of resulting in an all-zero shared key
+#[cfg(not(feature = "decap-only"))]
+fn encapsulate_checked(
+    c: &mut Ciphertext,
+    key: &mut SharedSecret,
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+    ctx: &[u8],
+) -> Result<(), Error> {
+    check_public_key_bytes(&pk.0)?;
+    crypto_kem_enc_with_context(c, key, pk, e, ctx)?;
+    Ok(())
+}
+
+/// Like `crypto_kem_dec_with_key`, but a ciphertext with non-zero padding bits (mceliece6960119)
+/// is rejected with `Error::InvalidPadding` instead of resulting in an all-one shared key
+#[cfg(not(feature = "encap-only"))]
+fn decapsulate_checked(
+    key: &mut SharedSecret,
+    c: &Ciphertext,
+    sk: &SecretKey,
+    dk: &DecryptionKey,
+    cond: &impl Conditions,
+    ctx: &[u8],
+    buf: &mut [Gf; 1 << GFBITS],
+) -> Result<(), Error> {
+    check_ciphertext_bytes(&c.0)?;
+    crypto_kem_dec_with_key(key, c, sk, dk, cond, ctx, buf)?;
+    Ok(())
+}
+
+/// The maximum number of attempts of the key generation to find an irreducible polynomial
+/// and a public key in systematic form. Exceeding it is practically impossible
+/// (each attempt succeeds with a probability of about 29 % even for the non-f variants).
+pub const KEYGEN_MAX_ATTEMPTS: usize = 256;
+
+/// A phase of the key generation, as reported by `crypto_kem_keypair_with_progress`
+/// and performed by `KeygenState::step`
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KeygenPhase {
+    /// The attempt with the given number (starting at 1, at most `KEYGEN_MAX_ATTEMPTS`) starts
+    Attempt(usize),
+    /// The irreducible Goppa polynomial is generated
+    Polynomial,
+    /// The field ordering is generated and the public key is brought into systematic form
+    /// (Gaussian elimination), which fails for about 71 % of the attempts of the non-f variants
+    PublicKey,
+    /// The control bits of the Beneš network are computed from the field ordering
+    ControlBits,
+}
+
 /// KEM Keypair generation.
 ///
 /// Generate some public and secret key.
@@ -231,177 +451,964 @@ pub fn crypto_kem_dec(
 /// The structure of the secret key is given by the following segments:
 /// (32 bytes seed, 8 bytes pivots, IRR_BYTES bytes, COND_BYTES bytes, SYS_N/8 bytes).
 /// The structure of the public key is simple: a matrix of PK_NROWS times PK_ROW_BYTES bytes.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
 pub fn crypto_kem_keypair(
-    pk: &mut [u8; CRYPTO_PUBLICKEYBYTES],
-    sk: &mut [u8; CRYPTO_SECRETKEYBYTES],
-    rng: &mut impl RNGState,
-) -> Result<(), Box<dyn error::Error>> {
-    let mut seed = [0u8; 33];
-    seed[0] = 64;
-
-    const S_BASE: usize = 32 + 8 + IRR_BYTES + COND_BYTES;
-
-    const SEED: usize = SYS_N / 8 + (1 << GFBITS) * 4 + SYS_T * 2;
-    const IRR_POLYS: usize = SYS_N / 8 + (1 << GFBITS) * 4;
-    const PERM: usize = SYS_N / 8;
-
-    let mut r = [0u8; SEED + 32];
-
-    #[cfg(any(
-        feature = "mceliece348864f",
-        feature = "mceliece460896f",
-        feature = "mceliece6688128f",
-        feature = "mceliece6960119f",
-        feature = "mceliece8192128f"
-    ))]
-    let mut pivots = 0u64;
-    #[cfg(any(
-        feature = "mceliece348864",
-        feature = "mceliece460896",
-        feature = "mceliece6688128",
-        feature = "mceliece6960119",
-        feature = "mceliece8192128"
-    ))]
-    let pivots: u64;
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(), Error> {
+    let mut seed = [0u8; 32];
+    rng.try_fill_bytes(&mut seed)?;
+
+    crypto_kem_keypair_from_seed(pk, sk, &seed)
+}
+
+/// KEM Keypair generation from a 32-byte seed.
+///
+/// The seed is expanded deterministically with SHAKE256 as specified (the seed δ of the specification).
+/// Hence, the same seed always results in the same key pair and a key pair can be restored from its seed.
+/// The seed must be kept as secret as the secret key itself.
+///
+/// The first 32 bytes of the secret key contain the seed of the successful iteration,
+/// which regenerates the same key pair.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn crypto_kem_keypair_from_seed(
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    delta: &[u8; 32],
+) -> Result<(), Error> {
+    keypair_attempts(pk, sk, delta, &mut Workspace::new(), &mut |_| true)
+}
 
-    let mut f = [0u16; SYS_T];
-    let mut irr = [0u16; SYS_T];
+/// KEM Keypair generation which can be cancelled.
+///
+/// Like `crypto_kem_keypair`, but `should_continue` is called before each attempt of the key generation.
+/// As soon as it returns `false`, the key generation stops and fails with `Error::Cancelled`.
+/// Since each attempt takes about as long as a successful key generation, this bounds the time
+/// to react to e.g. a cancelled UI operation or a shutting down service.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn crypto_kem_keypair_cancellable(
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    rng: &mut impl CryptoRngCore,
+    mut should_continue: impl FnMut() -> bool,
+) -> Result<(), Error> {
+    let mut seed = [0u8; 32];
+    rng.try_fill_bytes(&mut seed)?;
+
+    let mut hook = |phase| !matches!(phase, KeygenPhase::Attempt(_)) || should_continue();
+    keypair_attempts(pk, sk, &seed, &mut Workspace::new(), &mut hook)
+}
 
-    let mut perm = [0u32; 1 << GFBITS];
-    let mut pi = [0i16; 1 << GFBITS];
+/// KEM Keypair generation reporting its progress.
+///
+/// Like `crypto_kem_keypair`, but `progress` is called when a phase of the key generation starts,
+/// e.g. to show a progress bar or to log the number of attempts.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn crypto_kem_keypair_with_progress(
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    rng: &mut impl CryptoRngCore,
+    mut progress: impl FnMut(KeygenPhase),
+) -> Result<(), Error> {
+    let mut seed = [0u8; 32];
+    rng.try_fill_bytes(&mut seed)?;
+
+    let mut hook = |phase| {
+        progress(phase);
+        true
+    };
+    keypair_attempts(pk, sk, &seed, &mut Workspace::new(), &mut hook)
+}
 
-    rng.randombytes(&mut seed[1..])?;
+/// KEM Keypair generation with the memory of the workspace `ws`.
+///
+/// Like `crypto_kem_keypair`, but the matrix of the public key computation is computed in `ws`
+/// instead of being allocated for the key generation. Hence, the same workspace can be reused
+/// for many key generations.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_with(
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    rng: &mut impl CryptoRngCore,
+    ws: &mut Workspace,
+) -> Result<(), Error> {
+    let mut seed = [0u8; 32];
+    rng.try_fill_bytes(&mut seed)?;
+
+    keypair_attempts(pk, sk, &seed, ws, &mut |_| true)
+}
 
-    loop {
-        // expanding and updating the seed
-        shake256(&mut r[..], &seed[0..33])?;
+/// Runs the key generation starting with seed `delta` to completion in the workspace `ws`.
+/// `hook` is called at the start of each phase and cancels the key generation by returning `false`.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+fn keypair_attempts(
+    pk: &mut PublicKey,
+    sk: &mut SecretKey,
+    delta: &[u8; 32],
+    ws: &mut Workspace,
+    hook: &mut impl FnMut(KeygenPhase) -> bool,
+) -> Result<(), Error> {
+    let mut state = KeygenState::from_seed(delta);
+    while let Some(phase) = state.phase() {
+        if !hook(phase) {
+            return Err(Error::Cancelled);
+        }
+        state.step_with(pk, sk, ws)?;
+    }
+    Ok(())
+}
 
-        sk[..32].clone_from_slice(&seed[1..]);
-        seed[1..].clone_from_slice(&r[r.len() - 32..]);
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+const KEYGEN_SEED: usize = SYS_N / 8 + (1 << GFBITS) * 4 + SYS_T * 2;
 
-        // generating irreducible polynomial
+/// The key generation as state machine, which performs one `KeygenPhase` per call of `step`.
+///
+/// This allows to spread the key generation across many time slices, e.g. for cooperative
+/// schedulers or microcontrollers with a watchdog. The phases are not split further: the step performing
+/// `KeygenPhase::PublicKey` runs the whole Gaussian elimination at once, and the step performing
+/// `KeygenPhase::ControlBits` all of the control bits. Each of them takes almost half of a successful
+/// key generation, hence a time slice (or watchdog period) must be long enough for it.
+///
+/// The state holds the seed of the key generation and must be kept as secret as the secret key.
+/// The same key pair as `crypto_kem_keypair_from_seed` results for the same seed.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub struct KeygenState {
+    seed: [u8; 33],
+    r: [u8; KEYGEN_SEED + 32],
+    pi: [i16; 1 << GFBITS],
+    pivots: u64,
+    attempt: usize,
+    next: Option<KeygenPhase>,
+    done: bool,
+}
 
-        for (i, chunk) in r[IRR_POLYS..SEED].chunks(2).enumerate() {
-            f[i] = load_gf(sub!(chunk, 0, 2));
-        }
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+impl KeygenState {
+    /// Prepares the key generation with a seed sampled from `rng`
+    pub fn new(rng: &mut impl CryptoRngCore) -> Result<Self, Error> {
+        let mut delta = [0u8; 32];
+        rng.try_fill_bytes(&mut delta)?;
+        let state = Self::from_seed(&delta);
+        #[cfg(feature = "zeroize")]
+        delta.zeroize();
+        Ok(state)
+    }
 
-        if genpoly_gen(&mut irr, &mut f) != 0 {
-            continue;
+    /// Prepares the key generation from a 32-byte seed, see `crypto_kem_keypair_from_seed`
+    pub fn from_seed(delta: &[u8; 32]) -> Self {
+        let mut seed = [0u8; 33];
+        seed[0] = 64;
+        seed[1..].copy_from_slice(delta);
+
+        Self {
+            seed,
+            r: [0u8; KEYGEN_SEED + 32],
+            pi: [0i16; 1 << GFBITS],
+            pivots: 0,
+            attempt: 0,
+            next: Some(KeygenPhase::Attempt(1)),
+            done: false,
         }
+    }
 
-        for (i, chunk) in sk[40..40 + IRR_BYTES].chunks_mut(2).enumerate() {
-            store_gf(sub!(mut chunk, 0, 2), irr[i]);
-        }
+    /// The phase performed by the next call of `step`, or `None` if the key generation has
+    /// finished (successfully or by exceeding `KEYGEN_MAX_ATTEMPTS`)
+    pub fn phase(&self) -> Option<KeygenPhase> {
+        self.next
+    }
 
-        // generating permutation
+    /// Performs the next phase of the key generation and returns whether the key pair is complete.
+    ///
+    /// `pk` and `sk` hold intermediate results between the steps,
+    /// hence the same keys must be passed to all calls.
+    /// Fails with `Error::RetryBoundExceeded` when the last attempt fails.
+    /// Once finished, further calls do no work and return the same result.
+    pub fn step(&mut self, pk: &mut PublicKey, sk: &mut SecretKey) -> Result<bool, Error> {
+        self.step_with(pk, sk, &mut Workspace::new())
+    }
 
-        for (i, chunk) in r[PERM..IRR_POLYS].chunks(4).enumerate() {
-            perm[i] = load4(sub!(chunk, 0, 4));
-        }
+    /// Like `step`, but with the memory of the workspace `ws` (see `keypair_with`).
+    /// The workspace need not be the same for all calls.
+    pub fn step_with(
+        &mut self,
+        pk: &mut PublicKey,
+        sk: &mut SecretKey,
+        ws: &mut Workspace,
+    ) -> Result<bool, Error> {
+        self.advance(sk, &mut StoredPublicKey { pk: &mut pk.0, ws })
+    }
 
-        #[cfg(any(
-            feature = "mceliece348864f",
-            feature = "mceliece460896f",
-            feature = "mceliece6688128f",
-            feature = "mceliece6960119f",
-            feature = "mceliece8192128f"
-        ))]
-        {
-            if pk_gen(
-                pk,
-                sub!(mut sk, 40, IRR_BYTES),
-                &mut perm,
-                &mut pi,
-                &mut pivots,
-            )? != 0
-            {
-                continue;
+    /// Performs the next phase, computing the public key with `out`
+    fn advance(
+        &mut self,
+        sk: &mut SecretKey,
+        out: &mut impl PublicKeyOutput,
+    ) -> Result<bool, Error> {
+        const S_BASE: usize = 32 + 8 + IRR_BYTES + COND_BYTES;
+        const IRR_POLYS: usize = SYS_N / 8 + (1 << GFBITS) * 4;
+        const PERM: usize = SYS_N / 8;
+
+        let sk = &mut sk.0;
+
+        let phase = match self.next {
+            Some(phase) => phase,
+            None if self.done => return Ok(true),
+            None => return Err(Error::RetryBoundExceeded),
+        };
+
+        match phase {
+            KeygenPhase::Attempt(attempt) => {
+                self.attempt = attempt;
+
+                // expanding and updating the seed
+                shake256(&mut self.r[..], &self.seed[0..33])?;
+
+                sk[..32].clone_from_slice(&self.seed[1..]);
+                self.seed[1..].clone_from_slice(&self.r[KEYGEN_SEED..]);
+
+                self.next = Some(KeygenPhase::Polynomial);
             }
-        }
-        #[cfg(any(
-            feature = "mceliece348864",
-            feature = "mceliece460896",
-            feature = "mceliece6688128",
-            feature = "mceliece6960119",
-            feature = "mceliece8192128"
-        ))]
-        {
-            if pk_gen(pk, sub!(mut sk, 40, IRR_BYTES), &mut perm, &mut pi)? != 0 {
-                continue;
+            KeygenPhase::Polynomial => {
+                // generating irreducible polynomial
+                let mut f = [0u16; SYS_T];
+                let mut irr = [0u16; SYS_T];
+
+                for (i, chunk) in self.r[IRR_POLYS..KEYGEN_SEED].chunks(2).enumerate() {
+                    f[i] = load_gf(sub!(chunk, 0, 2));
+                }
+
+                if genpoly_gen(&mut irr, &f) != 0 {
+                    return self.retry();
+                }
+
+                for (i, chunk) in sk[40..40 + IRR_BYTES].chunks_mut(2).enumerate() {
+                    store_gf(sub!(mut chunk, 0, 2), irr[i]);
+                }
+
+                self.next = Some(KeygenPhase::PublicKey);
+            }
+            KeygenPhase::PublicKey => {
+                // generating permutation
+                let mut perm = [0u32; 1 << GFBITS];
+
+                for (i, chunk) in self.r[PERM..IRR_POLYS].chunks(4).enumerate() {
+                    perm[i] = load4(sub!(chunk, 0, 4));
+                }
+
+                let ret = out.pk_gen(
+                    sub!(sk, 40, IRR_BYTES),
+                    &perm,
+                    &mut self.pi,
+                    #[cfg(semi_systematic)]
+                    &mut self.pivots,
+                )?;
+
+                if ret != 0 {
+                    return self.retry();
+                }
+
+                self.next = Some(KeygenPhase::ControlBits);
+            }
+            KeygenPhase::ControlBits => {
+                controlbitsfrompermutation(
+                    &mut sk[(40 + IRR_BYTES)..(40 + IRR_BYTES + COND_BYTES)],
+                    &self.pi,
+                    GFBITS,
+                    1 << GFBITS,
+                );
+
+                // storing the random string s
+
+                sk[S_BASE..(S_BASE + SYS_N / 8)].clone_from_slice(&self.r[0..SYS_N / 8]);
+
+                // storing positions of the 32 pivots
+
+                #[cfg(any(
+                    feature = "mceliece348864",
+                    feature = "mceliece460896",
+                    feature = "mceliece6688128",
+                    feature = "mceliece6960119",
+                    feature = "mceliece8192128"
+                ))]
+                {
+                    self.pivots = 0xFFFFFFFF;
+                }
+
+                store8(sub!(mut sk, 32, 8), self.pivots);
+
+                self.next = None;
+                self.done = true;
+                return Ok(true);
             }
         }
 
-        controlbitsfrompermutation(
-            &mut sk[(40 + IRR_BYTES)..(40 + IRR_BYTES + COND_BYTES)],
-            &mut pi,
-            GFBITS,
-            1 << GFBITS,
-        );
+        Ok(false)
+    }
 
-        // storing the random string s
+    /// Continues with the next attempt after the current one failed
+    fn retry(&mut self) -> Result<bool, Error> {
+        if self.attempt >= KEYGEN_MAX_ATTEMPTS {
+            self.next = None;
+            return Err(Error::RetryBoundExceeded);
+        }
+        self.next = Some(KeygenPhase::Attempt(self.attempt + 1));
+        Ok(false)
+    }
+}
 
-        sk[S_BASE..(S_BASE + SYS_N / 8)].clone_from_slice(&r[0..SYS_N / 8]);
+#[cfg(all(
+    feature = "zeroize",
+    not(any(feature = "encap-only", feature = "decap-only"))
+))]
+impl Drop for KeygenState {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+        self.r.zeroize();
+        self.pi.zeroize();
+        self.pivots.zeroize();
+    }
+}
 
-        // storing positions of the 32 pivots
+/// The computation of the public key in the phase `KeygenPhase::PublicKey`
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+trait PublicKeyOutput {
+    /// Computes the public key of the Goppa polynomial `sk` like `pk_gen`
+    fn pk_gen(
+        &mut self,
+        sk: &[u8; IRR_BYTES],
+        perm: &[u32; 1 << GFBITS],
+        pi: &mut [i16; 1 << GFBITS],
+        #[cfg(semi_systematic)] pivots: &mut u64,
+    ) -> Result<i32, Error>;
+}
 
-        #[cfg(any(
-            feature = "mceliece348864",
-            feature = "mceliece460896",
-            feature = "mceliece6688128",
-            feature = "mceliece6960119",
-            feature = "mceliece8192128"
-        ))]
-        {
-            pivots = 0xFFFFFFFF;
+/// Stores the public key in `pk`, computing the matrix in `ws`
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+struct StoredPublicKey<'a> {
+    pk: &'a mut [u8; CRYPTO_PUBLICKEYBYTES],
+    ws: &'a mut Workspace,
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+impl PublicKeyOutput for StoredPublicKey<'_> {
+    fn pk_gen(
+        &mut self,
+        sk: &[u8; IRR_BYTES],
+        perm: &[u32; 1 << GFBITS],
+        pi: &mut [i16; 1 << GFBITS],
+        #[cfg(semi_systematic)] pivots: &mut u64,
+    ) -> Result<i32, Error> {
+        pk_gen(
+            self.pk,
+            self.ws.matrix(),
+            sk,
+            perm,
+            pi,
+            #[cfg(semi_systematic)]
+            pivots,
+        )
+    }
+}
+
+/// Passes the rows of the public key to `writer`, computing the matrix in `rows`
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+struct StreamedPublicKey<W: FnMut(&[u8])> {
+    rows: Box<StreamMatrix>,
+    writer: W,
+}
+
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+impl<W: FnMut(&[u8])> PublicKeyOutput for StreamedPublicKey<W> {
+    fn pk_gen(
+        &mut self,
+        sk: &[u8; IRR_BYTES],
+        perm: &[u32; 1 << GFBITS],
+        pi: &mut [i16; 1 << GFBITS],
+        #[cfg(semi_systematic)] pivots: &mut u64,
+    ) -> Result<i32, Error> {
+        pk_gen_streaming(
+            &mut self.writer,
+            &mut self.rows,
+            sk,
+            perm,
+            pi,
+            #[cfg(semi_systematic)]
+            pivots,
+        )
+    }
+}
+
+#[cfg(all(
+    feature = "zeroize",
+    not(any(feature = "encap-only", feature = "decap-only"))
+))]
+impl<W: FnMut(&[u8])> Drop for StreamedPublicKey<W> {
+    fn drop(&mut self) {
+        self.rows.iter_mut().for_each(|row| row.zeroize());
+    }
+}
+
+/// KEM Keypair generation returning the generated keys.
+///
+/// This is a convenience wrapper for `crypto_kem_keypair`.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair(rng: &mut impl CryptoRngCore) -> Result<(PublicKey, SecretKey), Error> {
+    let mut pk = PublicKey::zero();
+    let mut sk = SecretKey::zero();
+    crypto_kem_keypair(&mut pk, &mut sk, rng)?;
+    Ok((pk, sk))
+}
+
+/// KEM Keypair generation from a 32-byte seed returning the generated keys.
+///
+/// This is a convenience wrapper for `crypto_kem_keypair_from_seed`.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_from_seed(seed: &[u8; 32]) -> Result<(PublicKey, SecretKey), Error> {
+    let mut pk = PublicKey::zero();
+    let mut sk = SecretKey::zero();
+    crypto_kem_keypair_from_seed(&mut pk, &mut sk, seed)?;
+    Ok((pk, sk))
+}
+
+/// KEM Keypair generation passing the public key to `writer` row by row, returning the secret key.
+///
+/// Like `keypair`, but instead of being returned, the public key is passed to `writer` in m · t rows
+/// of equal length (see `Params`), which concatenate to the public key, e.g. to write it to flash or a socket directly.
+/// The rows are computed (and passed) only once an attempt succeeded, hence `writer` receives exactly
+/// one public key. The key pair equals the one of `keypair` for the same `rng`.
+///
+/// The rows of the public key are final only at the end of the Gaussian elimination, so the public key is
+/// recomputed in parts from the inverse of the left block of the matrix. Thus, instead of the public key
+/// and the matrix of `keypair_with`, the key generation needs about 150 KB (mceliece348864) to 700 KB
+/// (mceliece6688128 and mceliece8192128) of heap memory. The recomputation takes place for the successful
+/// attempt only, which makes the key generation up to about twice as slow (for the f-variants).
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_streaming(
+    rng: &mut impl CryptoRngCore,
+    writer: impl FnMut(&[u8]),
+) -> Result<SecretKey, Error> {
+    let mut sk = SecretKey::zero();
+    let mut state = KeygenState::new(rng)?;
+    let mut out = StreamedPublicKey {
+        rows: bytemuck::zeroed_box(),
+        writer,
+    };
+
+    while state.phase().is_some() {
+        state.advance(&mut sk, &mut out)?;
+    }
+    Ok(sk)
+}
+
+/// KEM Keypair generation returning heap-allocated keys.
+///
+/// The keys are zero-initialized on the heap and filled in place,
+/// so unlike `keypair` they are never held on the stack.
+/// Use this for the larger variants whose public keys exceed common stack sizes.
+///
+/// The zero-initialization uses a zeroed allocation (i.e. `calloc`), not an explicit memset.
+/// For allocations as large as a public key, the allocator obtains fresh pages which
+/// the operating system provides already zeroed, hence there is no cost to skip.
+/// Uninitialized (`MaybeUninit`) outputs are not offered, since they require unsafe code,
+/// which this crate forbids.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_boxed(
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Box<PublicKey>, Box<SecretKey>), Error> {
+    let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+    let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+    crypto_kem_keypair(&mut pk, &mut sk, rng)?;
+    Ok((pk, sk))
+}
+
+/// KEM Keypair generation returning heap-allocated keys, which can be cancelled.
+///
+/// This is a convenience wrapper for `crypto_kem_keypair_cancellable`, see also `keypair_boxed`.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_boxed_cancellable(
+    rng: &mut impl CryptoRngCore,
+    should_continue: impl FnMut() -> bool,
+) -> Result<(Box<PublicKey>, Box<SecretKey>), Error> {
+    let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+    let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+    crypto_kem_keypair_cancellable(&mut pk, &mut sk, rng, should_continue)?;
+    Ok((pk, sk))
+}
+
+/// KEM Keypair generation racing `threads` seeds concurrently, returning heap-allocated keys.
+///
+/// Each attempt of the key generation fails with a probability of about 71 % for the non-f variants,
+/// which makes the latency of `keypair` vary a lot. Here, each thread runs the key generation from its own seed
+/// sampled from `rng`, and the first key pair found is returned, while the other threads are cancelled.
+/// Hence, the result depends on the scheduling and is not reproducible from `rng`.
+/// `threads` is at least 1.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_parallel(
+    rng: &mut impl CryptoRngCore,
+    threads: usize,
+) -> Result<(Box<PublicKey>, Box<SecretKey>), Error> {
+    let mut seeds = vec![[0u8; 32]; threads.max(1)];
+    for seed in seeds.iter_mut() {
+        rng.try_fill_bytes(seed)?;
+    }
+
+    let found = AtomicBool::new(false);
+    let results: Vec<Result<Option<_>, Error>> = thread::scope(|scope| {
+        let handles: Vec<_> = seeds
+            .iter()
+            .map(|seed| {
+                let found = &found;
+                scope.spawn(move || {
+                    let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+                    let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+                    let mut hook = |_| !found.load(Ordering::Relaxed);
+                    match keypair_attempts(&mut pk, &mut sk, seed, &mut Workspace::new(), &mut hook)
+                    {
+                        Ok(()) if !found.swap(true, Ordering::Relaxed) => Ok(Some((pk, sk))),
+                        Ok(()) | Err(Error::Cancelled) => Ok(None),
+                        Err(e) => Err(e),
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or(Err(Error::Internal("key generation thread panicked")))
+            })
+            .collect()
+    });
+
+    #[cfg(feature = "zeroize")]
+    seeds.zeroize();
+
+    let mut error = None;
+    for result in results {
+        match result {
+            Ok(Some(keys)) => return Ok(keys),
+            Ok(None) => {}
+            Err(e) => error = error.or(Some(e)),
         }
+    }
+    Err(error.unwrap_or(Error::Internal("no key generation thread succeeded")))
+}
+
+/// A heap-allocated key pair, as returned by `keypair_batch`
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+type BoxedKeyPair = (Box<PublicKey>, Box<SecretKey>);
 
-        store8(sub!(mut sk, 32, 8), pivots);
+/// KEM Keypair generation of `n` independent key pairs on all available threads, returning heap-allocated keys.
+///
+/// The seeds are sampled from `rng` upfront, and each thread generates the key pairs of a contiguous share
+/// of the seeds one after another. Thus, the key pairs are returned in the order of the seeds and equal
+/// the key pairs of `keypair_from_seed`, i.e. the result is reproducible from `rng` (unlike `keypair_parallel`).
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_batch(n: usize, rng: &mut impl CryptoRngCore) -> Result<Vec<BoxedKeyPair>, Error> {
+    let mut seeds = vec![[0u8; 32]; n];
+    for seed in seeds.iter_mut() {
+        rng.try_fill_bytes(seed)?;
+    }
 
-        break;
+    let threads = thread::available_parallelism().map_or(1, |t| t.get());
+    let share = n.div_ceil(threads).max(1);
+
+    let results: Vec<Result<Vec<_>, Error>> = thread::scope(|scope| {
+        let handles: Vec<_> = seeds
+            .chunks(share)
+            .map(|seeds| {
+                scope.spawn(move || {
+                    let mut ws = Workspace::new();
+                    seeds
+                        .iter()
+                        .map(|seed| {
+                            let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+                            let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+                            keypair_attempts(&mut pk, &mut sk, seed, &mut ws, &mut |_| true)?;
+                            Ok((pk, sk))
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or(Err(Error::Internal("key generation thread panicked")))
+            })
+            .collect()
+    });
+
+    #[cfg(feature = "zeroize")]
+    seeds.zeroize();
+
+    let mut keys = Vec::with_capacity(n);
+    for result in results {
+        keys.extend(result?);
     }
+    Ok(keys)
+}
+
+/// KEM Encapsulation returning the ciphertext and the shared secret.
+///
+/// This is a convenience wrapper for `crypto_kem_enc`. Unlike the latter, it fails with `Error::InvalidPadding`
+/// if padding bits of the public key are set (only mceliece6960119 has padding).
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate(
+    pk: &PublicKey,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+    encapsulate_with_coins(pk, &e)
+}
+
+/// KEM Encapsulation reading the public key from `pk` row by row.
+///
+/// Like `encapsulate`, but the public key is read from `pk` in m · t rows of equal length (see `Params`),
+/// which concatenate to the public key, e.g. from external flash or a socket. Only a block of eight rows
+/// (at most 7 KB) is held in memory at a time. A public key with non-zero padding bits (mceliece6960119) is rejected
+/// with `Error::InvalidPadding`, after it has been read completely. Failing reads result in `Error::Io`.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_streaming(
+    pk: &mut impl Read,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+
+    let mut c = Ciphertext::zero();
+    let mut key = SharedSecret::zero();
+    encrypt_streaming(&mut c.0, pk, &e)?;
+    hash_encapsulation(&mut c.0, &mut key.0, &e, &[])?;
+    Ok((c, key))
+}
+
+/// Like `encapsulate`, but for a public key in column-major layout (see `PublicKeyColumns`).
+///
+/// The ciphertext and shared secret are the same as those of `encapsulate` with the row-major public key
+/// (and the same random numbers), hence a party may store and use its peers' keys in either layout.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_columns(
+    pk: &PublicKeyColumns,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+
+    check_public_key_columns_bytes(&pk.0)?;
+
+    let mut c = Ciphertext::zero();
+    let mut key = SharedSecret::zero();
+    encrypt_columns(&mut c.0, &pk.0, &e)?;
+    hash_encapsulation(&mut c.0, &mut key.0, &e, &[])?;
+    Ok((c, key))
+}
+
+/// KEM Encapsulation of `n` independent shared secrets to the same public key `pk`.
+///
+/// This is a convenience wrapper for `n` calls of `encapsulate`. Classic McEliece does not hash
+/// the public key during encapsulation and every error vector must be sampled independently,
+/// hence there is no per-key work to share between the encapsulations.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_batch(
+    pk: &PublicKey,
+    n: usize,
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<(Ciphertext, SharedSecret)>, Error> {
+    (0..n).map(|_| encapsulate(pk, rng)).collect()
+}
+
+/// KEM Encapsulation of one shared secret to each of the public keys `pks`
+/// (e.g. the members of a group or the escrow agents of a backup).
+///
+/// Returns the ciphertexts and shared secrets in the order of `pks`.
+/// All error vectors are sampled from `rng`, and the scratch buffers of the encapsulation
+/// are stack arrays of fixed size, i.e. no allocation happens besides the returned vector.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_many(
+    pks: &[&PublicKey],
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<(Ciphertext, SharedSecret)>, Error> {
+    pks.iter().map(|pk| encapsulate(pk, rng)).collect()
+}
+
+/// KEM Encapsulation with a given error vector returning the ciphertext and the shared secret.
+///
+/// This is a convenience wrapper for `crypto_kem_enc_with_coins`, which rejects padding bits like `encapsulate`.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_with_coins(
+    pk: &PublicKey,
+    e: &[u8; CRYPTO_ERRORVECTORBYTES],
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut c = Ciphertext::zero();
+    let mut key = SharedSecret::zero();
+    encapsulate_checked(&mut c, &mut key, pk, e, &[])?;
+    Ok((c, key))
+}
 
+/// KEM Encapsulation sampling the error vector without rejection, returning the ciphertext and the shared secret.
+///
+/// Like `encapsulate`, but the positions of the error vector are obtained by sorting random keys,
+/// so the running time does not depend on the random numbers (which the rejection sampling of the
+/// specification reveals through the number of attempts). The ciphertexts differ from those of
+/// `encapsulate` (and the KATs) for the same RNG, but decapsulate with `decapsulate` as usual.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_without_rejection(
+    pk: &PublicKey,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e_without_rejection(&mut e, rng)?;
+    encapsulate_with_coins(pk, &e)
+}
+
+/// KEM Encapsulation binding the shared secret to the application-supplied context `ctx`
+/// (e.g. a protocol identifier), returning the ciphertext and the shared secret.
+///
+/// The context is appended to the input of the hash deriving the shared secret.
+/// Hence, the shared secret only matches if the decapsulation uses the same context
+/// (see `decapsulate_with_context`), which prevents reusing it across protocols.
+/// With an empty context, the shared secret is the one of `encapsulate`.
+#[cfg(not(feature = "decap-only"))]
+pub fn encapsulate_with_context(
+    pk: &PublicKey,
+    ctx: &[u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext, SharedSecret), Error> {
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+
+    let mut c = Ciphertext::zero();
+    let mut key = SharedSecret::zero();
+    encapsulate_checked(&mut c, &mut key, pk, &e, ctx)?;
+    Ok((c, key))
+}
+
+/// KEM Decapsulation returning the shared secret.
+///
+/// This is a convenience wrapper for `crypto_kem_dec`. Unlike the latter, it fails with `Error::InvalidPadding`
+/// if padding bits of the ciphertext are set (only mceliece6960119 has padding).
+#[cfg(not(feature = "encap-only"))]
+pub fn decapsulate(sk: &SecretKey, c: &Ciphertext) -> Result<SharedSecret, Error> {
+    let mut key = SharedSecret::zero();
+    decapsulate_checked(
+        &mut key,
+        c,
+        sk,
+        &decryption_key(sk)?,
+        sk.control_bits(),
+        &[],
+        &mut [0; 1 << GFBITS],
+    )?;
+    Ok(key)
+}
+
+/// KEM Decapsulation with an expanded secret key (see `SecretKey::expand`) returning the shared secret.
+///
+/// The shared secret is the one of `decapsulate` with the secret key `esk` was expanded from,
+/// but neither the inverted squares of the Goppa polynomial nor the layers of the Beneš network are recomputed.
+#[cfg(not(feature = "encap-only"))]
+pub fn decapsulate_expanded(
+    esk: &ExpandedSecretKey,
+    c: &Ciphertext,
+) -> Result<SharedSecret, Error> {
+    decapsulate_expanded_in(esk, c, &mut [0; 1 << GFBITS])
+}
+
+/// Like `decapsulate_expanded`, with the buffer `buf` of the decryption instead of one on the stack
+#[cfg(not(feature = "encap-only"))]
+pub(crate) fn decapsulate_expanded_in(
+    esk: &ExpandedSecretKey,
+    c: &Ciphertext,
+    buf: &mut [Gf; 1 << GFBITS],
+) -> Result<SharedSecret, Error> {
+    let mut key = SharedSecret::zero();
+    decapsulate_checked(&mut key, c, &esk.sk, &esk.dk, &*esk.net, &[], buf)?;
+    Ok(key)
+}
+
+/// KEM Decapsulation of several ciphertexts `cs` with the same secret key returning the shared secrets.
+///
+/// Equivalent to calling `decapsulate` for each ciphertext, but `sk` is expanded only once
+/// (see `SecretKey::expand`) and each ciphertext is decapsulated with `decapsulate_expanded`.
+#[cfg(not(feature = "encap-only"))]
+pub fn decapsulate_batch(sk: &SecretKey, cs: &[Ciphertext]) -> Result<Vec<SharedSecret>, Error> {
+    let esk = sk.expand()?;
+
+    cs.iter().map(|c| decapsulate_expanded(&esk, c)).collect()
+}
+
+/// KEM Decapsulation with the context `ctx` of `encapsulate_with_context` returning the shared secret.
+///
+/// A different context results in a different (pseudo-random) shared secret,
+/// just like an invalid ciphertext does.
+#[cfg(not(feature = "encap-only"))]
+pub fn decapsulate_with_context(
+    sk: &SecretKey,
+    c: &Ciphertext,
+    ctx: &[u8],
+) -> Result<SharedSecret, Error> {
+    let mut key = SharedSecret::zero();
+    decapsulate_checked(
+        &mut key,
+        c,
+        sk,
+        &decryption_key(sk)?,
+        sk.control_bits(),
+        ctx,
+        &mut [0; 1 << GFBITS],
+    )?;
+    Ok(key)
+}
+
+/// The stack usage of `decap_with` is bounded by 16 KB (it uses less than 8 KB on x86-64).
+/// The bound is checked by the tests, which run `decap_with` on a thread with a stack of this size.
+#[cfg(not(feature = "encap-only"))]
+pub const DECAP_WITH_MAX_STACK: usize = 16 * 1024;
+
+/// KEM Decapsulation with the memory of the workspace `ws` returning the shared secret.
+///
+/// Like `decapsulate`, but the decryption key and the buffer of the decryption are computed in `ws`
+/// instead of on the stack. Hence, the same workspace can be reused for many decapsulations.
+///
+/// The remaining temporaries are small, so that it runs with `DECAP_WITH_MAX_STACK` bytes of stack
+/// for all variants (e.g. in RTOS tasks). The other decapsulation functions keep the decryption key
+/// on the stack and need about 40 KB (mceliece348864) to 80 KB (mceliece8192128).
+#[cfg(not(feature = "encap-only"))]
+pub fn decap_with(
+    sk: &SecretKey,
+    c: &Ciphertext,
+    ws: &mut Workspace,
+) -> Result<SharedSecret, Error> {
+    let (dk, buf) = ws.decryption();
+    dk.load(sub!(sk.0, 40, IRR_BYTES))?;
+
+    let mut key = SharedSecret::zero();
+    decapsulate_checked(&mut key, c, sk, dk, sk.control_bits(), &[], buf)?;
+    Ok(key)
+}
+
+/// Checks whether the public key `pk` belongs to the secret key `sk`.
+///
+/// The public key is re-derived from the secret key (see `SecretKey::public_key`) and compared to `pk`.
+/// This is meant for validating imported or restored key material.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn verify_keypair(pk: &PublicKey, sk: &SecretKey) -> bool {
+    match sk.public_key() {
+        Ok(derived) => *derived == *pk,
+        Err(_) => false,
+    }
+}
+
+/// KEM Keypair generation writing the keys into caller-provided buffers.
+///
+/// `pk_out` and `sk_out` must be of `CRYPTO_PUBLICKEYBYTES` and `CRYPTO_SECRETKEYBYTES` bytes.
+/// The keys are generated in place, i.e. no intermediate copies are made.
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+pub fn keypair_into(
+    pk_out: &mut [u8],
+    sk_out: &mut [u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(), Error> {
+    let pk = PublicKey::wrap_mut(array_mut::<CRYPTO_PUBLICKEYBYTES>(pk_out)?);
+    let sk = SecretKey::wrap_mut(array_mut::<CRYPTO_SECRETKEYBYTES>(sk_out)?);
+    crypto_kem_keypair(pk, sk, rng)?;
     Ok(())
 }
 
+/// KEM Encapsulation writing the ciphertext and the shared secret into caller-provided buffers.
+///
+/// `c_out`, `ss_out`, and `pk` must be of `CRYPTO_CIPHERTEXTBYTES`, `CRYPTO_BYTES`,
+/// and `CRYPTO_PUBLICKEYBYTES` bytes. Like `encapsulate`, it fails with `Error::InvalidPadding`
+/// if padding bits of the public key are set.
+#[cfg(not(feature = "decap-only"))]
+pub fn encap_into(
+    c_out: &mut [u8],
+    ss_out: &mut [u8],
+    pk: &[u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(), Error> {
+    let c = Ciphertext::wrap_mut(array_mut::<CRYPTO_CIPHERTEXTBYTES>(c_out)?);
+    let key = SharedSecret::wrap_mut(array_mut::<CRYPTO_BYTES>(ss_out)?);
+    let pk = PublicKey::wrap_ref(array_ref::<CRYPTO_PUBLICKEYBYTES>(pk)?);
+
+    let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+    gen_e(&mut e, rng)?;
+    encapsulate_checked(c, key, pk, &e, &[])
+}
+
+/// KEM Decapsulation writing the shared secret into a caller-provided buffer.
+///
+/// `ss_out`, `c`, and `sk` must be of `CRYPTO_BYTES`, `CRYPTO_CIPHERTEXTBYTES`,
+/// and `CRYPTO_SECRETKEYBYTES` bytes. Like `decapsulate`, it fails with `Error::InvalidPadding`
+/// if padding bits of the ciphertext are set.
+#[cfg(not(feature = "encap-only"))]
+pub fn decap_into(ss_out: &mut [u8], c: &[u8], sk: &[u8]) -> Result<(), Error> {
+    let key = SharedSecret::wrap_mut(array_mut::<CRYPTO_BYTES>(ss_out)?);
+    let c = Ciphertext::wrap_ref(array_ref::<CRYPTO_CIPHERTEXTBYTES>(c)?);
+    let sk = SecretKey::wrap_ref(array_ref::<CRYPTO_SECRETKEYBYTES>(sk)?);
+    decapsulate_checked(
+        key,
+        c,
+        sk,
+        &decryption_key(sk)?,
+        sk.control_bits(),
+        &[],
+        &mut [0; 1 << GFBITS],
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    #[cfg(all(feature = "mceliece8192128f", test))]
     use super::*;
-    #[cfg(all(feature = "mceliece8192128f", test))]
+    #[cfg_attr(
+        any(feature = "encap-only", feature = "decap-only"),
+        allow(unused_imports)
+    )]
     use crate::randombytes::AesState;
-    #[cfg(all(feature = "mceliece8192128f", test))]
+    #[cfg(all(feature = "mceliece8192128f", not(feature = "decap-only")))]
+    use rand_core::RngCore;
+    #[cfg(feature = "mceliece8192128f")]
     use std::convert::TryFrom;
 
     #[test]
-    #[cfg(feature = "mceliece8192128f")]
-    fn test_crypto_kem_dec() -> Result<(), Box<dyn error::Error>> {
-        use crate::api::{CRYPTO_CIPHERTEXTBYTES, CRYPTO_SECRETKEYBYTES};
-
-        let mut sk = crate::TestData::new().u8vec("mceliece8192128f_sk1");
-        let mut c = crate::TestData::new().u8vec("mceliece8192128f_ct1");
-        let mut test_key = [0u8; 32];
+    #[cfg(all(feature = "mceliece8192128f", not(feature = "encap-only")))]
+    fn test_crypto_kem_dec() -> Result<(), Error> {
+        let sk = SecretKey::try_from(
+            crate::TestData::new()
+                .u8vec("mceliece8192128f_sk1")
+                .as_slice(),
+        )?;
+        let c = Ciphertext::try_from(
+            crate::TestData::new()
+                .u8vec("mceliece8192128f_ct1")
+                .as_slice(),
+        )?;
+        let mut test_key = SharedSecret::zero();
         let compare_key = crate::TestData::new().u8vec("mceliece8192128f_operations_ss");
 
-        crypto_kem_dec(
-            &mut test_key,
-            sub!(mut c, 0, CRYPTO_CIPHERTEXTBYTES),
-            sub!(mut sk, 0, CRYPTO_SECRETKEYBYTES),
-        )?;
+        crypto_kem_dec(&mut test_key, &c, &sk)?;
 
-        assert_eq!(test_key, compare_key.as_slice());
+        assert_eq!(test_key.as_ref(), compare_key.as_slice());
 
         Ok(())
     }
 
     #[test]
-    #[cfg(feature = "mceliece8192128f")]
-    fn test_crypto_kem_enc() -> Result<(), Box<dyn error::Error>> {
-        use crate::api::{CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_PUBLICKEYBYTES};
-
-        let mut c = [0u8; CRYPTO_CIPHERTEXTBYTES];
-        let mut ss = [0u8; CRYPTO_BYTES];
-        let mut pk = crate::TestData::new().u8vec("mceliece8192128f_pk1");
-        assert_eq!(pk.len(), CRYPTO_PUBLICKEYBYTES);
+    #[cfg(all(feature = "mceliece8192128f", not(feature = "decap-only")))]
+    fn test_crypto_kem_enc() -> Result<(), Error> {
+        let mut c = Ciphertext::zero();
+        let mut ss = SharedSecret::zero();
+        let pk_vec = crate::TestData::new().u8vec("mceliece8192128f_pk1");
+        assert_eq!(pk_vec.len(), CRYPTO_PUBLICKEYBYTES);
+        let pk = PublicKey::try_from(pk_vec.as_slice())?;
 
         let compare_ss = crate::TestData::new().u8vec("mceliece8192128f_operations_ss");
         let compare_ct = crate::TestData::new().u8vec("mceliece8192128f_operations_enc1_ct");
@@ -420,29 +1427,25 @@ mod tests {
         let mut second_seed = [0u8; 33];
         second_seed[0] = 64;
 
-        rng_state.randombytes(&mut second_seed[1..])?;
+        rng_state.fill_bytes(&mut second_seed[1..]);
 
-        crypto_kem_enc(
-            &mut c,
-            &mut ss,
-            sub!(mut pk, 0, CRYPTO_PUBLICKEYBYTES),
-            &mut rng_state,
-        )?;
+        crypto_kem_enc(&mut c, &mut ss, &pk, &mut rng_state)?;
 
-        assert_eq!(ss, compare_ss.as_slice());
+        assert_eq!(ss.as_ref(), compare_ss.as_slice());
 
-        assert_eq!(c, compare_ct.as_slice());
+        assert_eq!(c.as_ref(), compare_ct.as_slice());
 
         Ok(())
     }
 
     #[test]
-    #[cfg(feature = "mceliece8192128f")]
-    fn test_crypto_kem_keypair() -> Result<(), Box<dyn error::Error>> {
-        use crate::api::{CRYPTO_PUBLICKEYBYTES, CRYPTO_SECRETKEYBYTES};
-
-        let mut pk_input = [0; CRYPTO_PUBLICKEYBYTES].to_vec();
-        let mut sk_input = [0; CRYPTO_SECRETKEYBYTES].to_vec();
+    #[cfg(all(
+        feature = "mceliece8192128f",
+        not(any(feature = "encap-only", feature = "decap-only"))
+    ))]
+    fn test_crypto_kem_keypair() -> Result<(), Error> {
+        let mut pk = PublicKey::zero();
+        let mut sk = SecretKey::zero();
 
         let entropy_input = <[u8; 48]>::try_from(
             crate::TestData::new()
@@ -460,14 +1463,686 @@ mod tests {
         let mut rng_state = AesState::new();
         rng_state.randombytes_init(entropy_input);
 
-        crypto_kem_keypair(
-            sub!(mut pk_input, 0, CRYPTO_PUBLICKEYBYTES),
-            sub!(mut sk_input, 0, CRYPTO_SECRETKEYBYTES),
-            &mut rng_state,
-        )?;
+        crypto_kem_keypair(&mut pk, &mut sk, &mut rng_state)?;
+
+        assert_eq!(compare_sk.as_slice(), sk.as_ref());
+        assert_eq!(compare_pk.as_slice(), pk.as_ref());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_encapsulate_decapsulate() {
+        // keys returned by value exceed the default stack size of test threads
+        let child = std::thread::Builder::new()
+            .stack_size(16 * 1024 * 1024)
+            .spawn(|| {
+                let mut rng_state = crate::test_rng(0x2A);
+
+                let (pk, sk) = keypair(&mut rng_state).unwrap();
+                let (c, ss_bob) = encapsulate(&pk, &mut rng_state).unwrap();
+                let ss_alice = decapsulate(&sk, &c).unwrap();
+
+                assert_eq!(ss_alice, ss_bob);
+            })
+            .unwrap();
+
+        child.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_crypto_kem_keypair_from_seed() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+
+        let mut pk_restored: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut sk_restored: Box<SecretKey> = bytemuck::zeroed_box();
+        let seed = sub!(sk.as_array(), 0, 32);
+        crypto_kem_keypair_from_seed(&mut pk_restored, &mut sk_restored, seed)?;
+
+        assert!(*pk == pk_restored);
+        assert!(*sk == sk_restored);
+
+        Ok(())
+    }
+
+    /// Verifies the NIST response file at `$CLASSIC_MCELIECE_RSP` (e.g. `PQCkemKAT_935.rsp` of the
+    /// reference implementation), if set, against the key generation, encapsulation and decapsulation
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_rsp_file() -> Result<(), Error> {
+        let Ok(path) = std::env::var("CLASSIC_MCELIECE_RSP") else {
+            return Ok(());
+        };
+        let records = crate::read_rsp(&path).unwrap_or_else(|| {
+            panic!(
+                "{} is not a response file of {}",
+                path,
+                crate::CRYPTO_PRIMITIVE
+            )
+        });
+        assert!(!records.is_empty(), "{} has no test cases", path);
+
+        for r in records {
+            let mut rng_state = AesState::new();
+            rng_state.randombytes_init(r.seed.as_slice().try_into().expect("seed of 48 bytes"));
+
+            let (pk, sk) = keypair_boxed(&mut rng_state)?;
+            assert_eq!(
+                pk.as_array().as_slice(),
+                r.pk,
+                "public key of test case {}",
+                r.count
+            );
+            assert_eq!(
+                sk.as_array().as_slice(),
+                r.sk,
+                "secret key of test case {}",
+                r.count
+            );
+
+            let (ct, ss) = encapsulate(&pk, &mut rng_state)?;
+            assert_eq!(
+                ct.as_array().as_slice(),
+                r.ct,
+                "ciphertext of test case {}",
+                r.count
+            );
+            assert_eq!(
+                ss.as_array().as_slice(),
+                r.ss,
+                "shared secret of test case {}",
+                r.count
+            );
+            assert!(decapsulate(&sk, &ct)? == ss);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_with_coins() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+
+        // sample the same error vector as `encapsulate`
+        let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+        gen_e(&mut e, &mut rng_state.clone())?;
+
+        let (c, ss_bob) = encapsulate(pk, &mut rng_state)?;
+        let (c_with_coins, ss_with_coins) = encapsulate_with_coins(pk, &e)?;
+
+        assert_eq!(c, c_with_coins);
+        assert_eq!(ss_bob, ss_with_coins);
+        assert_eq!(decapsulate(sk, &c_with_coins)?, ss_with_coins);
+
+        // error vectors of wrong weight are rejected
+        assert!(matches!(
+            encapsulate_with_coins(pk, &[0u8; CRYPTO_ERRORVECTORBYTES]),
+            Err(Error::InvalidErrorVector)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_without_rejection() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+
+        let (c, ss_bob) = encapsulate_without_rejection(pk, &mut rng_state.clone())?;
+        assert_eq!(decapsulate(sk, &c)?, ss_bob);
+        assert_ne!(encapsulate(pk, &mut rng_state)?.0, c);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_batch() -> Result<(), Error> {
+        let (pk, _) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+
+        let mut rng_single = rng_state.clone();
+        let batch = encapsulate_batch(pk, 3, &mut rng_state)?;
+        assert_eq!(batch.len(), 3);
+
+        // the same encapsulations as one after the other
+        for (c, ss_bob) in &batch {
+            assert_eq!(
+                encapsulate(pk, &mut rng_single)?,
+                (c.clone(), ss_bob.clone())
+            );
+        }
+        assert!(encapsulate_batch(pk, 0, &mut rng_state)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_many() -> Result<(), Error> {
+        let (pk, _) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+        let mut expected_rng_state = rng_state.clone();
+
+        // the same encapsulations as one after the other, also against the same key
+        let sessions = encapsulate_many(&[pk, pk], &mut rng_state)?;
+        assert_eq!(sessions.len(), 2);
+        for (c, ss_bob) in &sessions {
+            assert_eq!(
+                encapsulate(pk, &mut expected_rng_state)?,
+                (c.clone(), ss_bob.clone())
+            );
+        }
+        assert_ne!(sessions[0].1, sessions[1].1);
+        assert!(encapsulate_many(&[], &mut rng_state)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_padding_rejected() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+        fn is_padding_error<T>(result: Result<T, Error>) -> bool {
+            matches!(result, Err(Error::InvalidPadding))
+        }
+
+        // a padding bit of the last row of the public key
+        let mut bad_pk = pk.clone();
+        bad_pk.0[CRYPTO_PUBLICKEYBYTES - 1] |= 0x80;
+        let mut bad_columns = pk.to_columns();
+        bad_columns.0[SYND_BYTES - 1] |= 0x80;
+        let mut e = [0u8; CRYPTO_ERRORVECTORBYTES];
+        gen_e(&mut e, &mut rng_state.clone())?;
+
+        assert!(is_padding_error(encapsulate(&bad_pk, &mut rng_state)));
+        assert!(is_padding_error(encapsulate_with_coins(&bad_pk, &e)));
+        assert!(is_padding_error(encapsulate_with_context(
+            &bad_pk,
+            b"ctx",
+            &mut rng_state
+        )));
+        assert!(is_padding_error(encapsulate_without_rejection(
+            &bad_pk,
+            &mut rng_state
+        )));
+        assert!(is_padding_error(encapsulate_columns(
+            &bad_columns,
+            &mut rng_state
+        )));
+        let (mut c_out, mut ss_out) = ([0u8; CRYPTO_CIPHERTEXTBYTES], [0u8; CRYPTO_BYTES]);
+        assert!(is_padding_error(encap_into(
+            &mut c_out,
+            &mut ss_out,
+            bad_pk.as_array(),
+            &mut rng_state
+        )));
+
+        // a padding bit of the syndrome of a valid ciphertext
+        let (mut bad_c, _) = encapsulate(pk, &mut rng_state)?;
+        bad_c.0[SYND_BYTES - 1] |= 0x80;
+
+        assert!(is_padding_error(decapsulate(sk, &bad_c)));
+        assert!(is_padding_error(decapsulate_expanded(
+            &sk.expand()?,
+            &bad_c
+        )));
+        assert!(is_padding_error(decapsulate_batch(sk, &[bad_c.clone()])));
+        assert!(is_padding_error(decapsulate_with_context(
+            sk, &bad_c, b"ctx"
+        )));
+        assert!(is_padding_error(decap_with(
+            sk,
+            &bad_c,
+            &mut Workspace::new()
+        )));
+        assert!(is_padding_error(decap_into(
+            &mut ss_out,
+            bad_c.as_array(),
+            sk.as_array()
+        )));
+
+        // the low-level functions keep the behavior of the reference implementation
+        let mut ss = SharedSecret::zero();
+        assert_eq!(crypto_kem_dec(&mut ss, &bad_c, sk)?, 0xFF);
+        assert_eq!(ss.as_array(), &[0xFF; CRYPTO_BYTES]);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_streaming() -> Result<(), Error> {
+        let (pk, _) = crate::test_keypair();
+        let mut encap_rng_state = crate::test_rng(0x2B);
+        let mut expected_rng_state = crate::test_rng(0x2B);
+
+        let (c, ss_bob) =
+            encapsulate_streaming(&mut pk.as_array().as_slice(), &mut encap_rng_state)?;
+        let (expected_c, expected_ss) = encapsulate(pk, &mut expected_rng_state)?;
+        assert!(c == expected_c && ss_bob == expected_ss);
+
+        // the source ends before the last row
+        assert!(matches!(
+            encapsulate_streaming(&mut &pk.as_array()[1..], &mut encap_rng_state),
+            Err(Error::Io(_))
+        ));
+
+        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+        {
+            let mut pk = pk.as_array().to_vec();
+            pk[CRYPTO_PUBLICKEYBYTES - 1] |= 0x80;
+            assert!(matches!(
+                encapsulate_streaming(&mut pk.as_slice(), &mut encap_rng_state),
+                Err(Error::InvalidPadding)
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "decap-only"))]
+    fn test_encapsulate_columns() -> Result<(), Error> {
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        for (i, b) in pk.0.iter_mut().enumerate() {
+            *b = (i * 37 + 5) as u8;
+        }
+        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+        for row in pk.0.chunks_exact_mut(PK_ROW_BYTES) {
+            row[PK_ROW_BYTES - 1] &= (1 << (PK_NCOLS % 8)) - 1;
+        }
+
+        let mut rng_state = crate::test_rng(0x2B);
+        let mut expected_rng_state = rng_state.clone();
+
+        let (c, ss) = encapsulate_columns(&pk.to_columns(), &mut rng_state)?;
+        let (expected_c, expected_ss) = encapsulate(&pk, &mut expected_rng_state)?;
+        assert!(c == expected_c && ss == expected_ss);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_decapsulate_batch() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let (cs, ss_bob): (Vec<_>, Vec<_>) = encapsulate_batch(pk, 3, &mut crate::test_rng(0x2B))?
+            .into_iter()
+            .unzip();
+
+        assert_eq!(decapsulate_batch(sk, &cs)?, ss_bob);
+
+        // invalid ciphertexts are rejected implicitly like by `decapsulate`
+        let mut tampered = cs.clone();
+        tampered[1].0[0] ^= 1;
+        let ss_alice = decapsulate_batch(sk, &tampered)?;
+        assert_eq!(ss_alice[0], ss_bob[0]);
+        assert_eq!(ss_alice[1], decapsulate(sk, &tampered[1])?);
+        assert_ne!(ss_alice[1], ss_bob[1]);
+
+        assert!(decapsulate_batch(sk, &[])?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_decapsulate_expanded() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+        let esk = sk.expand()?;
+        assert!(esk.secret_key() == &**sk);
+
+        for _ in 0..2 {
+            let (mut c, ss_bob) = encapsulate(pk, &mut rng_state)?;
+            assert_eq!(decapsulate_expanded(&esk, &c)?, ss_bob);
+
+            c.0[0] ^= 1;
+            assert_eq!(decapsulate_expanded(&esk, &c)?, decapsulate(sk, &c)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_encapsulate_with_context() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let mut rng_state = crate::test_rng(0x2B);
+
+        let (c, ss_bob) = encapsulate_with_context(pk, b"protocol A", &mut rng_state)?;
+        assert_eq!(decapsulate_with_context(sk, &c, b"protocol A")?, ss_bob);
+        assert_ne!(decapsulate_with_context(sk, &c, b"protocol B")?, ss_bob);
+        assert_ne!(decapsulate(sk, &c)?, ss_bob);
+
+        // the empty context yields the shared secret of the specification
+        let (c, ss_bob) = encapsulate_with_context(pk, b"", &mut rng_state)?;
+        assert_eq!(decapsulate(sk, &c)?, ss_bob);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_into_buffers() -> Result<(), Error> {
+        let (expected_pk, expected_sk) = crate::test_keypair();
+
+        let mut pk = vec![0u8; CRYPTO_PUBLICKEYBYTES];
+        let mut sk = vec![0u8; CRYPTO_SECRETKEYBYTES];
+        let mut c = [0u8; CRYPTO_CIPHERTEXTBYTES];
+        let mut ss_bob = [0u8; CRYPTO_BYTES];
+        let mut ss_alice = [0u8; CRYPTO_BYTES];
+
+        keypair_into(&mut pk, &mut sk, &mut crate::test_rng(0x2A))?;
+        assert!(pk == expected_pk.as_array() && sk == expected_sk.as_array());
+        encap_into(&mut c, &mut ss_bob, &pk, &mut crate::test_rng(0x2B))?;
+        decap_into(&mut ss_alice, &c, &sk)?;
+
+        assert_eq!(ss_alice, ss_bob);
+
+        // buffers of wrong length are rejected
+        assert!(matches!(
+            decap_into(&mut ss_alice, &c[1..], &sk),
+            Err(Error::WrongLength { expected, got })
+                if expected == CRYPTO_CIPHERTEXTBYTES && got == CRYPTO_CIPHERTEXTBYTES - 1
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_verify_keypair() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        assert!(verify_keypair(pk, sk));
+
+        let mut pk = pk.clone();
+        pk.0[0] ^= 1;
+        assert!(!verify_keypair(&pk, sk));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "encap-only"))]
+    fn test_decap_into_wrong_length() {
+        let mut ss = [0u8; CRYPTO_BYTES];
+        let c = [0u8; CRYPTO_CIPHERTEXTBYTES];
+        let sk = vec![0u8; CRYPTO_SECRETKEYBYTES];
+
+        assert!(matches!(
+            decap_into(&mut ss, &c, &sk[1..]),
+            Err(Error::WrongLength { expected, got })
+                if expected == CRYPTO_SECRETKEYBYTES && got == CRYPTO_SECRETKEYBYTES - 1
+        ));
+        assert!(matches!(
+            decap_into(&mut ss[1..], &c, &sk),
+            Err(Error::WrongLength { expected, got })
+                if expected == CRYPTO_BYTES && got == CRYPTO_BYTES - 1
+        ));
+    }
+
+    /// RNG whose entropy source is unavailable: `try_fill_bytes` fails, the infallible methods return zeros
+    #[cfg(not(feature = "decap-only"))]
+    struct FailingRng;
+
+    #[cfg(not(feature = "decap-only"))]
+    impl rand_core::RngCore for FailingRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            Err(rand_core::Error::new("entropy source unavailable"))
+        }
+    }
+
+    #[cfg(not(feature = "decap-only"))]
+    impl rand_core::CryptoRng for FailingRng {}
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_rng_failure() {
+        let result = keypair_boxed(&mut FailingRng);
+        assert!(matches!(result, Err(Error::Rng(_))));
+    }
+
+    #[test]
+    #[cfg(not(feature = "decap-only"))]
+    fn test_encapsulate_rng_failure() {
+        let pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let result = encapsulate(&pk, &mut FailingRng);
+        assert!(matches!(result, Err(Error::Rng(_))));
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_cancellable() -> Result<(), Error> {
+        // without cancellation, the same key pair as `keypair_boxed` results
+        let mut attempts = 0;
+        let keypair = keypair_boxed_cancellable(&mut crate::test_rng(0x2A), || {
+            attempts += 1;
+            true
+        })?;
+        assert!(attempts >= 1);
+        assert!(&keypair == crate::test_keypair());
+
+        assert!(matches!(
+            keypair_boxed_cancellable(&mut crate::test_rng(0x2A), || false),
+            Err(Error::Cancelled)
+        ));
 
-        assert_eq!(compare_sk, sk_input);
-        assert_eq!(compare_pk, pk_input);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_crypto_kem_keypair_with_progress() -> Result<(), Error> {
+        let mut rng_state = crate::test_rng(0x2A);
+
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+        let mut phases = Vec::new();
+        crypto_kem_keypair_with_progress(&mut pk, &mut sk, &mut rng_state, |phase| {
+            phases.push(phase)
+        })?;
+        assert!(&(pk, sk) == crate::test_keypair());
+
+        // the successful attempt runs through all phases
+        let attempts = phases
+            .iter()
+            .filter(|phase| matches!(phase, KeygenPhase::Attempt(_)))
+            .count();
+        assert_eq!(phases[0], KeygenPhase::Attempt(1));
+        assert_eq!(
+            phases[phases.len() - 4..],
+            [
+                KeygenPhase::Attempt(attempts),
+                KeygenPhase::Polynomial,
+                KeygenPhase::PublicKey,
+                KeygenPhase::ControlBits
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keygen_state() -> Result<(), Error> {
+        let mut rng_state = crate::test_rng(0x2A);
+
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+        let mut state = KeygenState::new(&mut rng_state)?;
+        assert_eq!(state.phase(), Some(KeygenPhase::Attempt(1)));
+
+        let mut steps = 1;
+        while !state.step(&mut pk, &mut sk)? {
+            steps += 1;
+        }
+        assert!(steps >= 4);
+        assert_eq!(state.phase(), None);
+        assert!(state.step(&mut pk, &mut sk)?);
+
+        assert!(&(pk, sk) == crate::test_keypair());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_concurrent_encapsulate_decapsulate() -> Result<(), Error> {
+        use std::sync::Arc;
+        use std::thread;
+
+        let (pk, sk) = crate::test_keypair();
+        let pk: Arc<PublicKey> = Arc::new((**pk).clone());
+        let sk: Arc<SecretKey> = Arc::new((**sk).clone());
+
+        let threads: Vec<_> = (0..8u8)
+            .map(|i| {
+                let pk = Arc::clone(&pk);
+                let sk = Arc::clone(&sk);
+                thread::spawn(move || -> Result<(), Error> {
+                    let mut rng = crate::test_rng(i);
+                    for _ in 0..4 {
+                        let (c, ss_bob) = encapsulate(&pk, &mut rng)?;
+                        let ss_alice = decapsulate(&sk, &c)?;
+                        assert_eq!(ss_alice, ss_bob);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().expect("thread panicked")?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_boxed() -> Result<(), Error> {
+        // runs on the default stack of test threads also for the larger variants
+        let mut rng_state = crate::test_rng(0x2A);
+
+        let (pk, sk) = keypair_boxed(&mut rng_state)?;
+        let (c, ss_bob) = encapsulate(&pk, &mut rng_state)?;
+        let ss_alice = decapsulate(&sk, &c)?;
+
+        assert_eq!(ss_alice, ss_bob);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_parallel() -> Result<(), Error> {
+        // the key pair depends on the scheduling of the threads
+        let (pk, sk) = keypair_parallel(&mut crate::test_rng(0x2A), 3)?;
+        let (c, ss_bob) = encapsulate(&pk, &mut crate::test_rng(0x2B))?;
+        assert_eq!(decapsulate(&sk, &c)?, ss_bob);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_batch() -> Result<(), Error> {
+        let keys = keypair_batch(3, &mut crate::test_rng(0x2A))?;
+        assert_eq!(keys.len(), 3);
+
+        let mut rng_state = crate::test_rng(0x2A);
+        for (pk, sk) in keys.iter() {
+            let mut seed = [0u8; 32];
+            rand_core::RngCore::fill_bytes(&mut rng_state, &mut seed);
+            let mut expected_pk: Box<PublicKey> = bytemuck::zeroed_box();
+            let mut expected_sk: Box<SecretKey> = bytemuck::zeroed_box();
+            crypto_kem_keypair_from_seed(&mut expected_pk, &mut expected_sk, &seed)?;
+            assert!(*pk == expected_pk && *sk == expected_sk);
+        }
+
+        assert!(keypair_batch(0, &mut rng_state)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_decap_with_stack() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        let (c, ss_bob) = encapsulate(pk, &mut crate::test_rng(0x2B))?;
+
+        // a stack overflow aborts the test process
+        let ss_alice = thread::Builder::new()
+            .stack_size(DECAP_WITH_MAX_STACK)
+            .spawn(move || decap_with(sk, &c, &mut Workspace::new()))
+            .map_err(|_| Error::Internal("cannot spawn thread"))?
+            .join()
+            .map_err(|_| Error::Internal("decapsulation thread panicked"))??;
+        assert_eq!(ss_alice, ss_bob);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_workspace_reuse() -> Result<(), Error> {
+        let mut rng_state = crate::test_rng(0x2A);
+        let mut expected_rng_state = crate::test_rng(0x2A);
+        let mut encap_rng_state = crate::test_rng(0x2B);
+
+        let mut ws = Workspace::new();
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+        let mut expected_pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut expected_sk: Box<SecretKey> = bytemuck::zeroed_box();
+
+        for _ in 0..2 {
+            keypair_with(&mut pk, &mut sk, &mut rng_state, &mut ws)?;
+            crypto_kem_keypair(&mut expected_pk, &mut expected_sk, &mut expected_rng_state)?;
+            assert!(pk == expected_pk && sk == expected_sk);
+
+            let (mut c, ss_bob) = encapsulate(&pk, &mut encap_rng_state)?;
+            assert_eq!(decap_with(&sk, &c, &mut ws)?, ss_bob);
+            c.0[0] ^= 1;
+            assert_eq!(decap_with(&sk, &c, &mut ws)?, decapsulate(&sk, &c)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_keypair_streaming() -> Result<(), Error> {
+        use crate::params::PK_ROW_BYTES;
+
+        let mut pk = Vec::with_capacity(CRYPTO_PUBLICKEYBYTES);
+        let sk = keypair_streaming(&mut crate::test_rng(0x2A), |row| {
+            assert_eq!(row.len(), PK_ROW_BYTES);
+            pk.extend_from_slice(row);
+        })?;
+        let (expected_pk, expected_sk) = crate::test_keypair();
+
+        assert!(pk == expected_pk.as_array() && sk == **expected_sk);
 
         Ok(())
     }
@@ -0,0 +1,49 @@
+//! Convenience functions drawing their randomness from the operating system
+//!
+//! The functions of this module use `getrandom` (through `rand_core::OsRng`) instead of
+//! a caller-provided RNG. They are named like their counterparts at the crate root,
+//! e.g. `os_rng::keypair()` instead of `keypair(&mut rng)`.
+
+use rand_core::OsRng;
+
+use crate::error::Error;
+use crate::operations;
+#[cfg(not(feature = "encap-only"))]
+use crate::operations::keypair_boxed;
+use crate::types::PublicKey;
+#[cfg(not(feature = "encap-only"))]
+use crate::types::SecretKey;
+use crate::types::{Ciphertext, SharedSecret};
+
+/// KEM Keypair generation with randomness of the operating system.
+///
+/// The keys are heap-allocated like those of `keypair_boxed`, so this works for all variants.
+#[cfg(not(feature = "encap-only"))]
+pub fn keypair() -> Result<(Box<PublicKey>, Box<SecretKey>), Error> {
+    keypair_boxed(&mut OsRng)
+}
+
+/// KEM Encapsulation with randomness of the operating system.
+///
+/// This is a convenience wrapper for `encapsulate`.
+pub fn encapsulate(pk: &PublicKey) -> Result<(Ciphertext, SharedSecret), Error> {
+    operations::encapsulate(pk, &mut OsRng)
+}
+
+#[cfg(all(test, not(feature = "encap-only")))]
+mod tests {
+    use super::*;
+    use crate::operations::decapsulate;
+
+    #[test]
+    fn test_os_rng_roundtrip() -> Result<(), Error> {
+        let (pk, sk) = keypair()?;
+
+        let (ct, ss_bob) = encapsulate(&pk)?;
+        let ss_alice = decapsulate(&sk, &ct)?;
+
+        assert_eq!(ss_alice, ss_bob);
+
+        Ok(())
+    }
+}
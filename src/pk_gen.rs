@@ -1,32 +1,183 @@
 //! Generation of public key
 
 use crate::{
-    gf::{gf_inv, gf_mul},
+    ct::bit_mask,
+    error::Error,
+    gf::{gf_inv_batch, gf_mul, Gf},
     macros::sub,
     params::{GFBITS, GFMASK, PK_NROWS, PK_ROW_BYTES, SYS_N, SYS_T},
     root::root,
-    uint64_sort::uint64_sort,
+    sort::uint64_sort,
     util::{bitrev, load_gf},
 };
-use std::error;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::ops::Range;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
+#[cfg(semi_systematic)]
+use crate::{
+    ct::{cswap, eq_mask},
+    transpose::transpose_64x64_inplace,
+    util::{load8, store8},
+};
+
+/// The number of bytes of the rows of the matrix left of the bytes of the public key
+pub(crate) const MAT_LEFT_BYTES: usize = SYS_N / 8 - PK_ROW_BYTES;
+
+/// The number of bytes of the rows of the inverse of the left `PK_NROWS × PK_NROWS` block of the matrix
+const INV_BYTES: usize = PK_NROWS.div_ceil(8);
+
+/// The number of bytes of the columns of the matrix kept by `pk_rows_from_permutation`,
+/// which include the columns moved by `mov_columns` for the f-variants
+#[cfg(semi_systematic)]
+const EXT_BYTES: usize = if (PK_NROWS - 32) / 8 + 9 > INV_BYTES {
+    (PK_NROWS - 32) / 8 + 9
+} else {
+    INV_BYTES
+};
+/// The number of bytes of the columns of the matrix kept by `pk_rows_from_permutation`
 #[cfg(any(
-    feature = "mceliece348864f",
-    feature = "mceliece460896f",
-    feature = "mceliece6688128f",
-    feature = "mceliece6960119f",
-    feature = "mceliece8192128f"
+    feature = "mceliece348864",
+    feature = "mceliece460896",
+    feature = "mceliece6688128",
+    feature = "mceliece6960119",
+    feature = "mceliece8192128"
 ))]
-use crate::util::{load8, store8};
+const EXT_BYTES: usize = INV_BYTES;
+
+/// The number of bytes of the rows of the matrix of `pk_rows_from_permutation`
+const STREAM_ROW_BYTES: usize = EXT_BYTES + INV_BYTES;
+
+/// The matrix of `pk_rows_from_permutation`: the kept columns of the matrix next to the inverse
+/// of its left block, in rows of `STREAM_ROW_BYTES` bytes
+pub(crate) type StreamMatrix = [[u8; STREAM_ROW_BYTES]; PK_NROWS];
+
+/// The number of rows of the public key computed at once by `pk_rows_from_permutation`
+const STREAM_ROWS: usize = 64;
+
+/// The matrix of the public key computation with `PK_NROWS` rows of `SYS_N / 8` bytes.
+///
+/// Each row is split into its first `MAT_LEFT_BYTES` bytes in `left` and the remaining bytes
+/// in the row of the public key `pk`. Once the matrix is in systematic form, the right parts
+/// are the public key (up to the alignment of mceliece6960119). Thus, the key generation needs the memory
+/// of the left parts (the columns of the identity) only in addition to the public key.
+struct Matrix<'a> {
+    left: &'a mut [[u8; MAT_LEFT_BYTES]; PK_NROWS],
+    pk: &'a mut [u8; PK_NROWS * PK_ROW_BYTES],
+}
+
+/// Byte access to the rows of a matrix of the public key computation
+trait MatrixRows {
+    /// Returns byte `idx` of row `row`
+    fn byte(&self, row: usize, idx: usize) -> u8;
+
+    /// Sets byte `idx` of row `row` to `b`
+    fn set_byte(&mut self, row: usize, idx: usize, b: u8);
+
+    /// Returns row `row` in two parts, which concatenate to the row
+    fn row(&self, row: usize) -> (&[u8], &[u8]);
+
+    /// Returns row `row` in two parts, like `row`
+    fn row_mut(&mut self, row: usize) -> (&mut [u8], &mut [u8]);
+
+    /// Calls `f` with the index and the two parts of each row (in parallel with feature `parallel`)
+    fn for_each_row_mut(&mut self, f: impl Fn(usize, &mut [u8], &mut [u8]) + Send + Sync);
+
+    /// Returns the `N` bytes of row `row` starting at byte `idx`
+    #[cfg(semi_systematic)]
+    fn load_bytes<const N: usize>(&self, row: usize, idx: usize) -> [u8; N] {
+        let mut out = [0u8; N];
+        for (k, b) in out.iter_mut().enumerate() {
+            *b = self.byte(row, idx + k);
+        }
+        out
+    }
+
+    /// Overwrites the bytes of row `row` starting at byte `idx` with `bytes`
+    #[cfg(semi_systematic)]
+    fn store_bytes(&mut self, row: usize, idx: usize, bytes: &[u8]) {
+        for (k, &b) in bytes.iter().enumerate() {
+            self.set_byte(row, idx + k, b);
+        }
+    }
+}
+
+impl MatrixRows for Matrix<'_> {
+    fn byte(&self, row: usize, idx: usize) -> u8 {
+        row_byte(&self.left[row], &self.pk[row * PK_ROW_BYTES..], idx)
+    }
+
+    fn set_byte(&mut self, row: usize, idx: usize, b: u8) {
+        if idx < MAT_LEFT_BYTES {
+            self.left[row][idx] = b;
+        } else {
+            self.pk[row * PK_ROW_BYTES + idx - MAT_LEFT_BYTES] = b;
+        }
+    }
+
+    fn row(&self, row: usize) -> (&[u8], &[u8]) {
+        (
+            &self.left[row][..],
+            &self.pk[row * PK_ROW_BYTES..(row + 1) * PK_ROW_BYTES],
+        )
+    }
+
+    fn row_mut(&mut self, row: usize) -> (&mut [u8], &mut [u8]) {
+        (
+            &mut self.left[row][..],
+            &mut self.pk[row * PK_ROW_BYTES..(row + 1) * PK_ROW_BYTES],
+        )
+    }
+
+    fn for_each_row_mut(&mut self, f: impl Fn(usize, &mut [u8], &mut [u8]) + Send + Sync) {
+        #[cfg(feature = "parallel")]
+        self.left
+            .par_iter_mut()
+            .zip(self.pk.par_chunks_exact_mut(PK_ROW_BYTES))
+            .enumerate()
+            .for_each(|(r, (left, right))| f(r, left, right));
+        #[cfg(not(feature = "parallel"))]
+        self.left
+            .iter_mut()
+            .zip(self.pk.chunks_exact_mut(PK_ROW_BYTES))
+            .enumerate()
+            .for_each(|(r, (left, right))| f(r, left, right));
+    }
+}
+
+impl MatrixRows for [[u8; STREAM_ROW_BYTES]] {
+    fn byte(&self, row: usize, idx: usize) -> u8 {
+        self[row][idx]
+    }
+
+    fn set_byte(&mut self, row: usize, idx: usize, b: u8) {
+        self[row][idx] = b;
+    }
+
+    fn row(&self, row: usize) -> (&[u8], &[u8]) {
+        (&self[row][..], &[])
+    }
+
+    fn row_mut(&mut self, row: usize) -> (&mut [u8], &mut [u8]) {
+        (&mut self[row][..], &mut [])
+    }
+
+    fn for_each_row_mut(&mut self, f: impl Fn(usize, &mut [u8], &mut [u8]) + Send + Sync) {
+        #[cfg(feature = "parallel")]
+        self.par_iter_mut()
+            .enumerate()
+            .for_each(|(r, row)| f(r, row, &mut []));
+        #[cfg(not(feature = "parallel"))]
+        self.iter_mut()
+            .enumerate()
+            .for_each(|(r, row)| f(r, row, &mut []));
+    }
+}
 
 /// Return number of trailing zeros of the non-zero input `input`
-#[cfg(any(
-    feature = "mceliece348864f",
-    feature = "mceliece460896f",
-    feature = "mceliece6688128f",
-    feature = "mceliece6960119f",
-    feature = "mceliece8192128f"
-))]
+#[cfg(semi_systematic)]
 fn ctz(input: u64) -> i32 {
     let (mut m, mut r) = (0i32, 0i32);
 
@@ -39,62 +190,86 @@ fn ctz(input: u64) -> i32 {
     r
 }
 
-/// Takes two 16-bit integers and determines whether they are equal (u64::MAX) or different (0)
-#[cfg(any(
-    feature = "mceliece348864f",
-    feature = "mceliece460896f",
-    feature = "mceliece6688128f",
-    feature = "mceliece6960119f",
-    feature = "mceliece8192128f"
-))]
-fn same_mask(x: u16, y: u16) -> u64 {
-    let mut mask = (x ^ y) as u64;
-    mask = mask.wrapping_sub(1);
-    mask >>= 63;
-    mask = 0u64.wrapping_sub(mask);
-
-    mask
-}
-
-/// Move columns in matrix `mat`
-#[cfg(any(
-    feature = "mceliece348864f",
-    feature = "mceliece460896f",
-    feature = "mceliece6688128f",
-    feature = "mceliece6960119f",
-    feature = "mceliece8192128f"
-))]
-fn mov_columns(
-    mat: &mut [[u8; SYS_N / 8]; PK_NROWS],
-    pi: &mut [i16; 1 << GFBITS],
-    pivots: &mut u64,
-) -> Result<i32, Box<dyn error::Error>> {
-    let mut buf = [0u64; 64];
-    let mut ctz_list = [0u64; 32];
+/// The first of the last 32 rows, whose pivots `mov_columns` chooses from 64 columns
+#[cfg(semi_systematic)]
+const MOV_ROW: usize = PK_NROWS - 32;
 
-    let row = PK_NROWS - 32;
-    let block_idx = row / 8;
+/// Returns the 64 bits of row `i` of `mat` starting at column `MOV_ROW`
+#[cfg(semi_systematic)]
+fn load_block(mat: &(impl MatrixRows + ?Sized), i: usize) -> Result<u64, Error> {
+    #[cfg(not(feature = "mceliece6960119f"))]
+    {
+        Ok(load8(&mat.load_bytes::<8>(i, MOV_ROW / 8)))
+    }
 
     #[cfg(feature = "mceliece6960119f")]
-    let tail = row % 8;
-    #[cfg(feature = "mceliece6960119f")]
-    let mut tmp = [0u8; 9];
+    {
+        let tail = MOV_ROW % 8;
+        let mut tmp = mat.load_bytes::<9>(i, MOV_ROW / 8);
+        for j in 0..8 {
+            tmp[j] = (tmp[j] >> tail) | (tmp[j + 1] << (8 - tail));
+        }
 
+        Ok(load8(sub!(tmp, 0, 8)))
+    }
+}
+
+/// Overwrites the 64 bits of row `i` of `mat` starting at column `MOV_ROW` with `t`
+#[cfg(semi_systematic)]
+fn store_block(mat: &mut (impl MatrixRows + ?Sized), i: usize, t: u64) -> Result<(), Error> {
     #[cfg(not(feature = "mceliece6960119f"))]
-    for i in 0..32 {
-        buf[i] = load8(sub!(mat[row + i], block_idx, 8));
+    {
+        let mut block = [0u8; 8];
+        store8(&mut block, t);
+        mat.store_bytes(i, MOV_ROW / 8, &block);
     }
 
     #[cfg(feature = "mceliece6960119f")]
-    for i in 0..32 {
-        for j in 0..9 {
-            tmp[j] = mat[row + i][block_idx + j];
+    {
+        let tail = MOV_ROW % 8;
+        let mut block = mat.load_bytes::<9>(i, MOV_ROW / 8);
+        let mut tmp = [0u8; 9];
+        store8(sub!(mut tmp, 0, 8), t);
+
+        block[8] = (block[8] >> tail << tail) | (tmp[7] >> (8 - tail));
+        block[0] = (tmp[0] << tail) | (block[0] << (8 - tail) >> (8 - tail));
+
+        for k in (1..=7).rev() {
+            block[k] = (tmp[k] << tail) | (tmp[k - 1] >> (8 - tail));
         }
-        for j in 0..8 {
-            tmp[j] = (tmp[j] >> tail) | (tmp[j + 1] << (8 - tail));
+
+        mat.store_bytes(i, MOV_ROW / 8, &block);
+    }
+
+    Ok(())
+}
+
+/// Swaps column `j` with column `ctz_list[j]` for j = 0, 1, …, 31 (in this order) of the 64 columns `cols`,
+/// i.e. of the transposed 64 × 64 block. Each swap exchanges two 64-bit words, selected with masks in
+/// constant time. The pivots are strictly increasing, thus `ctz_list[j] ≥ j` and only the columns
+/// after `j` need to be considered.
+#[cfg(semi_systematic)]
+fn swap_columns(cols: &mut [u64; 64], ctz_list: &[u64; 32]) {
+    for j in 0..32 {
+        let (head, tail) = cols.split_at_mut(j + 1);
+        for (k, col) in (j as u64 + 1..).zip(tail) {
+            cswap(&mut head[j], col, eq_mask(k, ctz_list[j]));
         }
+    }
+}
+
+/// Move columns in matrix `mat`
+#[cfg(semi_systematic)]
+fn mov_columns(
+    mat: &mut (impl MatrixRows + ?Sized),
+    pi: &mut [i16; 1 << GFBITS],
+    pivots: &mut u64,
+) -> Result<i32, Error> {
+    let mut buf = [0u64; 64];
+    let mut ctz_list = [0u64; 32];
 
-        buf[i] = load8(sub!(tmp, 0, 8));
+    for (i, b) in buf[..32].iter_mut().enumerate() {
+        *b = load_block(mat, MOV_ROW + i)?;
     }
 
     // Compute the column indices of pivots by Gaussian elimination.
@@ -103,8 +278,8 @@ fn mov_columns(
     *pivots = 0;
     for i in 0..32 {
         let mut t = buf[i];
-        for j in i + 1..32 {
-            t |= buf[j];
+        for &b in &buf[i + 1..32] {
+            t |= b;
         }
 
         if t == 0 {
@@ -117,121 +292,274 @@ fn mov_columns(
         *pivots |= 1u64 << s;
 
         for j in i + 1..32 {
-            let mut mask = (buf[i] >> s) & 1;
-            mask = mask.wrapping_sub(1);
-            buf[i] ^= buf[j] & mask;
+            buf[i] ^= buf[j] & !bit_mask(buf[i], s);
         }
 
         for j in i + 1..32 {
-            let mut mask = (buf[j] >> s) & 1;
-            mask = 0u64.wrapping_sub(mask);
-            buf[j] ^= buf[i] & mask;
+            buf[j] ^= buf[i] & bit_mask(buf[j], s);
         }
     }
 
     // updating permutation
     for j in 0..32 {
         for k in j + 1..64 {
-            let mut d = (pi[row + j] ^ pi[row + k]) as u64;
-            d &= same_mask(k as u16, ctz_list[j] as u16);
-            pi[row + j] ^= d as i16;
-            pi[row + k] ^= d as i16;
+            let mut d = (pi[MOV_ROW + j] ^ pi[MOV_ROW + k]) as u64;
+            d &= eq_mask(k as u64, ctz_list[j]);
+            pi[MOV_ROW + j] ^= d as i16;
+            pi[MOV_ROW + k] ^= d as i16;
         }
     }
 
-    // moving columns of mat according to the column indices of pivots
-    #[cfg(not(feature = "mceliece6960119f"))]
-    for i in 0..PK_NROWS {
-        let mut t = load8(sub!(mat[i], block_idx, 8));
+    // moving columns of mat according to the column indices of pivots,
+    // for blocks of 64 rows transposed into 64 columns (see `swap_columns`)
+    for base in (0..PK_NROWS).step_by(64) {
+        let rows = base..PK_NROWS.min(base + 64);
+
+        buf = [0u64; 64];
+        for (w, i) in buf.iter_mut().zip(rows.clone()) {
+            *w = load_block(mat, i)?;
+        }
 
-        for j in 0..32 {
-            let mut d: u64 = t >> j;
-            d ^= t >> ctz_list[j];
-            d &= 1;
+        transpose_64x64_inplace(&mut buf);
+        swap_columns(&mut buf, &ctz_list);
+        transpose_64x64_inplace(&mut buf);
 
-            t ^= d << ctz_list[j];
-            t ^= d << j;
+        for (&w, i) in buf.iter().zip(rows) {
+            store_block(mat, i, w)?;
         }
+    }
+
+    Ok(0)
+}
 
-        store8(sub!(mut mat[i], block_idx, 8), t);
+/// Returns byte `idx` of the row with left part `left` and right part `right` (see `Matrix`)
+fn row_byte(left: &[u8; MAT_LEFT_BYTES], right: &[u8], idx: usize) -> u8 {
+    match idx.checked_sub(MAT_LEFT_BYTES) {
+        None => left[idx],
+        Some(k) => right[k],
     }
+}
 
-    #[cfg(feature = "mceliece6960119f")]
-    for i in 0..PK_NROWS {
-        for k in 0..9 {
-            tmp[k] = mat[i][block_idx + k];
-        }
-        for k in 0..8 {
-            tmp[k] = (tmp[k] >> tail) | (tmp[k + 1] << (8 - tail));
+/// Adds row `src` to row `dst` if `mask` is set (u8::MAX), in 64-bit words
+fn xor_masked(dst: &mut [u8], src: &[u8], mask: u8) {
+    let mask = u64::from_ne_bytes([mask; 8]);
+    let mut dst_words = dst.chunks_exact_mut(8);
+    let mut src_words = src.chunks_exact(8);
+
+    for (d, s) in dst_words.by_ref().zip(src_words.by_ref()) {
+        let (mut x, mut y) = ([0u8; 8], [0u8; 8]);
+        x.copy_from_slice(d);
+        y.copy_from_slice(s);
+        d.copy_from_slice(&(u64::from_le_bytes(x) ^ (u64::from_le_bytes(y) & mask)).to_le_bytes());
+    }
+
+    for (d, &s) in dst_words
+        .into_remainder()
+        .iter_mut()
+        .zip(src_words.remainder())
+    {
+        *d ^= s & mask as u8;
+    }
+}
+
+/// The number of bytes of the rows of the accumulators of `eliminate_pivots`, at least the bytes of the rows
+/// of both matrices
+const ACC_BYTES: usize = SYS_N / 8;
+const _: () = assert!(STREAM_ROW_BYTES <= ACC_BYTES);
+
+/// Adds the row with parts `left` and `right` to the accumulator `acc` if `mask` is set (u8::MAX),
+/// from byte `start` of the row on
+fn xor_into_acc(acc: &mut [u8; ACC_BYTES], left: &[u8], right: &[u8], start: usize, mask: u8) {
+    let (acc_left, acc_right) = acc.split_at_mut(left.len());
+    xor_masked(&mut acc_left[start..], &left[start..], mask);
+    xor_masked(&mut acc_right[..right.len()], right, mask);
+}
+
+/// Adds the accumulator `acc` to the row with parts `left` and `right` if `mask` is set (u8::MAX),
+/// from byte `start` of the row on
+fn xor_from_acc(left: &mut [u8], right: &mut [u8], acc: &[u8; ACC_BYTES], start: usize, mask: u8) {
+    let (acc_left, acc_right) = acc.split_at(left.len());
+    xor_masked(&mut left[start..], &acc_left[start..], mask);
+    xor_masked(right, &acc_right[..right.len()], mask);
+}
+
+/// Gaussian elimination of the columns of the (at most 8) pivot rows `pivots`, which lie in the same byte
+/// of the rows. Returns false if the matrix is not in systematic form, i.e. some pivot is zero.
+///
+/// The row additions are those of the row-by-row elimination (which adds the rows below the pivot row
+/// to it if their bit of the pivot column differs, and then the pivot row to all rows with the bit set),
+/// but the matrix is read in two passes for all pivots together, in the manner of the method of four Russians.
+/// The masks of the additions only depend on the byte of the pivot columns, hence they are derived
+/// by eliminating these bytes first. Then the (final) pivot rows are accumulated in one pass over the rows below
+/// the pivots, and added to the other rows in a second pass. Unlike the method of four Russians, which looks up
+/// precomputed combinations of the pivot rows by the (secret) bits of a row, each pivot row is added with a mask.
+fn eliminate_pivots(mat: &mut (impl MatrixRows + ?Sized), pivots: Range<usize>) -> bool {
+    let (first, end) = (pivots.start, pivots.end);
+    let (i, start) = (first / 8, first / 64 * 8);
+
+    // Bit k of `search[o]` is set if row o is added to the pivot row `first + k` to find the pivot,
+    // and bit k of `elim[o]` if the pivot row `first + k` is added to row o to eliminate the pivot column
+    let mut bytes = [0u8; PK_NROWS];
+    let mut search = [0u8; PK_NROWS];
+    let mut elim = [0u8; PK_NROWS];
+
+    for (o, b) in bytes.iter_mut().enumerate() {
+        *b = mat.byte(o, i);
+    }
+    for (k, row) in pivots.clone().enumerate() {
+        let j = row % 8;
+
+        let mut pivot = bytes[row];
+        for (b, s) in bytes.iter().zip(search.iter_mut()).skip(row + 1) {
+            let mask = bit_mask((pivot ^ b) as u64, j) as u8;
+            *s |= mask & (1 << k);
+            pivot ^= b & mask;
         }
+        bytes[row] = pivot;
 
-        let mut t = load8(sub!(tmp, 0, 8));
+        if ((pivot >> j) & 1) == 0 {
+            #[cfg(feature = "zeroize")]
+            {
+                bytes.zeroize();
+                search.zeroize();
+                elim.zeroize();
+            }
+            return false;
+        }
 
-        for j in 0..32 {
-            let mut d = t >> j;
-            d ^= t >> ctz_list[j];
-            d &= 1;
+        for (o, (b, e)) in bytes.iter_mut().zip(elim.iter_mut()).enumerate() {
+            if o != row {
+                let mask = bit_mask(*b as u64, j) as u8;
+                *e |= mask & (1 << k);
+                *b ^= pivot & mask;
+            }
+        }
+    }
 
-            t ^= d << ctz_list[j];
-            t ^= d << j;
+    // The sums of the rows below the pivots added to each pivot row, as they are at the start. Their additions
+    // of earlier pivot rows are accounted for by bit l of `earlier[k]`, which is set if the pivot row `first + l`
+    // (as it is after its search) is to be added to the pivot row `first + k`.
+    let mut acc = [[0u8; ACC_BYTES]; 8];
+    let mut earlier = [0u8; 8];
+
+    for o in end..PK_NROWS {
+        let (left, right) = mat.row(o);
+        for (k, (a, e)) in acc.iter_mut().zip(earlier.iter_mut()).enumerate() {
+            let mask = bit_mask(search[o] as u64, k) as u8;
+            xor_into_acc(a, left, right, start, mask);
+            *e ^= elim[o] & mask;
         }
+    }
 
-        store8(sub!(mut tmp, 0, 8), t);
+    // The pivot rows after their search, eliminated among each other
+    for (k, row) in pivots.clone().enumerate() {
+        let (done, rest) = acc.split_at_mut(k);
+        let a = &mut rest[0];
+
+        for (q, &s) in (row..end).zip(&search[row..end]) {
+            let (left, right) = mat.row(q);
+            let mask = if q == row {
+                u8::MAX
+            } else {
+                bit_mask(s as u64, k) as u8
+            };
+            xor_into_acc(a, left, right, start, mask);
+        }
+        for (l, d) in done.iter().enumerate() {
+            xor_masked(
+                &mut a[start..],
+                &d[start..],
+                bit_mask(earlier[k] as u64, l) as u8,
+            );
+        }
 
-        mat[i][block_idx + 8] = (mat[i][block_idx + 8] >> tail << tail) | (tmp[7] >> (8 - tail));
-        mat[i][block_idx + 0] = (tmp[0] << tail) | (mat[i][block_idx] << (8 - tail) >> (8 - tail));
+        for q in pivots.clone() {
+            let (left, right) = mat.row_mut(q);
+            let mask = if q == row {
+                left[start..].fill(0);
+                right.fill(0);
+                u8::MAX
+            } else {
+                bit_mask(elim[q] as u64, k) as u8
+            };
+            xor_from_acc(left, right, a, start, mask);
+        }
+    }
 
-        for k in (1..=7).rev() {
-            mat[i][block_idx + k] = (tmp[k] << tail) | (tmp[k - 1] >> (8 - tail));
+    // The pivot rows added to all other rows
+    let sums = &acc[..pivots.len()];
+    let masks = &elim;
+    mat.for_each_row_mut(|o, left, right| {
+        if !pivots.contains(&o) {
+            for (k, a) in sums.iter().enumerate() {
+                xor_from_acc(left, right, a, start, bit_mask(masks[o] as u64, k) as u8);
+            }
         }
+    });
+
+    #[cfg(feature = "zeroize")]
+    {
+        bytes.zeroize();
+        search.zeroize();
+        elim.zeroize();
+        acc.zeroize();
+        earlier.zeroize();
     }
 
-    Ok(0)
+    true
 }
 
-/// Public key generation. Generate the public key `pk`,
-/// permutation `pi` and pivot element `pivots` based on the
-/// secret key `sk` and permutation `perm` provided.
-/// `pk` has `max(1 << GFBITS, SYS_N)` elements which is
-/// 4096 for mceliece348864 and 8192 for mceliece8192128.
-/// `sk` has `2 * SYS_T` elements and perm `1 << GFBITS`.
-pub(crate) fn pk_gen(
-    pk: &mut [u8; PK_NROWS * PK_ROW_BYTES],
-    sk: &[u8; 2 * SYS_T],
-    perm: &[u32; 1 << GFBITS],
-    pi: &mut [i16; 1 << GFBITS],
-    #[cfg(any(
-        feature = "mceliece348864f",
-        feature = "mceliece460896f",
-        feature = "mceliece6688128f",
-        feature = "mceliece6960119f",
-        feature = "mceliece8192128f"
-    ))]
-    pivots: &mut u64,
-) -> Result<i32, Box<dyn error::Error>> {
-    let mut buf = [0u64; 1 << GFBITS];
-    let mut mat = [[0u8; SYS_N / 8]; PK_NROWS];
+/// Gaussian elimination of the matrix `mat` into systematic form (see `eliminate_pivots`).
+/// For the f-variants, the columns are moved by `mov_columns` once the pivots `MOV_ROW` and onwards are left.
+/// Returns -1 if the matrix cannot be brought into (semi-)systematic form.
+fn gaussian_elimination(
+    mat: &mut (impl MatrixRows + ?Sized),
+    #[cfg(semi_systematic)] pi: &mut [i16; 1 << GFBITS],
+    #[cfg(semi_systematic)] pivots: &mut u64,
+) -> Result<i32, Error> {
+    let mut first = 0;
+    while first < PK_NROWS {
+        #[cfg(semi_systematic)]
+        {
+            if first == MOV_ROW && mov_columns(mat, pi, pivots)? != 0 {
+                return Ok(-1);
+            }
+        }
 
-    let mut g = [0u16; SYS_T + 1];
-    let mut l = [0u16; SYS_N];
-    let mut inv = [0u16; SYS_N];
+        // the blocks of pivots end in the byte of the first of them (and before `MOV_ROW`)
+        let end = PK_NROWS.min(first / 8 * 8 + 8);
+        #[cfg(semi_systematic)]
+        let end = if first < MOV_ROW {
+            end.min(MOV_ROW)
+        } else {
+            end
+        };
 
-    g[SYS_T] = 1;
-    for (i, chunk) in sk.chunks(2).take(SYS_T).enumerate() {
-        g[i] = load_gf(sub!(chunk, 0, 2));
+        if !eliminate_pivots(mat, first..end) {
+            return Ok(-1);
+        }
+        first = end;
     }
 
+    Ok(0)
+}
+
+/// Computes the permutation `pi` by sorting the random values `perm`.
+/// Returns -1 if two values are equal, i.e. the permutation would not be uniform.
+fn permutation(perm: &[u32; 1 << GFBITS], pi: &mut [i16; 1 << GFBITS]) -> i32 {
+    let mut buf = [0u64; 1 << GFBITS];
+
     for i in 0..(1 << GFBITS) {
         buf[i] = perm[i] as u64;
         buf[i] <<= 31;
         buf[i] |= i as u64;
     }
 
-    uint64_sort(sub!(mut buf, 0, 1 << GFBITS, u64));
+    uint64_sort(&mut buf);
 
     for i in 1..(1 << GFBITS) {
         if buf[i - 1] >> 31 == buf[i] >> 31 {
-            return Ok(-1);
+            return -1;
         }
     }
 
@@ -239,16 +567,87 @@ pub(crate) fn pk_gen(
         pi[i] = buf[i] as i16 & GFMASK as i16;
     }
 
+    0
+}
+
+/// Computes the support `l` given by the first `SYS_N` elements of `pi` and the inverses `inv`
+/// of the Goppa polynomial `sk` evaluated at the support, i.e. the first row of the parity-check matrix
+fn support_inverses(
+    sk: &[u8; 2 * SYS_T],
+    pi: &[i16; 1 << GFBITS],
+    l: &mut [Gf; SYS_N],
+    inv: &mut [Gf; SYS_N],
+) -> Result<(), Error> {
+    let mut g = [0u16; SYS_T + 1];
+
+    g[SYS_T] = 1;
+    for (i, chunk) in sk.chunks(2).take(SYS_T).enumerate() {
+        g[i] = load_gf(sub!(chunk, 0, 2));
+    }
+
     for i in 0..SYS_N {
         l[i] = bitrev(pi[i] as u16);
     }
 
-    root(&mut inv, &g, &l);
+    root(inv, &g, l);
+    gf_inv_batch(inv, &mut [0; SYS_N]);
 
-    for i in 0..SYS_N {
-        inv[i] = gf_inv(inv[i]);
+    Ok(())
+}
+
+/// Public key generation. Generate the public key `pk`,
+/// permutation `pi` and pivot element `pivots` based on the
+/// secret key `sk` and permutation `perm` provided.
+/// `pk` has `max(1 << GFBITS, SYS_N)` elements which is
+/// 4096 for mceliece348864 and 8192 for mceliece8192128.
+/// `sk` has `2 * SYS_T` elements and perm `1 << GFBITS`.
+/// `left` is the memory for the matrix (see `pk_from_permutation`).
+pub(crate) fn pk_gen(
+    pk: &mut [u8; PK_NROWS * PK_ROW_BYTES],
+    left: &mut [[u8; MAT_LEFT_BYTES]; PK_NROWS],
+    sk: &[u8; 2 * SYS_T],
+    perm: &[u32; 1 << GFBITS],
+    pi: &mut [i16; 1 << GFBITS],
+    #[cfg(semi_systematic)] pivots: &mut u64,
+) -> Result<i32, Error> {
+    if permutation(perm, pi) != 0 {
+        return Ok(-1);
     }
 
+    pk_from_permutation(
+        pk,
+        left,
+        sk,
+        pi,
+        #[cfg(semi_systematic)]
+        pivots,
+    )
+}
+
+/// Public key computation. Computes the public key `pk` based on the
+/// Goppa polynomial `sk` and the permutation `pi` whose first `SYS_N`
+/// elements determine the support. For the f-variants, columns are moved
+/// if necessary, which updates `pi` and `pivots`.
+/// Returns -1 if the matrix cannot be brought into systematic form. The elimination returns at the first row
+/// without a pivot, i.e. a failed attempt skips the remaining rows (like `pk_rows_from_permutation`).
+///
+/// The matrix is computed in `left` and `pk` (see `Matrix`), which are completely overwritten,
+/// i.e. `pk` is garbage if -1 is returned. `left` takes up to 340 KB for the larger variants,
+/// hence it is allocated on the heap by the callers (see `Workspace`).
+pub(crate) fn pk_from_permutation(
+    pk: &mut [u8; PK_NROWS * PK_ROW_BYTES],
+    left: &mut [[u8; MAT_LEFT_BYTES]; PK_NROWS],
+    sk: &[u8; 2 * SYS_T],
+    pi: &mut [i16; 1 << GFBITS],
+    #[cfg(semi_systematic)] pivots: &mut u64,
+) -> Result<i32, Error> {
+    let mut mat = Matrix { left, pk };
+
+    let mut l = [0u16; SYS_N];
+    let mut inv = [0u16; SYS_N];
+
+    support_inverses(sk, pi, &mut l, &mut inv)?;
+
     for i in 0..SYS_T {
         for j in (0..SYS_N).step_by(8) {
             for k in 0..GFBITS {
@@ -266,118 +665,205 @@ pub(crate) fn pk_gen(
                 b <<= 1;
                 b |= ((inv[j + 1] >> k) & 1) as u8;
                 b <<= 1;
-                b |= ((inv[j + 0] >> k) & 1) as u8;
+                b |= ((inv[j] >> k) & 1) as u8;
 
-                mat[i * GFBITS + k][j / 8] = b;
+                mat.set_byte(i * GFBITS + k, j / 8, b);
             }
         }
         for j in 0..SYS_N {
             inv[j] = gf_mul(inv[j], l[j]);
         }
     }
-    // gaussian elimination
-    const ROWS: usize = (PK_NROWS + 7) / 8;
-
-    for i in 0..ROWS {
-        for j in 0..8 {
-            // NOTE: this loop is much very slow without optimization.
-            //       test_pk_gen_2 takes 126s, but 4s with opt-level=1.
-            let row = i * 8 + j;
-
-            if row >= PK_NROWS {
-                break;
-            }
+    if gaussian_elimination(
+        &mut mat,
+        #[cfg(semi_systematic)]
+        pi,
+        #[cfg(semi_systematic)]
+        pivots,
+    )? != 0
+    {
+        return Ok(-1);
+    }
 
-            #[cfg(any(
-                feature = "mceliece348864f",
-                feature = "mceliece460896f",
-                feature = "mceliece6688128f",
-                feature = "mceliece6960119f",
-                feature = "mceliece8192128f"
-            ))]
-            {
-                if row == PK_NROWS - 32 {
-                    if mov_columns(&mut mat, pi, pivots)? != 0 {
-                        return Ok(-1);
-                    }
-                }
+    // The right parts of the rows are the rows of the public key. For mceliece6960119, the columns
+    // of the identity end within the first of these bytes, hence the rows are shifted by the remaining bits.
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    {
+        let tail = PK_NROWS % 8;
+        for row in mat.pk.chunks_exact_mut(PK_ROW_BYTES) {
+            for j in 0..PK_ROW_BYTES - 1 {
+                row[j] = (row[j] >> tail) | (row[j + 1] << (8 - tail));
             }
+            row[PK_ROW_BYTES - 1] >>= tail;
+        }
+    }
 
-            for k in (row + 1)..PK_NROWS {
-                let mut mask = mat[row][i] ^ mat[k][i];
-                mask >>= j;
-                mask &= 1;
-                mask = 0u8.wrapping_sub(mask);
+    Ok(0)
+}
 
-                for c in 0..SYS_N / 8 {
-                    mat[row][c] ^= mat[k][c] & mask;
-                }
-            }
+/// Public key generation streaming the rows of the public key.
+/// Like `pk_gen`, but the rows of the public key are passed to `writer` one after another
+/// (see `pk_rows_from_permutation`) instead of being stored.
+pub(crate) fn pk_gen_streaming(
+    writer: &mut impl FnMut(&[u8]),
+    rows: &mut StreamMatrix,
+    sk: &[u8; 2 * SYS_T],
+    perm: &[u32; 1 << GFBITS],
+    pi: &mut [i16; 1 << GFBITS],
+    #[cfg(semi_systematic)] pivots: &mut u64,
+) -> Result<i32, Error> {
+    if permutation(perm, pi) != 0 {
+        return Ok(-1);
+    }
 
-            if ((mat[row][i] >> j) & 1) == 0 {
-                return Ok(-1);
-            }
+    pk_rows_from_permutation(
+        writer,
+        rows,
+        sk,
+        pi,
+        #[cfg(semi_systematic)]
+        pivots,
+    )
+}
 
-            for k in 0..PK_NROWS {
-                if k == row {
-                    continue;
-                }
+/// Public key computation streaming the rows of the public key.
+/// Computes the same public key as `pk_from_permutation`, but passes its rows (of `PK_ROW_BYTES` bytes)
+/// to `writer` in order instead of storing them. `writer` is only called once the matrix
+/// is known to be in systematic form, i.e. not at all if -1 is returned.
+///
+/// The rows of the public key are final only at the end of the Gaussian elimination. Hence, the elimination
+/// runs on the left `PK_NROWS × PK_NROWS` block of the matrix only (and the columns moved by `mov_columns`),
+/// next to the identity, which thereby turns into the inverse `S` of the block. The public key is `S` times the
+/// right block of the matrix, whose columns are recomputed from the support for each `STREAM_ROWS` rows of `S`.
+/// The matrix `rows` thus takes about half of the memory of `pk_from_permutation`,
+/// at the cost of recomputing the right block of the matrix `PK_NROWS / STREAM_ROWS` times.
+fn pk_rows_from_permutation(
+    writer: &mut impl FnMut(&[u8]),
+    rows: &mut StreamMatrix,
+    sk: &[u8; 2 * SYS_T],
+    pi: &mut [i16; 1 << GFBITS],
+    #[cfg(semi_systematic)] pivots: &mut u64,
+) -> Result<i32, Error> {
+    let mut l = [0u16; SYS_N];
+    let mut inv = [0u16; SYS_N];
 
-                let mut mask = mat[k][i] >> j;
-                mask &= 1;
-                mask = 0u8.wrapping_sub(mask);
+    support_inverses(sk, pi, &mut l, &mut inv)?;
 
-                for c in 0..(SYS_N / 8) {
-                    mat[k][c] ^= mat[row][c] & mask;
-                }
+    for row in rows.iter_mut() {
+        row.fill(0);
+    }
+    for (j, (&x, &y)) in inv.iter().zip(l.iter()).enumerate().take(EXT_BYTES * 8) {
+        let mut e = x;
+        for block in rows.chunks_exact_mut(GFBITS) {
+            for (k, row) in block.iter_mut().enumerate() {
+                row[j / 8] |= (((e >> k) & 1) as u8) << (j % 8);
             }
+            e = gf_mul(e, y);
         }
     }
+    for (r, row) in rows.iter_mut().enumerate() {
+        row[EXT_BYTES + r / 8] |= 1 << (r % 8);
+    }
 
-    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-    let tail = PK_NROWS % 8;
-    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-    const INNER_PK_ACCESSES: usize = ((SYS_N / 8 - 1) - (PK_NROWS - 1) / 8) + 1;
+    if gaussian_elimination(
+        &mut rows[..],
+        #[cfg(semi_systematic)]
+        pi,
+        #[cfg(semi_systematic)]
+        pivots,
+    )? != 0
+    {
+        return Ok(-1);
+    }
+
+    // For the f-variants, the support changed with the moved columns
+    #[cfg(semi_systematic)]
+    support_inverses(sk, pi, &mut l, &mut inv)?;
 
-    for i in 0..PK_NROWS {
-        #[cfg(not(any(feature = "mceliece6960119", feature = "mceliece6960119f")))]
-        pk[i * PK_ROW_BYTES..(i + 1) * PK_ROW_BYTES]
-            .copy_from_slice(&mat[i][PK_NROWS / 8..PK_NROWS / 8 + PK_ROW_BYTES]);
+    // Bit x of `columns[b]` is entry (x, b) of the current rows of `S`. Then bit x of the sum of the
+    // `columns[b]` for the set bits b of a column of the right block is the entry of the public key.
+    let mut columns = [0u64; PK_NROWS];
+    let mut out = vec![[0u8; PK_ROW_BYTES]; STREAM_ROWS];
 
-        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-        for (idx, j) in ((PK_NROWS - 1) / 8..SYS_N / 8 - 1).enumerate() {
-            pk[i * INNER_PK_ACCESSES + idx] = (mat[i][j] >> tail) | (mat[i][j + 1] << (8 - tail));
+    for group in rows.chunks(STREAM_ROWS) {
+        columns.fill(0);
+        for (x, row) in group.iter().enumerate() {
+            for (b, c) in columns.iter_mut().enumerate() {
+                *c |= (((row[EXT_BYTES + b / 8] >> (b % 8)) & 1) as u64) << x;
+            }
         }
-        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
-        {
-            pk[(i + 1) * INNER_PK_ACCESSES - 1] = mat[i][SYS_N / 8 - 1] >> tail;
+
+        for o in out.iter_mut() {
+            o.fill(0);
+        }
+        for (c, (&x, &y)) in inv.iter().zip(l.iter()).skip(PK_NROWS).enumerate() {
+            let mut e = x;
+            let mut sum = 0u64;
+            for block in columns.chunks_exact(GFBITS) {
+                for (k, &w) in block.iter().enumerate() {
+                    sum ^= w & 0u64.wrapping_sub(((e >> k) & 1) as u64);
+                }
+                e = gf_mul(e, y);
+            }
+            for (x, o) in out.iter_mut().enumerate() {
+                o[c / 8] |= (((sum >> x) & 1) as u8) << (c % 8);
+            }
+        }
+
+        for o in out.iter().take(group.len()) {
+            writer(o);
         }
     }
 
+    #[cfg(feature = "zeroize")]
+    columns.zeroize();
+
     Ok(0)
 }
 
 #[cfg(test)]
 mod tests {
-    #[cfg(any(
-        feature = "mceliece348864f",
-        feature = "mceliece460896f",
-        feature = "mceliece6688128f",
-        feature = "mceliece6960119f",
-        feature = "mceliece8192128f"
-    ))]
     use super::*;
     #[cfg(feature = "mceliece8192128f")]
     use crate::api::CRYPTO_PUBLICKEYBYTES;
+    #[cfg(feature = "mceliece8192128f")]
+    use crate::workspace::Workspace;
+    #[cfg(feature = "mceliece8192128f")]
+    use std::error;
+
+    #[test]
+    fn test_eliminate_pivots() {
+        // rows of pseudo-random bits, whose first 64 columns have full rank
+        let mut rows: Box<StreamMatrix> = bytemuck::zeroed_box();
+        let mut x = 0u64;
+        for b in rows.iter_mut().flatten() {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            *b = (x >> 56) as u8;
+        }
+
+        // the blocks of pivots perform the row additions of the pivots one at a time
+        let mut expected = rows.clone();
+        for pivot in 0..64 {
+            assert!(eliminate_pivots(&mut expected[..], pivot..pivot + 1));
+        }
+        assert!(eliminate_pivots(&mut rows[..], 0..3));
+        assert!(eliminate_pivots(&mut rows[..], 3..8));
+        for first in (8..64).step_by(8) {
+            assert!(eliminate_pivots(&mut rows[..], first..first + 8));
+        }
+        assert!(rows == expected);
+
+        // the first 64 columns are those of the identity
+        for (r, row) in rows.iter().enumerate() {
+            let word = u64::from_le_bytes(row[..8].try_into().unwrap());
+            assert_eq!(word, if r < 64 { 1 << r } else { 0 });
+        }
+    }
 
     #[test]
-    #[cfg(any(
-        feature = "mceliece348864f",
-        feature = "mceliece460896f",
-        feature = "mceliece6688128f",
-        feature = "mceliece6960119f",
-        feature = "mceliece8192128f"
-    ))]
+    #[cfg(semi_systematic)]
     fn test_ctz() {
         const EXPECTED: [i32; 180] = [
             64, 0, 1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 2, 0, 1, 0, 4, 0, 1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 2,
@@ -388,52 +874,43 @@ mod tests {
             0, 1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 2, 0, 1, 0, 5, 0, 1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 2, 0,
             1, 0, 4, 0, 1, 0,
         ];
-        for i in 0..180 {
-            assert_eq!(ctz(i as u64), EXPECTED[i]);
+        for (i, &expected) in EXPECTED.iter().enumerate() {
+            assert_eq!(ctz(i as u64), expected);
         }
     }
 
     #[test]
-    #[cfg(any(
-        feature = "mceliece348864f",
-        feature = "mceliece460896f",
-        feature = "mceliece6688128f",
-        feature = "mceliece6960119f",
-        feature = "mceliece8192128f"
-    ))]
-    fn test_same_mask() {
-        const EXPECTED: [u64; 25] = [
-            0xFFFFFFFFFFFFFFFF,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0xFFFFFFFFFFFFFFFF,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0xFFFFFFFFFFFFFFFF,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0xFFFFFFFFFFFFFFFF,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0xFFFFFFFFFFFFFFFF,
-        ];
-        for i in 0..5 {
-            for j in 0..5 {
-                assert_eq!(same_mask(i as u16, j as u16), EXPECTED[i * 5 + j]);
+    #[cfg(semi_systematic)]
+    fn test_swap_columns() {
+        // increasing pivots, some of them in their own column
+        let mut ctz_list = [0u64; 32];
+        for (j, c) in ctz_list.iter_mut().enumerate() {
+            *c = (j + j / 3) as u64;
+        }
+
+        let mut rows = [0u64; 64];
+        let mut x = 0x9E3779B97F4A7C15u64;
+        for w in rows.iter_mut() {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            *w = x;
+        }
+
+        // the single-bit swaps of each row of the reference implementation
+        let mut expected = rows;
+        for t in expected.iter_mut() {
+            for (j, &c) in ctz_list.iter().enumerate() {
+                let d = ((*t >> j) ^ (*t >> c)) & 1;
+                *t ^= d << c;
+                *t ^= d << j;
             }
         }
+
+        transpose_64x64_inplace(&mut rows);
+        swap_columns(&mut rows, &ctz_list);
+        transpose_64x64_inplace(&mut rows);
+        assert_eq!(rows, expected);
     }
 
     #[test]
@@ -442,13 +919,18 @@ mod tests {
         const COLS: usize = SYS_N / 8;
 
         // input data
-        let mut mat = vec![[0u8; COLS]; PK_NROWS];
+        let mut left = vec![[0u8; MAT_LEFT_BYTES]; PK_NROWS];
+        let mut pk = vec![0u8; CRYPTO_PUBLICKEYBYTES];
+        let mut mat = Matrix {
+            left: sub!(mut left.as_mut_slice(), 0, PK_NROWS, [u8; MAT_LEFT_BYTES]),
+            pk: sub!(mut pk, 0, CRYPTO_PUBLICKEYBYTES),
+        };
         let mat_data = crate::TestData::new().u8vec("mceliece8192128f_mat_before");
         assert_eq!(mat_data.len(), PK_NROWS * COLS);
 
         for row in 0..PK_NROWS {
             for col in 0..COLS {
-                mat[row][col] = mat_data[row * COLS + col];
+                mat.set_byte(row, col, mat_data[row * COLS + col]);
             }
         }
 
@@ -456,11 +938,7 @@ mod tests {
         let mut pivots = 0u64;
 
         // generated actual result
-        mov_columns(
-            sub!(mut mat.as_mut_slice(), 0, PK_NROWS, [u8; COLS]),
-            sub!(mut pi, 0, 1 << GFBITS, i16),
-            &mut pivots,
-        )?;
+        mov_columns(&mut mat, sub!(mut pi, 0, 1 << GFBITS, i16), &mut pivots)?;
 
         // expected data
         let mut mat_expected = [[0u8; COLS]; PK_NROWS];
@@ -474,7 +952,11 @@ mod tests {
         let pivots_expected = 8053063679u64;
 
         // comparison
-        assert_eq!(mat, mat_expected);
+        for (row, expected) in mat_expected.iter().enumerate() {
+            for (col, &b) in expected.iter().enumerate() {
+                assert_eq!(mat.byte(row, col), b);
+            }
+        }
         assert_eq!(pi, pi_expected);
         assert_eq!(pivots, pivots_expected);
 
@@ -505,8 +987,9 @@ mod tests {
 
         pk_gen(
             sub!(mut pk, 0, CRYPTO_PUBLICKEYBYTES),
-            &mut sk,
-            &mut perm,
+            Workspace::new().matrix(),
+            &sk,
+            &perm,
             &mut pi,
             &mut pivots,
         )?;
@@ -556,8 +1039,9 @@ mod tests {
 
         pk_gen(
             sub!(mut pk, 0, CRYPTO_PUBLICKEYBYTES),
-            &mut sk,
-            &mut perm,
+            Workspace::new().matrix(),
+            &sk,
+            &perm,
             &mut pi,
             &mut pivots,
         )?;
@@ -0,0 +1,100 @@
+//! Implementations of the `EncodePrivateKey`/`DecodePrivateKey` traits of `pkcs8` (feature `pkcs8`)
+//!
+//! The `privateKey` of the `PrivateKeyInfo` is the secret key in the format of the specification
+//! (`SecretKey::as_ref`), the algorithm is the OID of the compiled variant (`Variant::oid`) without
+//! parameters, like Botan encodes it. Decoding rejects other OIDs, parameters and keys of a wrong
+//! length or with nonzero padding bits.
+
+use std::convert::TryFrom;
+
+use pkcs8::der::asn1::ObjectIdentifier;
+use pkcs8::der::SecretDocument;
+use pkcs8::{AlgorithmIdentifierRef, EncodePrivateKey, PrivateKeyInfo};
+
+use crate::types::SecretKey;
+use crate::variant::Variant;
+
+/// The OID of the compiled variant
+const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap(Variant::compiled().oid());
+
+/// The `AlgorithmIdentifier` of the compiled variant, without parameters
+const ALGORITHM: AlgorithmIdentifierRef<'static> = AlgorithmIdentifierRef {
+    oid: OID,
+    parameters: None,
+};
+
+impl EncodePrivateKey for SecretKey {
+    fn to_pkcs8_der(&self) -> pkcs8::Result<SecretDocument> {
+        SecretDocument::try_from(PrivateKeyInfo::new(ALGORITHM, self.as_ref()))
+    }
+}
+
+impl TryFrom<PrivateKeyInfo<'_>> for SecretKey {
+    type Error = pkcs8::Error;
+
+    fn try_from(info: PrivateKeyInfo<'_>) -> pkcs8::Result<SecretKey> {
+        info.algorithm.assert_algorithm_oid(OID)?;
+        if info.algorithm.parameters.is_some() {
+            return Err(pkcs8::Error::ParametersMalformed);
+        }
+        SecretKey::try_from(info.private_key).map_err(|_| pkcs8::Error::KeyMalformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkcs8::der::asn1::AnyRef;
+    use pkcs8::der::Encode;
+    use pkcs8::DecodePrivateKey;
+
+    fn secret_key() -> SecretKey {
+        let mut sk = SecretKey::zero();
+        for (i, b) in sk.0.iter_mut().enumerate() {
+            *b = (i * 89 + 7) as u8;
+        }
+        sk
+    }
+
+    #[test]
+    fn test_pkcs8_roundtrip() {
+        let sk = secret_key();
+        let der = sk.to_pkcs8_der().unwrap();
+        let info = PrivateKeyInfo::try_from(der.as_bytes()).unwrap();
+        assert_eq!(info.algorithm.oid.to_string(), Variant::compiled().oid());
+        assert_eq!(info.private_key, sk.as_ref());
+
+        let decoded = SecretKey::from_pkcs8_der(der.as_bytes()).unwrap();
+        assert_eq!(decoded.as_ref(), sk.as_ref());
+    }
+
+    #[test]
+    fn test_pkcs8_invalid() {
+        let sk = secret_key();
+        let encode = |oid, parameters, key: &[u8]| {
+            PrivateKeyInfo::new(AlgorithmIdentifierRef { oid, parameters }, key)
+                .to_der()
+                .unwrap()
+        };
+
+        // the OID of another variant
+        let other = Variant::ALL
+            .into_iter()
+            .find(|&v| v != Variant::compiled())
+            .unwrap();
+        let der = encode(ObjectIdentifier::new_unwrap(other.oid()), None, sk.as_ref());
+        assert!(SecretKey::from_pkcs8_der(&der).is_err());
+
+        let der = encode(OID, Some(AnyRef::NULL), sk.as_ref());
+        assert_eq!(
+            SecretKey::from_pkcs8_der(&der).err(),
+            Some(pkcs8::Error::ParametersMalformed)
+        );
+
+        let der = encode(OID, None, &sk.as_ref()[1..]);
+        assert_eq!(
+            SecretKey::from_pkcs8_der(&der).err(),
+            Some(pkcs8::Error::KeyMalformed)
+        );
+    }
+}
@@ -6,22 +6,13 @@
 
 use aes::BlockEncrypt;
 use aes::NewBlockCipher;
-use std::error;
+use rand_core::{CryptoRng, RngCore};
 use std::fmt;
 
-/// Trait requiring primitives to generate pseudo-random numbers.
-/// `AesState` is an object implementing this trait.
-pub trait RNGState {
-    /// Fill the buffer `x` with pseudo-random bytes resulting from the
-    /// RNG run updating the RNG state
-    fn randombytes(&mut self, x: &mut [u8]) -> Result<(), Box<dyn error::Error>>;
-    /// Initialize/reset the RNG state based on the seed provided as `entropy_input`
-    fn randombytes_init(&mut self, entropy_input: [u8; 48]);
-}
-
 /// AesState is a struct storing data of a pseudo-random number generator.
-/// Using `randombytes_init`, it can be initialized once. Using `randombytes`,
-/// one can successively fetch new pseudo-random numbers.
+/// Using `randombytes_init`, it can be initialized once. Using the `RngCore` methods
+/// (e.g. `fill_bytes`), one can successively fetch new pseudo-random numbers.
+/// Every call of `fill_bytes` corresponds to one call of `randombytes` in the NIST reference.
 #[derive(Clone, Debug, PartialEq)]
 pub struct AesState {
     pub key: [u8; 32],
@@ -75,12 +66,10 @@ impl AesState {
         key[16..32].copy_from_slice(&temp[1]);
         v.copy_from_slice(&temp[2]);
     }
-}
 
-impl RNGState for AesState {
     /// Fill the buffer `x` with pseudo-random bytes resulting from the
     /// AES run in counter mode updating the object state
-    fn randombytes(&mut self, x: &mut [u8]) -> Result<(), Box<dyn error::Error>> {
+    fn randombytes(&mut self, x: &mut [u8]) {
         for chunk in x.chunks_mut(16) {
             let count = u128::from_be_bytes(self.v);
             self.v.copy_from_slice(&(count + 1).to_be_bytes());
@@ -93,12 +82,10 @@ impl RNGState for AesState {
 
         Self::aes256_ctr_update(&mut None, &mut self.key, &mut self.v);
         self.reseed_counter += 1;
-
-        Ok(())
     }
 
     /// Initialize/reset the state based on the seed provided as `entropy_input`
-    fn randombytes_init(&mut self, entropy_input: [u8; 48]) {
+    pub fn randombytes_init(&mut self, entropy_input: [u8; 48]) {
         self.key = [0u8; 32];
         self.v = [0u8; 16];
         self.reseed_counter = 1i32;
@@ -108,6 +95,27 @@ impl RNGState for AesState {
     }
 }
 
+impl RngCore for AesState {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.randombytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.randombytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for AesState {}
+
 impl Default for AesState {
     fn default() -> Self {
         Self::new()
@@ -130,6 +138,7 @@ impl fmt::Display for AesState {
 mod tests {
     use super::*;
     use std::convert::TryFrom;
+    use std::error;
 
     #[test]
     fn test_rng() -> Result<(), Box<dyn error::Error>> {
@@ -140,17 +149,17 @@ mod tests {
 
         for i in 0..48 {
             entropy_input[i] = i as u8;
-            personalization_string[i] = 0 as u8;
+            personalization_string[i] = 0u8;
         }
 
         rng_state.randombytes_init(entropy_input);
 
-        rng_state.randombytes(&mut data)?;
+        rng_state.fill_bytes(&mut data);
         let ref1_src = crate::TestData::new().u8vec("rng_ref1");
         let ref1 = <[u8; 256]>::try_from(ref1_src).unwrap();
         assert_eq!(data, ref1);
 
-        rng_state.randombytes(&mut data)?;
+        rng_state.fill_bytes(&mut data);
         let ref2_src = crate::TestData::new().u8vec("rng_ref2");
         let ref2 = <[u8; 256]>::try_from(ref2_src).unwrap();
         assert_eq!(data, ref2);
@@ -1,12 +1,23 @@
 //! This file is for evaluating a polynomial at one or more field elements
 
+#[cfg(test)]
+use crate::gf::gf_mul;
+#[cfg(any(test, not(feature = "decap-only")))]
+use crate::gf::{gf_add, gf_mul_slice};
+#[cfg(any(test, not(feature = "decap-only")))]
+use crate::params::SYS_N;
 use crate::{
-    gf::{gf_add, gf_mul, Gf},
-    params::{SYS_N, SYS_T},
+    fft::fft,
+    gf::{gf_iszero, Gf},
+    params::{GFBITS, SYS_T},
 };
+#[cfg(all(feature = "parallel", any(test, not(feature = "decap-only"))))]
+use rayon::prelude::*;
 
 /// Evaluate polynomial `f` with argument `a`.
 /// Thus it returns `f(a)` in symbolic notation.
+/// This is the reference for the evaluations by `fft`.
+#[cfg(test)]
 pub(crate) fn eval(f: &[Gf; SYS_T + 1], a: Gf) -> Gf {
     let mut r: Gf = f[SYS_T];
 
@@ -17,11 +28,73 @@ pub(crate) fn eval(f: &[Gf; SYS_T + 1], a: Gf) -> Gf {
     r
 }
 
+/// Evaluates polynomial `f` at the field elements `points` into `out`, i.e. `out[k] = f(points[k])`.
+/// Only the length of the shorter slice is processed.
+///
+/// The Horner steps of blocks of 64 points are interleaved: each coefficient is applied to all points
+/// of a block before the next one, so the multiplications of a step are independent
+/// and keep the pipeline (or the vector units) busy. The decryption evaluates at all field elements
+/// with the additive FFT in `root_bits` instead, which needs fewer multiplications.
+#[cfg(any(test, not(feature = "decap-only")))]
+pub(crate) fn eval_batch(f: &[Gf; SYS_T + 1], points: &[Gf], out: &mut [Gf]) {
+    const BLOCK: usize = 64;
+
+    let n = points.len().min(out.len());
+    for (out, points) in out[..n].chunks_mut(BLOCK).zip(points[..n].chunks(BLOCK)) {
+        let mut r = [f[SYS_T]; BLOCK];
+        let mut prod = [0; BLOCK];
+
+        for &fi in f[..SYS_T].iter().rev() {
+            gf_mul_slice(&r, points, &mut prod);
+            for (ri, &pi) in r.iter_mut().zip(&prod) {
+                *ri = gf_add(pi, fi);
+            }
+        }
+
+        for (o, &ri) in out.iter_mut().zip(&r) {
+            *o = ri;
+        }
+    }
+}
+
+/// The number of points evaluated by one task of `root` with feature `parallel`
+#[cfg(all(feature = "parallel", any(test, not(feature = "decap-only"))))]
+const PARALLEL_POINTS: usize = 1024;
+
 /// Given polynomial `f` and a list of field elements `l`,
 /// return the roots `out` satisfying `[ f(a) for a in L ]`
+///
+/// The key generation uses it for the support given by a permutation, which has no control bits yet.
+/// The decryption uses `root_bits` instead. With feature `parallel`, blocks of `PARALLEL_POINTS`
+/// points are evaluated on the threads of `rayon`.
+#[cfg(any(test, not(feature = "decap-only")))]
 pub(crate) fn root(out: &mut [Gf; SYS_N], f: &[Gf; SYS_T + 1], l: &[Gf; SYS_N]) {
-    for i in 0..SYS_N {
-        out[i] = eval(f, l[i]);
+    #[cfg(feature = "parallel")]
+    out.par_chunks_mut(PARALLEL_POINTS)
+        .zip(l.par_chunks(PARALLEL_POINTS))
+        .for_each(|(out, points)| eval_batch(f, points, out));
+    #[cfg(not(feature = "parallel"))]
+    eval_batch(f, l, out);
+}
+
+/// Given polynomial `f`, return the bits `out` where bit `a` is set iff `f` vanishes at field element `a`,
+/// with the field elements in the bit-reversed order of `fft` (like `unpermute_bits`).
+///
+/// `f` is evaluated at all field elements with the additive FFT in `images`, which is overwritten.
+/// The decryption moves the bits to the order of the support with `permute_bits`, so neither the
+/// evaluations are permuted nor is any evaluation looked up by a (secret) element of the support.
+pub(crate) fn root_bits(
+    out: &mut [u8; (1 << GFBITS) / 8],
+    f: &[Gf; SYS_T + 1],
+    images: &mut [Gf; 1 << GFBITS],
+) {
+    fft(images, f);
+
+    for (byte, chunk) in out.iter_mut().zip(images.chunks_exact(8)) {
+        *byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (j, &x)| acc | ((gf_iszero(x) & 1) as u8) << j);
     }
 }
 
@@ -29,6 +102,9 @@ pub(crate) fn root(out: &mut [Gf; SYS_N], f: &[Gf; SYS_T + 1], l: &[Gf; SYS_N])
 mod tests {
     use super::*;
     use crate::api::CRYPTO_PRIMITIVE;
+    use crate::benes::{permute_bits, support_gen};
+    use crate::error::Error;
+    use crate::params::COND_BYTES;
 
     #[test]
     #[cfg(feature = "mceliece8192128f")]
@@ -37,8 +113,8 @@ mod tests {
         let mut l = [0u16; SYS_N];
         let mut inv = [0u16; SYS_N];
 
-        for i in 0..l.len() {
-            l[i] = i as u16;
+        for (i, x) in l.iter_mut().enumerate() {
+            *x = i as u16;
         }
 
         root(&mut inv, &g, &l);
@@ -53,12 +129,12 @@ mod tests {
         let mut f = [0u16; SYS_T + 1];
         let mut l = [0u16; SYS_N];
 
-        for i in 0..SYS_N {
-            out[i] = (i as Gf).wrapping_add(3);
-            l[i] = (i as Gf).wrapping_add(7);
+        for (i, (o, x)) in out.iter_mut().zip(l.iter_mut()).enumerate() {
+            *o = (i as Gf).wrapping_add(3);
+            *x = (i as Gf).wrapping_add(7);
         }
-        for i in 0..(SYS_T + 1) {
-            f[i] = (i as Gf).wrapping_mul(3);
+        for (i, c) in f.iter_mut().enumerate() {
+            *c = (i as Gf).wrapping_mul(3);
         }
 
         root(&mut out, &f, &l);
@@ -69,4 +145,56 @@ mod tests {
         let expected = crate::TestData::new().u16vec(&name);
         assert_eq!(expected, out);
     }
+
+    #[test]
+    fn test_eval_batch() {
+        let mut f = [0u16; SYS_T + 1];
+        for (i, fi) in f.iter_mut().enumerate() {
+            *fi = (i * 2731 + 5) as Gf & ((1 << GFBITS) - 1);
+        }
+
+        // a partial last block and a longer output
+        let points: Vec<Gf> = (0..150).map(|a| (a * 37 + 11) as Gf).collect();
+        let mut out = [0xFFFFu16; 151];
+        eval_batch(&f, &points, &mut out);
+
+        for (&a, &o) in points.iter().zip(&out) {
+            assert_eq!(o, eval(&f, a), "f({a})");
+        }
+        assert_eq!(out[150], 0xFFFF);
+    }
+
+    #[test]
+    fn test_root_bits() -> Result<(), Error> {
+        let mut c = [0u8; COND_BYTES];
+        for (i, ci) in c.iter_mut().enumerate() {
+            *ci = (i * 167 + 13) as u8;
+        }
+        let mut l = [0u16; SYS_N];
+        support_gen(&mut l, &c)?;
+
+        // f = (x - l[0]) · … · (x - l[t-1]) vanishes at the first t elements of the support only
+        let mut f = [0u16; SYS_T + 1];
+        f[0] = 1;
+        for (k, &a) in l[..SYS_T].iter().enumerate() {
+            for i in (1..=k + 1).rev() {
+                f[i] = gf_add(f[i - 1], gf_mul(f[i], a));
+            }
+            f[0] = gf_mul(f[0], a);
+        }
+
+        let mut bits = [0u8; (1 << GFBITS) / 8];
+        root_bits(&mut bits, &f, &mut [0; 1 << GFBITS]);
+        permute_bits(&mut bits, &c)?;
+
+        for i in 0..SYS_N {
+            assert_eq!(
+                (bits[i / 8] >> (i % 8)) & 1 == 1,
+                i < SYS_T,
+                "support element {i}"
+            );
+        }
+
+        Ok(())
+    }
 }
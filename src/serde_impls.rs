@@ -0,0 +1,125 @@
+//! Implementations of the `Serialize`/`Deserialize` traits of `serde` (feature `serde`)
+//!
+//! Each type is serialized as one byte string (`serialize_bytes`), like `serde_bytes` does,
+//! instead of a sequence of `LEN` integers. Formats without byte strings (e.g. JSON) fall back to
+//! a sequence, which is accepted when deserializing, too. Deserialization checks the values like
+//! `TryFrom<&[u8]>`, i.e. the length and the padding bits (if any).
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::{
+    Ciphertext, CompactSecretKey, PublicKey, PublicKeyColumns, SecretKey, SharedSecret,
+};
+
+/// Collects the bytes of a byte string or a sequence into a `T`
+struct BytesVisitor<T>(std::marker::PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for BytesVisitor<T>
+where
+    T: for<'a> TryFrom<&'a [u8], Error = crate::Error>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a byte string of {}", std::any::type_name::<T>())
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<T, E> {
+        T::try_from(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element::<u8>()? {
+            bytes.push(b);
+        }
+        let value = T::try_from(&bytes[..]).map_err(A::Error::custom);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut bytes);
+        value
+    }
+}
+
+macro_rules! impl_serde {
+    ($name:ident) => {
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.as_ref())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_bytes(BytesVisitor(std::marker::PhantomData))
+            }
+        }
+    };
+}
+
+impl_serde!(PublicKey);
+impl_serde!(PublicKeyColumns);
+impl_serde!(SecretKey);
+impl_serde!(CompactSecretKey);
+impl_serde!(Ciphertext);
+impl_serde!(SharedSecret);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ciphertext() -> Ciphertext {
+        let mut c = Ciphertext::zero();
+        for (i, b) in c.0.iter_mut().enumerate() {
+            *b = (i * 89 + 7) as u8;
+        }
+        // the padding bits of mceliece6960119(f) must be zero
+        c.0[crate::params::SYND_BYTES - 1] &= 0x01;
+        c
+    }
+
+    #[test]
+    fn test_serde_byte_string() {
+        let c = ciphertext();
+        let cbor = serde_cbor::to_vec(&c).unwrap();
+        // major type 2 (byte string) with a 1-byte length, then the bytes
+        assert_eq!(cbor[..2], [0x58, Ciphertext::LEN as u8]);
+        assert_eq!(&cbor[2..], c.as_ref());
+
+        let decoded: Ciphertext = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(decoded, c);
+
+        let ss = SharedSecret::from([0x55; crate::CRYPTO_BYTES]);
+        let decoded: SharedSecret =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&ss).unwrap()).unwrap();
+        assert_eq!(decoded, ss);
+    }
+
+    #[test]
+    fn test_serde_sequence() {
+        let c = ciphertext();
+        let json = serde_json::to_string(&c).unwrap();
+        assert!(json.starts_with('['));
+        let decoded: Ciphertext = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, c);
+    }
+
+    #[test]
+    fn test_serde_invalid_length() {
+        let cbor = serde_cbor::to_vec(&serde_cbor::Value::Bytes(vec![0; 5])).unwrap();
+        assert!(serde_cbor::from_slice::<Ciphertext>(&cbor).is_err());
+        assert!(serde_json::from_str::<SharedSecret>("[1, 2, 3]").is_err());
+    }
+
+    #[test]
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    fn test_serde_invalid_padding() {
+        let mut bytes = ciphertext().into_array();
+        bytes[crate::params::SYND_BYTES - 1] |= 0x80;
+        let cbor = serde_cbor::to_vec(&serde_cbor::Value::Bytes(bytes.to_vec())).unwrap();
+        assert!(serde_cbor::from_slice::<Ciphertext>(&cbor).is_err());
+    }
+}
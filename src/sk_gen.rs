@@ -1,70 +1,236 @@
 //! Generation of secret key
 
-use crate::gf::{gf_inv, gf_iszero, gf_mul, gf_mul_inplace, Gf};
-use crate::params::SYS_T;
+use crate::bm::bm;
+use crate::gf::{gf_inv, gf_mul, gf_mul_inplace, gf_mul_y, Gf};
+use crate::params::{GFBITS, SYS_T};
+
+/// Number of powers of `f` computed one after the other in `genpoly_gen`
+const BABY_STEPS: usize = 16;
 
 /// Take element `f` in `GF((2^m)^t)` and return minimal polynomial `out` of `f`
 /// Returns 0 for success and -1 for failure
+///
+/// Instead of solving the linear system of the powers `f^0, …, f^t` like the reference
+/// implementation, this projects the powers `f^0, …, f^(2t-1)` to their constant coefficients and
+/// finds the shortest linear recurrence of that sequence with the Berlekamp–Massey algorithm of the
+/// decryption. As `GF((2^m)^t)` is a field, the minimal polynomial of `f` is irreducible, so the
+/// recurrence is the minimal polynomial itself and has degree `t` iff the reference would succeed.
+/// The powers are assembled from `f^a · f^(16b)` (baby steps and giant steps), which needs about
+/// `2 sqrt(2t)` multiplications in `GF((2^m)^t)` instead of `t`, and no elimination.
 pub(crate) fn genpoly_gen(out: &mut [Gf; SYS_T], f: &[Gf; SYS_T]) -> isize {
-    let mut mat = [[0u16; SYS_T]; SYS_T + 1];
-    mat[0][0] = 1;
+    let mut baby = [[0; SYS_T]; BABY_STEPS];
+    baby[0][0] = 1;
+    baby[1] = *f;
+    for a in 2..BABY_STEPS {
+        let (left, right) = baby.split_at_mut(a);
+        gf_mul_inplace(&mut right[0], &left[a - 1], f);
+    }
+    let mut giant = [0; SYS_T];
+    gf_mul_inplace(&mut giant, &baby[BABY_STEPS - 1], f);
+
+    // sequence[i] is the constant coefficient of f^i
+    let mut sequence = [0; 2 * SYS_T];
+    let mut power = [0; SYS_T];
+    power[0] = 1;
+    let mut shifted = [0; SYS_T];
+    let mut projection = [0; SYS_T];
+    for (b, block) in sequence.chunks_mut(BABY_STEPS).enumerate() {
+        if b > 0 {
+            let prev = power;
+            gf_mul_inplace(&mut power, &prev, &giant);
+        }
+
+        // projection[k] is the constant coefficient of y^k · f^(16b)
+        shifted.copy_from_slice(&power);
+        for p in projection.iter_mut() {
+            *p = shifted[0];
+            gf_mul_y(&mut shifted);
+        }
+
+        for (s, f_a) in block.iter_mut().zip(&baby) {
+            *s = f_a
+                .iter()
+                .zip(&projection)
+                .fold(0, |acc, (&x, &y)| acc ^ gf_mul(x, y));
+        }
+    }
+
+    let mut locator = [0; SYS_T + 1];
+    bm(&mut locator, &mut sequence);
+
+    // a recurrence shorter than t leaves the constant coefficient zero
+    if locator[0] == 0 {
+        return -1;
+    }
+
+    out.copy_from_slice(&locator[..SYS_T]);
+
+    0
+}
 
-    mat[0][1..SYS_T].fill(0);
+/// Reduces `prod` modulo the monic polynomial of degree `SYS_T` with lower coefficients `g`
+fn poly_reduce(prod: &mut [Gf; 2 * SYS_T - 1], g: &[Gf; SYS_T]) -> [Gf; SYS_T] {
+    for i in (SYS_T..2 * SYS_T - 1).rev() {
+        let c = prod[i];
+        for j in 0..SYS_T {
+            prod[i - SYS_T + j] ^= gf_mul(c, g[j]);
+        }
+    }
+
+    let mut out = [0 as Gf; SYS_T];
+    out.copy_from_slice(&prod[0..SYS_T]);
+    out
+}
 
+/// Squares `a` modulo the monic polynomial of degree `SYS_T` with lower coefficients `g`
+fn poly_sqmod(a: &[Gf; SYS_T], g: &[Gf; SYS_T]) -> [Gf; SYS_T] {
+    // in characteristic 2, squaring a polynomial squares its coefficients
+    let mut prod = [0 as Gf; 2 * SYS_T - 1];
     for i in 0..SYS_T {
-        mat[1][i] = f[i];
+        prod[2 * i] = gf_mul(a[i], a[i]);
     }
 
-    for j in 2..=SYS_T {
-        let (left, right) = mat.split_at_mut(j);
-        gf_mul_inplace(&mut right[0], &mut left[j - 1], f);
+    poly_reduce(&mut prod, g)
+}
+
+/// Removes leading zero coefficients of polynomial `a`
+fn poly_trim(a: &mut Vec<Gf>) {
+    while a.last() == Some(&0) {
+        a.pop();
     }
+}
 
-    for j in 0..SYS_T {
-        for k in (j + 1)..SYS_T {
-            let mask = gf_iszero(mat[j][j]);
+/// Determines whether polynomials `a` and `b` have no common factor by the Euclidean algorithm
+fn poly_coprime(mut a: Vec<Gf>, mut b: Vec<Gf>) -> bool {
+    poly_trim(&mut a);
+    poly_trim(&mut b);
 
-            let mut c = j;
-            while c < SYS_T + 1 {
-                mat[c][j] ^= mat[c][k] & mask;
-                c += 1;
+    while !b.is_empty() {
+        // a := a mod b
+        let db = b.len() - 1;
+        let inv = gf_inv(b[db]);
+        while a.len() > db {
+            let da = a.len() - 1;
+            let coef = gf_mul(a[da], inv);
+            for j in 0..=db {
+                a[da - db + j] ^= gf_mul(coef, b[j]);
             }
+            poly_trim(&mut a);
         }
 
-        if mat[j][j] == 0 {
-            return -1;
-        }
+        std::mem::swap(&mut a, &mut b);
+    }
 
-        let inv = gf_inv(mat[j][j]);
+    a.len() == 1
+}
 
-        for c in j..(SYS_T + 1) {
-            mat[c][j] = gf_mul(mat[c][j], inv);
+/// Determines whether the monic polynomial of degree `SYS_T` with lower coefficients `g`
+/// is irreducible over GF(2^m) by Rabin's test:
+/// it is irreducible iff x^(q^t) = x mod g and gcd(x^(q^(t/p)) - x, g) = 1
+/// for all prime divisors p of t, where q = 2^m.
+///
+/// NOTE this is not constant-time and meant for validating imported keys only.
+pub(crate) fn goppa_irreducible(g: &[Gf; SYS_T]) -> bool {
+    let mut reduced_degrees = vec![];
+    let mut t = SYS_T;
+    let mut p = 2;
+    while t > 1 {
+        if t.is_multiple_of(p) {
+            reduced_degrees.push(SYS_T / p);
+            while t.is_multiple_of(p) {
+                t /= p;
+            }
         }
+        p += 1;
+    }
 
-        for k in 0..SYS_T {
-            if k != j {
-                let t = mat[j][k];
+    let mut x = [0 as Gf; SYS_T];
+    x[1] = 1;
 
-                for c in j..(SYS_T + 1) {
-                    mat[c][k] ^= gf_mul(mat[c][j], t);
-                }
+    let mut modulus = g.to_vec();
+    modulus.push(1);
+
+    // h = x^(q^k) mod g
+    let mut h = x;
+    for k in 1..=SYS_T {
+        for _ in 0..GFBITS {
+            h = poly_sqmod(&h, g);
+        }
+
+        if reduced_degrees.contains(&k) {
+            let mut h_minus_x = h.to_vec();
+            h_minus_x[1] ^= 1;
+            if !poly_coprime(modulus.clone(), h_minus_x) {
+                return false;
             }
         }
     }
 
-    out[0..SYS_T].copy_from_slice(&mat[SYS_T][0..SYS_T]);
-
-    0
+    h == x
 }
 
 #[cfg(test)]
-#[cfg(all(feature = "mceliece8192128f", test))]
 mod tests {
     use super::*;
+    use crate::gf::gf_iszero;
+    #[cfg(feature = "mceliece8192128f")]
     use crate::macros::sub;
+    #[cfg(feature = "mceliece8192128f")]
     use std::error;
 
+    /// The Gaussian elimination of the reference implementation, which `genpoly_gen` replaced
+    fn genpoly_gen_reference(out: &mut [Gf; SYS_T], f: &[Gf; SYS_T]) -> isize {
+        let mut mat = [[0u16; SYS_T]; SYS_T + 1];
+        mat[0][0] = 1;
+
+        mat[0][1..SYS_T].fill(0);
+
+        mat[1][..SYS_T].copy_from_slice(&f[..SYS_T]);
+
+        for j in 2..=SYS_T {
+            let (left, right) = mat.split_at_mut(j);
+            gf_mul_inplace(&mut right[0], &left[j - 1], f);
+        }
+
+        for j in 0..SYS_T {
+            for k in (j + 1)..SYS_T {
+                let mask = gf_iszero(mat[j][j]);
+
+                let mut c = j;
+                while c < SYS_T + 1 {
+                    mat[c][j] ^= mat[c][k] & mask;
+                    c += 1;
+                }
+            }
+
+            if mat[j][j] == 0 {
+                return -1;
+            }
+
+            let inv = gf_inv(mat[j][j]);
+
+            for col in mat[j..].iter_mut() {
+                col[j] = gf_mul(col[j], inv);
+            }
+
+            for k in 0..SYS_T {
+                if k != j {
+                    let t = mat[j][k];
+
+                    for col in mat[j..].iter_mut() {
+                        col[k] ^= gf_mul(col[j], t);
+                    }
+                }
+            }
+        }
+
+        out[0..SYS_T].copy_from_slice(&mat[SYS_T][0..SYS_T]);
+
+        0
+    }
+
     #[test]
+    #[cfg(feature = "mceliece8192128f")]
     fn test_genpoly_gen() -> Result<(), Box<dyn error::Error>> {
         assert_eq!(SYS_T, 128);
 
@@ -82,4 +248,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_genpoly_gen_matches_reference() {
+        let mut f = [0; SYS_T];
+        for seed in 0..8u32 {
+            for (i, c) in f.iter_mut().enumerate() {
+                *c = ((i as u32 * 2971 + seed * 7919 + 5) & ((1 << GFBITS) - 1)) as Gf;
+            }
+            let (mut out, mut expected) = ([0; SYS_T], [0; SYS_T]);
+            let ret = genpoly_gen(&mut out, &f);
+            assert_eq!(ret, genpoly_gen_reference(&mut expected, &f));
+            if ret == 0 {
+                assert_eq!(out, expected);
+            }
+        }
+
+        // elements of the subfield GF(2^m) have a minimal polynomial of degree 1
+        let mut scalar = [0; SYS_T];
+        scalar[0] = 3;
+        let mut out = [0; SYS_T];
+        assert_eq!(genpoly_gen(&mut out, &scalar), -1);
+        assert_eq!(genpoly_gen_reference(&mut out, &scalar), -1);
+    }
 }
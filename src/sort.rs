@@ -0,0 +1,164 @@
+//! Sorting in constant time
+//!
+//! The functions of this module sort with a sorting network (following [djbsort](https://sorting.cr.yp.to/)),
+//! i.e. the sequence of compare-and-swap operations depends on the length of the input only.
+//! Each compare-and-swap is branch-free, hence the running time and the memory accesses are independent
+//! of the (secret) values. The key generation uses them to compute the control bits of the Beneš network
+//! and to sort the field ordering, but they are useful for constant-time code in general.
+//!
+//! ```
+//! use classic_mceliece_rust::sort::uint64_sort;
+//!
+//! let mut values = [5u64, u64::MAX, 0, 3];
+//! uint64_sort(&mut values);
+//! assert_eq!(values, [0, 3, 5, u64::MAX]);
+//! ```
+
+/// If `a > b`, swap `a` and `b` in-place. Otherwise keep values.
+/// Implements `(min(a, b), max(a, b))` in constant time.
+pub(crate) const fn int32_minmax(mut a: i32, mut b: i32) -> (i32, i32) {
+    let ab: i32 = b ^ a;
+    let mut c: i32 = (!b & a) | ((!b | a) & (b.wrapping_sub(a)));
+    c ^= ab & (c ^ b);
+    c >>= 31;
+    c &= ab;
+    a ^= c;
+    b ^= c;
+
+    (a, b)
+}
+
+/// If `a > b`, swap `a` and `b` in-place. Otherwise keep values.
+/// Implements `(min(a, b), max(a, b))` in constant time.
+///
+/// This differs from the C implementation, because the C implementation
+/// only works for 63-bit integers.
+///
+/// Instead this implementation is based on
+/// “side-channel effective overflow check of variable c”
+/// from the book “Hacker's Delight” 2–13 Overflow Detection,
+/// Section Unsigned Add/Subtract p. 40
+const fn uint64_minmax(mut a: u64, mut b: u64) -> (u64, u64) {
+    let d: u64 = (!b & a) | ((!b | a) & (b.wrapping_sub(a)));
+    let mut c: u64 = d >> 63;
+    c = 0u64.wrapping_sub(c);
+    c &= a ^ b;
+    a ^= c;
+    b ^= c;
+
+    (a, b)
+}
+
+/// Sorts `x` with the sorting network of djbsort, using the constant-time `minmax`.
+/// Like the C implementation, the index `i` continues across the merging steps `q` of the same `p`.
+fn sorting_network<T: Copy>(x: &mut [T], minmax: fn(T, T) -> (T, T)) {
+    let n = x.len();
+    if n < 2 {
+        return;
+    }
+
+    let mut top = 1;
+    while top < n - top {
+        top += top;
+    }
+
+    let mut p = top;
+    while p > 0 {
+        for i in 0..(n - p) {
+            if (i & p) == 0 {
+                let (lo, hi) = minmax(x[i], x[i + p]);
+                x[i] = lo;
+                x[i + p] = hi;
+            }
+        }
+
+        let mut i = 0;
+        let mut q = top;
+        while q > p {
+            while i < n - q {
+                if (i & p) == 0 {
+                    let mut a = x[i + p];
+                    let mut r = q;
+                    while r > p {
+                        let (lo, hi) = minmax(a, x[i + r]);
+                        x[i + r] = hi;
+                        a = lo;
+                        r >>= 1;
+                    }
+                    x[i + p] = a;
+                }
+                i += 1;
+            }
+            q >>= 1;
+        }
+        p >>= 1;
+    }
+}
+
+/// Sorts the signed integers `x` in ascending order in constant time
+pub fn int32_sort(x: &mut [i32]) {
+    sorting_network(x, int32_minmax);
+}
+
+/// Sorts the unsigned integers `x` in ascending order in constant time
+pub fn uint64_sort(x: &mut [u64]) {
+    sorting_network(x, uint64_minmax);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_int32_minmax() {
+        // first parameter should become min, second parameter should become max
+        assert_eq!(int32_minmax(45, -17), (-17, 45));
+        assert_eq!(int32_minmax(i32::MAX, 2), (2, i32::MAX));
+        assert_eq!(int32_minmax(i32::MAX, i32::MIN), (i32::MIN, i32::MAX));
+
+        for _ in 0..=40 {
+            let (x, y) = int32_minmax(rand::thread_rng().gen(), rand::thread_rng().gen());
+            assert!(x <= y, "erroneous behaviour with inputs: x: {} y: {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_uint64_minmax() {
+        assert_eq!(uint64_minmax(42, 10), (10, 42));
+        assert_eq!(uint64_minmax(u64::MAX, 1), (1, u64::MAX));
+
+        for _ in 0..=40 {
+            let (x, y) = uint64_minmax(rand::thread_rng().gen(), rand::thread_rng().gen());
+            assert!(x <= y, "erroneous behaviour with inputs: x: {} y: {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_int32_sort() {
+        for n in 0..200 {
+            let mut array: Vec<i32> = (0..n).map(|_| rand::thread_rng().gen()).collect();
+            let mut expected = array.clone();
+            expected.sort_unstable();
+
+            int32_sort(&mut array);
+            assert_eq!(array, expected, "length {}", n);
+        }
+    }
+
+    #[test]
+    fn test_uint64_sort() {
+        for n in (0..200).chain([1 << 12, 1 << 13]) {
+            // few distinct values to exercise equal elements
+            let mut array: Vec<u64> = (0..n)
+                .map(|_| rand::thread_rng().gen::<u64>() % 97)
+                .collect();
+            array.extend([u64::MAX, 0]);
+            let mut expected = array.clone();
+            expected.sort_unstable();
+
+            uint64_sort(&mut array);
+            assert_eq!(array, expected, "length {}", n + 2);
+        }
+    }
+}
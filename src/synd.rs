@@ -1,27 +1,127 @@
 //! Syndrome computation
 
-use crate::gf::{gf_add, gf_inv, gf_mul, Gf};
-use crate::params::{SYS_N, SYS_T};
-use crate::root::eval;
+use crate::benes::{unpermute_bits, Conditions};
+use crate::error::Error;
+use crate::fft::{fft, fft_tr};
+use crate::gf::Gf;
+use crate::params::{GFBITS, SYS_N, SYS_T};
+use crate::vec_gf::{vec_inv, vec_pack, vec_sq, vec_unpack};
 
-/// Given Goppa polynomial `f`, support `l`, and received word `r`
-/// compute `out`, the syndrome of length 2t
+/// Given Goppa polynomial `f`, compute `out`, the inverted squares `1/f(a)²` of all field elements `a`
+/// in the order of `fft`. These are the weights of the syndrome computation.
+///
+/// The squares and inverses are computed for 64 elements at once in bitsliced form (see `vec_gf`),
+/// which is as fast as `gf_inv_batch` here.
+pub(crate) fn inv_squares(out: &mut [Gf; 1 << GFBITS], f: &[Gf; SYS_T + 1]) {
+    fft(out, f);
+
+    for chunk in out.chunks_exact_mut(64) {
+        let v = vec_pack(chunk);
+        vec_unpack(&vec_inv(&vec_sq(&v)), chunk);
+    }
+}
+
+/// Given the inverted squares `inv_sq` of the Goppa polynomial (see `inv_squares`),
+/// the control bits `c` of support `l` (or its prepared `Network`), and received word `r`
+/// compute `out`, the syndrome of length 2t, i.e. `out[j] = Σ r[i] · l[i]^j / f(l[i])²`.
+///
+/// The received word is moved from the order of the support to the order of the field elements
+/// with the inverse Beneš network (see `synd_bits`).
 pub(crate) fn synd(
     out: &mut [Gf; SYS_T * 2],
-    f: &[Gf; SYS_T + 1],
-    l: &[Gf; SYS_N],
+    inv_sq: &[Gf; 1 << GFBITS],
+    c: &impl Conditions,
     r: &[u8; SYS_N / 8],
+    values: &mut [Gf; 1 << GFBITS],
+) -> Result<(), Error> {
+    let mut bits = [0u8; (1 << GFBITS) / 8];
+    bits[..SYS_N / 8].copy_from_slice(r);
+    unpermute_bits(&mut bits, c)?;
+
+    synd_bits(out, inv_sq, &bits, values);
+
+    Ok(())
+}
+
+/// Like `synd`, but for the bits of all field elements in the order of `fft` instead of the support.
+///
+/// The sum runs over all field elements with the transposed additive FFT
+/// instead of `2 · t · n` multiplications. The summands are computed in `values`, which is overwritten.
+pub(crate) fn synd_bits(
+    out: &mut [Gf; SYS_T * 2],
+    inv_sq: &[Gf; 1 << GFBITS],
+    bits: &[u8; (1 << GFBITS) / 8],
+    values: &mut [Gf; 1 << GFBITS],
 ) {
-    out[0..2 * SYS_T].fill(0);
+    for ((v, inv), &byte) in values
+        .chunks_exact_mut(8)
+        .zip(inv_sq.chunks_exact(8))
+        .zip(bits.iter())
+    {
+        for (j, (v, &inv)) in v.iter_mut().zip(inv).enumerate() {
+            *v = inv & (((byte >> j) & 1) as Gf).wrapping_neg();
+        }
+    }
+
+    fft_tr(out, values);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benes::support_gen;
+    use crate::gf::{gf_add, gf_inv, gf_mul};
+    use crate::params::COND_BYTES;
+    use crate::root::eval;
+
+    /// The direct syndrome computation for received word `r`
+    fn synd_direct(
+        out: &mut [Gf; SYS_T * 2],
+        f: &[Gf; SYS_T + 1],
+        l: &[Gf; SYS_N],
+        r: &[u8; SYS_N / 8],
+    ) {
+        out[0..2 * SYS_T].fill(0);
 
-    for i in 0..SYS_N {
-        let c: Gf = (r[i / 8] >> (i % 8)) as u16 & 1;
-        let e: Gf = eval(f, l[i]);
-        let mut e_inv: Gf = gf_inv(gf_mul(e, e));
+        for i in 0..SYS_N {
+            let c: Gf = (r[i / 8] >> (i % 8)) as u16 & 1;
+            let e: Gf = eval(f, l[i]);
+            let mut e_inv: Gf = gf_inv(gf_mul(e, e));
+
+            for o in out.iter_mut() {
+                *o = gf_add(*o, gf_mul(e_inv, c));
+                e_inv = gf_mul(e_inv, l[i]);
+            }
+        }
+    }
 
-        for j in 0..SYS_T * 2 {
-            out[j] = gf_add(out[j], gf_mul(e_inv, c));
-            e_inv = gf_mul(e_inv, l[i]);
+    #[test]
+    fn test_synd() -> Result<(), Error> {
+        let mut c = [0u8; COND_BYTES];
+        for (i, ci) in c.iter_mut().enumerate() {
+            *ci = (i * 89 + 7) as u8;
         }
+        let mut f = [1u16; SYS_T + 1];
+        for (i, fi) in f.iter_mut().take(SYS_T).enumerate() {
+            *fi = ((i * 617 + 29) & ((1 << GFBITS) - 1)) as Gf;
+        }
+        let mut r = [0u8; SYS_N / 8];
+        for (i, ri) in r.iter_mut().enumerate() {
+            *ri = (i * 37 + 101) as u8;
+        }
+
+        let mut l = [0u16; SYS_N];
+        support_gen(&mut l, &c)?;
+        let mut expected = [0u16; SYS_T * 2];
+        synd_direct(&mut expected, &f, &l, &r);
+
+        let mut inv_sq = [0u16; 1 << GFBITS];
+        inv_squares(&mut inv_sq, &f);
+        let mut out = [0u16; SYS_T * 2];
+        synd(&mut out, &inv_sq, &c, &r, &mut [0; 1 << GFBITS])?;
+
+        assert_eq!(expected, out);
+
+        Ok(())
     }
 }
@@ -3,32 +3,8 @@
 /// Compute transposition of `input` and store it in `output`
 #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
 pub(crate) fn transpose(output: &mut [u64; 64], input: [u64; 64]) {
-    let masks: [[u64; 2]; 6] = [
-        [0x5555555555555555, 0xAAAAAAAAAAAAAAAA],
-        [0x3333333333333333, 0xCCCCCCCCCCCCCCCC],
-        [0x0F0F0F0F0F0F0F0F, 0xF0F0F0F0F0F0F0F0],
-        [0x00FF00FF00FF00FF, 0xFF00FF00FF00FF00],
-        [0x0000FFFF0000FFFF, 0xFFFF0000FFFF0000],
-        [0x00000000FFFFFFFF, 0xFFFFFFFF00000000],
-    ];
-
-    for h in 0..64 {
-        output[h] = input[h];
-    }
-
-    for d in (0..=5).rev() {
-        let s = 1 << d;
-
-        for i in (0..64).step_by(s * 2) {
-            for j in i..i + s {
-                let x = (output[j] & masks[d][0]) | ((output[j + s] & masks[d][0]) << s);
-                let y = ((output[j] & masks[d][1]) >> s) | (output[j + s] & masks[d][1]);
-
-                output[j + 0] = x;
-                output[j + s] = y;
-            }
-        }
-    }
+    output.copy_from_slice(&input);
+    transpose_64x64_inplace(output);
 }
 
 /// Take a 64×64 matrix over GF(2).
@@ -39,10 +15,9 @@ pub(crate) fn transpose(output: &mut [u64; 64], input: [u64; 64]) {
 /// input argument == output argument. Because we cannot create a
 /// shared and mutable reference simultaneously, we can only generate
 /// one argument.
-#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
 pub(crate) fn transpose_64x64_inplace(arg: &mut [u64; 64]) {
-    let masks = [
-        [0x5555555555555555u64, 0xAAAAAAAAAAAAAAAAu64],
+    let masks: [[u64; 2]; 6] = [
+        [0x5555555555555555, 0xAAAAAAAAAAAAAAAA],
         [0x3333333333333333, 0xCCCCCCCCCCCCCCCC],
         [0x0F0F0F0F0F0F0F0F, 0xF0F0F0F0F0F0F0F0],
         [0x00FF00FF00FF00FF, 0xFF00FF00FF00FF00],
@@ -50,18 +25,19 @@ pub(crate) fn transpose_64x64_inplace(arg: &mut [u64; 64]) {
         [0x00000000FFFFFFFF, 0xFFFFFFFF00000000],
     ];
 
-    for d in (0..6).rev() {
+    // blocks of `2 * s` rows are iterated instead of indexed, so no bounds checks (i.e. panics) remain
+    for (d, mask) in masks.iter().enumerate().rev() {
         let s = 1 << d;
-        let mut i = 0;
-        while i < 64 {
-            for j in i..(i + s) {
-                let x: u64 = (arg[j] & masks[d][0]) | ((arg[j + s] & masks[d][0]) << s);
-                let y: u64 = ((arg[j] & masks[d][1]) >> s) | (arg[j + s] & masks[d][1]);
 
-                arg[j] = x;
-                arg[j + s] = y;
+        for block in arg.chunks_exact_mut(2 * s) {
+            let (lo, hi) = block.split_at_mut(block.len() / 2);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let x = (*a & mask[0]) | ((*b & mask[0]) << s);
+                let y = ((*a & mask[1]) >> s) | (*b & mask[1]);
+
+                *a = x;
+                *b = y;
             }
-            i += s * 2;
         }
     }
 }
@@ -357,19 +333,19 @@ mod tests {
             ],
         };
 
-        for i in 0..2 {
+        for testcase in testcases.iter() {
             #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
             {
                 let mut test_output: [u64; 64] = [0; 64];
-                transpose(&mut test_output, testcases[i].input);
-                assert_eq!(test_output, testcases[i].output);
+                transpose(&mut test_output, testcase.input);
+                assert_eq!(test_output, testcase.output);
             }
 
             #[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
             {
-                let mut data = testcases[i].input;
+                let mut data = testcase.input;
                 transpose_64x64_inplace(&mut data);
-                assert_eq!(data, testcases[i].output);
+                assert_eq!(data, testcase.output);
             }
         }
     }
@@ -0,0 +1,924 @@
+//! Types for the keys, ciphertexts, and shared secrets exchanged through the KEM API
+//!
+//! Each type wraps a byte array of the length determined by the variant.
+//! Because the types differ, a ciphertext cannot be accidentally used
+//! as shared secret (and vice versa). Being transparent wrappers, existing byte arrays can also be
+//! viewed as these types without copying them (see `TransparentWrapper::wrap_ref`).
+//!
+//! All types are `Send` and `Sync`. The operations only read their inputs,
+//! so one `PublicKey` or `SecretKey` can be used concurrently by many threads.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+
+use bytemuck::{TransparentWrapper, Zeroable};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::api::{
+    CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_COMPACTSECRETKEYBYTES, CRYPTO_PUBLICKEYBYTES,
+    CRYPTO_PUBLICKEYCOLUMNSBYTES, CRYPTO_SECRETKEYBYTES,
+};
+#[cfg(not(feature = "encap-only"))]
+use crate::benes::Network;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::benes::{permutation_from_controlbits, support_gen};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::controlbits::controlbitsfrompermutation;
+use crate::crypto_hash::shake256_concat;
+#[cfg(not(feature = "encap-only"))]
+use crate::decrypt::DecryptionKey;
+use crate::error::{array_ref, Error};
+#[cfg(any(
+    not(any(feature = "encap-only", feature = "decap-only")),
+    feature = "mceliece6960119",
+    feature = "mceliece6960119f"
+))]
+use crate::macros::sub;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::operations::crypto_kem_keypair_from_seed;
+#[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+use crate::operations::{check_c_padding, check_pk_padding};
+use crate::params::{
+    COND_BYTES, IRR_BYTES, PK_NCOLS, PK_NROWS, PK_ROW_BYTES, SYND_BYTES, SYS_N, SYS_T,
+};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::params::{GFBITS, GFMASK};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::pk_gen::pk_from_permutation;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::sk_gen::goppa_irreducible;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::util::bitrev;
+use crate::util::{load8, load_gf};
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::workspace::Workspace;
+
+/// A public key of `CRYPTO_PUBLICKEYBYTES` bytes.
+/// It is meant to be shared with any party.
+#[derive(Clone, PartialEq, Eq, Zeroable, TransparentWrapper)]
+#[repr(transparent)]
+pub struct PublicKey(pub(crate) [u8; CRYPTO_PUBLICKEYBYTES]);
+
+/// A public key in column-major layout of `CRYPTO_PUBLICKEYCOLUMNSBYTES` bytes (see `PublicKey::to_columns`).
+///
+/// A `PublicKey` stores the rows of the matrix T of the parity-check matrix H = (I | T), this type
+/// stores its columns of `⌈m · t / 8⌉` bytes each. Column `k` is the syndrome of bit `m · t + k`
+/// of the error vector, hence `encapsulate_columns` accumulates the syndrome in one pass over
+/// the error vector and the key. This is a local representation only, other parties exchange the `PublicKey`.
+#[derive(Clone, PartialEq, Eq, Zeroable, TransparentWrapper)]
+#[repr(transparent)]
+pub struct PublicKeyColumns(pub(crate) [u8; CRYPTO_PUBLICKEYCOLUMNSBYTES]);
+
+/// A secret key of `CRYPTO_SECRETKEYBYTES` bytes.
+/// Access to it must be limited to the generating party. It is compared in constant time.
+#[derive(Clone, Zeroable, TransparentWrapper)]
+#[repr(transparent)]
+pub struct SecretKey(pub(crate) [u8; CRYPTO_SECRETKEYBYTES]);
+
+/// A compact secret key of `CRYPTO_COMPACTSECRETKEYBYTES` bytes.
+/// It consists of the 32-byte seed of the key generation (followed by the 8-byte pivots for the f-variants),
+/// i.e. the prefix of the secret key, and can be expanded to the full secret key.
+/// Access to it must be limited to the generating party. It is compared in constant time.
+#[derive(Clone)]
+pub struct CompactSecretKey(pub(crate) [u8; CRYPTO_COMPACTSECRETKEYBYTES]);
+
+/// A ciphertext of `CRYPTO_CIPHERTEXTBYTES` bytes resulting from the encapsulation
+#[derive(Clone, PartialEq, Eq, TransparentWrapper)]
+#[repr(transparent)]
+pub struct Ciphertext(pub(crate) [u8; CRYPTO_CIPHERTEXTBYTES]);
+
+/// A shared secret of `CRYPTO_BYTES` bytes negotiated between both parties.
+/// It is wiped from memory when dropped and compared in constant time.
+#[derive(Clone, TransparentWrapper)]
+#[repr(transparent)]
+pub struct SharedSecret(pub(crate) [u8; CRYPTO_BYTES]);
+
+// All types are plain byte arrays without interior mutability, hence a `PublicKey` can be shared
+// (e.g. in an `Arc`) and encapsulated against from many threads, and keys can be moved between threads.
+// This is part of the API and must not regress, e.g. by caching values in a `Cell`.
+const fn assert_send_sync<T: Send + Sync>() {}
+const _: () = {
+    assert_send_sync::<PublicKey>();
+    assert_send_sync::<PublicKeyRef<'static>>();
+    assert_send_sync::<PublicKeyColumns>();
+    assert_send_sync::<SecretKey>();
+    assert_send_sync::<CompactSecretKey>();
+    assert_send_sync::<Ciphertext>();
+    assert_send_sync::<SharedSecret>();
+    #[cfg(not(feature = "encap-only"))]
+    assert_send_sync::<ExpandedSecretKey>();
+};
+
+/// Rejects public keys whose row padding bits are set
+pub(crate) fn check_public_key_bytes(_bytes: &[u8; CRYPTO_PUBLICKEYBYTES]) -> Result<(), Error> {
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    {
+        if check_pk_padding(_bytes) != 0 {
+            return Err(Error::InvalidPadding);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects public keys in column-major layout whose column padding bits are set
+pub(crate) fn check_public_key_columns_bytes(
+    bytes: &[u8; CRYPTO_PUBLICKEYCOLUMNSBYTES],
+) -> Result<(), Error> {
+    // only mceliece6960119 has columns with padding
+    if !PK_NROWS.is_multiple_of(8) {
+        let padding = bytes
+            .chunks_exact(SYND_BYTES)
+            .fold(0u8, |b, column| b | column[SYND_BYTES - 1]);
+        if padding >> (PK_NROWS % 8) != 0 {
+            return Err(Error::InvalidPadding);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects ciphertexts whose syndrome padding bits are set
+pub(crate) fn check_ciphertext_bytes(_bytes: &[u8; CRYPTO_CIPHERTEXTBYTES]) -> Result<(), Error> {
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    {
+        if check_c_padding(sub!(_bytes, 0, SYND_BYTES)) != 0 {
+            return Err(Error::InvalidPadding);
+        }
+    }
+
+    Ok(())
+}
+
+macro_rules! impl_byte_array_type {
+    ($name:ident, $len:expr) => {
+        impl_byte_array_type!(@common $name, $len, |_| Ok(()));
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+    };
+    ($name:ident, $len:expr, $check:expr) => {
+        impl_byte_array_type!(@common $name, $len, $check);
+
+        impl TryFrom<[u8; $len]> for $name {
+            type Error = Error;
+
+            /// Takes `bytes` if its padding bits (if any) are zero
+            fn try_from(bytes: [u8; $len]) -> Result<Self, Self::Error> {
+                let check: fn(&[u8; $len]) -> Result<(), Error> = $check;
+                check(&bytes)?;
+                Ok(Self(bytes))
+            }
+        }
+    };
+    (@common $name:ident, $len:expr, $check:expr) => {
+        impl $name {
+            /// The number of bytes of this type
+            pub const LEN: usize = $len;
+
+            /// Returns an all-zero value which can be used as output buffer
+            /// (not every type is an output, e.g. `PublicKeyColumns` is converted from a `PublicKey`)
+            #[allow(dead_code)]
+            pub(crate) fn zero() -> Self {
+                Self([0u8; $len])
+            }
+
+            /// Returns a reference to the underlying byte array
+            pub fn as_array(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            /// Returns the underlying byte array
+            pub fn into_array(self) -> [u8; $len] {
+                self.0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = Error;
+
+            /// Copies `bytes` if its length matches `Self::LEN` and its padding bits (if any) are zero
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                let bytes = array_ref::<$len>(bytes)?;
+                let check: fn(&[u8; $len]) -> Result<(), Error> = $check;
+                check(bytes)?;
+                Ok(Self(*bytes))
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    };
+}
+
+impl_byte_array_type!(PublicKey, CRYPTO_PUBLICKEYBYTES, check_public_key_bytes);
+impl_byte_array_type!(
+    PublicKeyColumns,
+    CRYPTO_PUBLICKEYCOLUMNSBYTES,
+    check_public_key_columns_bytes
+);
+impl_byte_array_type!(SecretKey, CRYPTO_SECRETKEYBYTES);
+impl_byte_array_type!(CompactSecretKey, CRYPTO_COMPACTSECRETKEYBYTES);
+impl_byte_array_type!(Ciphertext, CRYPTO_CIPHERTEXTBYTES, check_ciphertext_bytes);
+
+macro_rules! impl_ct_eq {
+    ($name:ident) => {
+        impl ConstantTimeEq for $name {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0.ct_eq(&other.0)
+            }
+        }
+
+        /// Comparison does not exit early, i.e. runs in constant time
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.ct_eq(other).into()
+            }
+        }
+
+        impl Eq for $name {}
+    };
+}
+
+impl_ct_eq!(SharedSecret);
+impl_ct_eq!(SecretKey);
+impl_ct_eq!(CompactSecretKey);
+
+impl Zeroize for SharedSecret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SharedSecret {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SharedSecret {}
+
+macro_rules! impl_zeroize_on_drop {
+    ($name:ident) => {
+        #[cfg(feature = "zeroize")]
+        impl Zeroize for $name {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl Drop for $name {
+            fn drop(&mut self) {
+                self.zeroize();
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl ZeroizeOnDrop for $name {}
+    };
+}
+
+impl_zeroize_on_drop!(SecretKey);
+impl_zeroize_on_drop!(CompactSecretKey);
+
+/// Offsets of the segments of the secret key,
+/// i.e. (32 bytes δ, 8 bytes pivots c, IRR_BYTES bytes g, COND_BYTES bytes control bits, SYS_N/8 bytes s)
+const SK_PIVOTS: usize = 32;
+const SK_IRR: usize = SK_PIVOTS + 8;
+const SK_COND: usize = SK_IRR + IRR_BYTES;
+const SK_S: usize = SK_COND + COND_BYTES;
+
+impl PublicKey {
+    /// Checks the structure of a public key received from another party.
+    ///
+    /// The length is guaranteed by the type (use `PublicKeyRef::try_from` to check a byte slice
+    /// without copying it). This checks that the padding bits of the rows are zero
+    /// (only mceliece6960119 has rows with padding) and that no row is entirely zero,
+    /// which happens for generated keys with negligible probability only.
+    /// It does not prove that the key was generated honestly.
+    pub fn validate(&self) -> Result<(), Error> {
+        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+        {
+            if check_pk_padding(&self.0) != 0 {
+                return Err(Error::InvalidPublicKey("padding bits are not zero"));
+            }
+        }
+
+        if self
+            .0
+            .chunks(PK_ROW_BYTES)
+            .any(|row| row.iter().all(|&b| b == 0))
+        {
+            return Err(Error::InvalidPublicKey("row is zero"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the public key in column-major layout (see `PublicKeyColumns`).
+    /// The public key is not secret, hence its bits are transposed one by one.
+    pub fn to_columns(&self) -> Box<PublicKeyColumns> {
+        let mut columns: Box<PublicKeyColumns> = bytemuck::zeroed_box();
+        for (i, row) in self.0.chunks_exact(PK_ROW_BYTES).enumerate() {
+            for k in 0..PK_NCOLS {
+                let bit = (row[k / 8] >> (k % 8)) & 1;
+                columns.0[k * SYND_BYTES + i / 8] |= bit << (i % 8);
+            }
+        }
+        columns
+    }
+}
+
+impl PublicKeyColumns {
+    /// Returns the public key in the row-major layout of the specification (see `PublicKey::to_columns`)
+    pub fn to_public_key(&self) -> Box<PublicKey> {
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        for (k, column) in self.0.chunks_exact(SYND_BYTES).enumerate() {
+            for i in 0..PK_NROWS {
+                let bit = (column[i / 8] >> (i % 8)) & 1;
+                pk.0[i * PK_ROW_BYTES + k / 8] |= bit << (k % 8);
+            }
+        }
+        pk
+    }
+}
+
+impl SecretKey {
+    /// Returns the segment of `LEN` bytes at `OFFSET` of the secret key
+    fn segment<const OFFSET: usize, const LEN: usize>(&self) -> &[u8; LEN] {
+        <&[u8; LEN]>::try_from(&self.0[OFFSET..OFFSET + LEN])
+            .expect("segment lies within the secret key")
+    }
+
+    /// Returns the 32-byte seed δ the key pair was generated from
+    pub fn delta(&self) -> &[u8; 32] {
+        self.segment::<0, 32>()
+    }
+
+    /// Returns the value c encoding the positions of the 32 pivots of the f-variants
+    /// (always `0xFFFFFFFF` for the other variants)
+    pub fn pivots(&self) -> u64 {
+        load8(self.segment::<SK_PIVOTS, 8>())
+    }
+
+    /// Returns the coefficients g_0, …, g_{t-1} of the monic Goppa polynomial g
+    pub fn goppa_polynomial(&self) -> [u16; SYS_T] {
+        let mut g = [0u16; SYS_T];
+        for (i, chunk) in self.segment::<SK_IRR, IRR_BYTES>().chunks(2).enumerate() {
+            g[i] = load_gf(<&[u8; 2]>::try_from(chunk).expect("chunk of two bytes"));
+        }
+        g
+    }
+
+    /// Returns the control bits of the Beneš network determining the support (α_1, …, α_n)
+    pub fn control_bits(&self) -> &[u8; COND_BYTES] {
+        self.segment::<SK_COND, COND_BYTES>()
+    }
+
+    /// Returns the string s of n bits used for implicit rejection
+    pub fn s(&self) -> &[u8; SYS_N / 8] {
+        self.segment::<SK_S, { SYS_N / 8 }>()
+    }
+
+    /// Checks the structure of a secret key, e.g. one imported from another implementation.
+    ///
+    /// This checks that the coefficients of the (monic) Goppa polynomial g are elements of GF(2^m),
+    /// that g is irreducible, and that the control bits are the canonical encoding of a permutation.
+    /// The checks are not constant-time and take about as long as a key generation.
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    pub fn validate(&self) -> Result<(), Error> {
+        let irr = self.segment::<SK_IRR, IRR_BYTES>();
+        if irr
+            .chunks(2)
+            .any(|chunk| (u16::from(chunk[1]) << 8 | u16::from(chunk[0])) as usize > GFMASK)
+        {
+            return Err(Error::InvalidSecretKey(
+                "coefficient of the Goppa polynomial exceeds the field",
+            ));
+        }
+
+        if !goppa_irreducible(&self.goppa_polynomial()) {
+            return Err(Error::InvalidSecretKey(
+                "Goppa polynomial is not irreducible",
+            ));
+        }
+
+        let mut pi = [0i16; 1 << GFBITS];
+        permutation_from_controlbits(&mut pi, self.control_bits())?;
+
+        let mut cond = [0u8; COND_BYTES];
+        controlbitsfrompermutation(&mut cond, &pi, GFBITS, 1 << GFBITS);
+        if &cond != self.control_bits() {
+            return Err(Error::InvalidSecretKey(
+                "control bits do not encode a permutation canonically",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Derives the public key from the Goppa polynomial g and the support encoded by the control bits.
+    ///
+    /// This recomputes the public matrix and is thus as expensive as the key generation
+    /// of a single attempt. Fails if the matrix cannot be brought into systematic form,
+    /// which does not happen for generated secret keys.
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    pub fn public_key(&self) -> Result<Box<PublicKey>, Error> {
+        let mut l = [0u16; SYS_N];
+        support_gen(&mut l, self.control_bits())?;
+
+        // only the first SYS_N elements of the permutation determine the public key
+        let mut pi = [0i16; 1 << GFBITS];
+        for i in 0..SYS_N {
+            pi[i] = bitrev(l[i]) as i16;
+        }
+
+        #[cfg(semi_systematic)]
+        let mut pivots = 0u64;
+
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let ret = pk_from_permutation(
+            &mut pk.0,
+            Workspace::new().matrix(),
+            self.segment::<SK_IRR, IRR_BYTES>(),
+            &mut pi,
+            #[cfg(semi_systematic)]
+            &mut pivots,
+        )?;
+        if ret != 0 {
+            return Err(Error::InvalidSecretKey(
+                "does not yield a public key in systematic form",
+            ));
+        }
+
+        Ok(pk)
+    }
+
+    /// Prepares this secret key for many decapsulations (see `ExpandedSecretKey`)
+    #[cfg(not(feature = "encap-only"))]
+    pub fn expand(&self) -> Result<ExpandedSecretKey, Error> {
+        let mut net: Box<Network> = bytemuck::zeroed_box();
+        net.load(self.control_bits())?;
+
+        Ok(ExpandedSecretKey {
+            sk: self.clone(),
+            dk: DecryptionKey::new(self.segment::<SK_IRR, IRR_BYTES>())?,
+            net,
+        })
+    }
+
+    /// Returns the compact representation of this secret key
+    pub fn compact(&self) -> CompactSecretKey {
+        let mut compact = CompactSecretKey::zero();
+        compact
+            .0
+            .copy_from_slice(&self.0[..CRYPTO_COMPACTSECRETKEYBYTES]);
+        compact
+    }
+}
+
+impl CompactSecretKey {
+    /// Re-derives the full secret key from the seed.
+    ///
+    /// This repeats the key generation (including the public key computation) and is thus expensive.
+    /// Fails if the seed does not immediately result in a valid key pair or the pivots do not match,
+    /// i.e. if this compact secret key was not obtained from a generated secret key.
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    pub fn expand(&self) -> Result<SecretKey, Error> {
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        let mut sk = SecretKey::zero();
+        crypto_kem_keypair_from_seed(&mut pk, &mut sk, sub!(self.0, 0, 32))?;
+
+        if sk.0[..CRYPTO_COMPACTSECRETKEYBYTES] != self.0 {
+            return Err(Error::InvalidSecretKey(
+                "compact secret key does not match the expanded secret key",
+            ));
+        }
+
+        Ok(sk)
+    }
+}
+
+/// A secret key together with the tables its decapsulations compute from it.
+///
+/// Each decapsulation with a `SecretKey` loads the Goppa polynomial g, evaluates it at all field elements
+/// and inverts the squares of the evaluations. An expanded secret key stores these inverted squares,
+/// hence decapsulations with it (see `decapsulate_expanded`) only decode the ciphertext.
+/// It also stores the condition words of all layers of the Beneš network (see `benes::Network`),
+/// so that the decapsulations neither load nor transpose the control bits.
+/// The tables take about `2^(m+1) + 512 · (2m - 1)` bytes in addition to the secret key.
+///
+/// The support itself is not stored: the decoder evaluates at all field elements with the additive FFT,
+/// and the Beneš network only moves two bit vectors per decapsulation
+/// (the received word and the roots of the error locator) between the order of the support and the order
+/// of the field elements. Skipping the network would require evaluating at the stored support element by
+/// element, i.e. `2 · t` multiplications per element for the syndrome, which is about three times slower
+/// for mceliece348864 than the FFTs and both passes through the network; looking up the evaluations
+/// by the (secret) support instead would not run in constant time.
+#[cfg(not(feature = "encap-only"))]
+pub struct ExpandedSecretKey {
+    pub(crate) sk: SecretKey,
+    pub(crate) dk: DecryptionKey,
+    pub(crate) net: Box<Network>,
+}
+
+#[cfg(not(feature = "encap-only"))]
+impl ExpandedSecretKey {
+    /// Returns the secret key this key was expanded from
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.sk
+    }
+}
+
+impl_byte_array_type!(SharedSecret, CRYPTO_BYTES);
+
+impl SharedSecret {
+    /// Derives `out.len()` bytes from this shared secret and the caller-chosen `label`,
+    /// e.g. to obtain longer keys or several independent subkeys.
+    ///
+    /// The output is the SHAKE256 output of the 32 bytes of the shared secret followed by `label`.
+    /// Distinct labels yield independent outputs, and a shorter output is a prefix of a longer one
+    /// with the same label. Hence, use distinct labels for subkeys instead of splitting one output.
+    pub fn expand(&self, label: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        shake256_concat(out, &[&self.0, label])
+    }
+}
+
+/// A borrowed public key referring to `CRYPTO_PUBLICKEYBYTES` bytes stored elsewhere
+/// (e.g. in a memory-mapped file or a flash region).
+///
+/// Since encapsulation only reads the public key, it dereferences to `PublicKey`
+/// and can be passed wherever `&PublicKey` is expected without copying the key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PublicKeyRef<'a>(&'a PublicKey);
+
+impl<'a> PublicKeyRef<'a> {
+    /// Returns the borrowed public key
+    pub fn as_public_key(&self) -> &'a PublicKey {
+        self.0
+    }
+}
+
+impl<'a> TryFrom<&'a [u8; CRYPTO_PUBLICKEYBYTES]> for PublicKeyRef<'a> {
+    type Error = Error;
+
+    /// Borrows `bytes` if its padding bits (if any) are zero
+    fn try_from(bytes: &'a [u8; CRYPTO_PUBLICKEYBYTES]) -> Result<Self, Self::Error> {
+        check_public_key_bytes(bytes)?;
+        Ok(Self(PublicKey::wrap_ref(bytes)))
+    }
+}
+
+impl<'a> From<&'a PublicKey> for PublicKeyRef<'a> {
+    fn from(pk: &'a PublicKey) -> Self {
+        Self(pk)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PublicKeyRef<'a> {
+    type Error = Error;
+
+    /// Borrows `bytes` if its length matches `CRYPTO_PUBLICKEYBYTES` and its padding bits (if any) are zero
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::try_from(array_ref::<CRYPTO_PUBLICKEYBYTES>(bytes)?)
+    }
+}
+
+impl Deref for PublicKeyRef<'_> {
+    type Target = PublicKey;
+
+    fn deref(&self) -> &PublicKey {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for PublicKeyRef<'_> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0 .0
+    }
+}
+
+impl fmt::Debug for PublicKeyRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PublicKeyRef").field(self.0).finish()
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", hex::encode_upper(self.0))
+    }
+}
+
+impl fmt::Debug for PublicKeyColumns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKeyColumns({})", hex::encode_upper(self.0))
+    }
+}
+
+impl fmt::Debug for Ciphertext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ciphertext({})", hex::encode_upper(self.0))
+    }
+}
+
+/// Secret data is not revealed by the `Debug` representation
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey(…)")
+    }
+}
+
+/// Secret data is not revealed by the `Debug` representation
+impl fmt::Debug for CompactSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CompactSecretKey(…)")
+    }
+}
+
+/// Secret data is not revealed by the `Debug` representation
+#[cfg(not(feature = "encap-only"))]
+impl fmt::Debug for ExpandedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExpandedSecretKey(…)")
+    }
+}
+
+/// Secret data is not revealed by the `Debug` representation
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedSecret(…)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    use crate::operations::encapsulate;
+
+    #[test]
+    fn test_try_from_slice() {
+        let bytes = [0x02u8; CRYPTO_CIPHERTEXTBYTES + 1];
+
+        assert!(matches!(
+            Ciphertext::try_from(&bytes[..CRYPTO_CIPHERTEXTBYTES - 1]),
+            Err(Error::WrongLength { expected, got })
+                if expected == CRYPTO_CIPHERTEXTBYTES && got == CRYPTO_CIPHERTEXTBYTES - 1
+        ));
+        assert!(matches!(
+            SecretKey::try_from(&bytes[..]),
+            Err(Error::WrongLength { expected, got })
+                if expected == CRYPTO_SECRETKEYBYTES && got == CRYPTO_CIPHERTEXTBYTES + 1
+        ));
+
+        let ct = Ciphertext::try_from(&bytes[..CRYPTO_CIPHERTEXTBYTES]).unwrap();
+        assert_eq!(ct.as_array(), &[0x02u8; CRYPTO_CIPHERTEXTBYTES]);
+        assert_eq!(ct.as_ref().len(), Ciphertext::LEN);
+    }
+
+    #[test]
+    fn test_public_key_columns() {
+        let mut pk: Box<PublicKey> = bytemuck::zeroed_box();
+        for (i, b) in pk.0.iter_mut().enumerate() {
+            *b = (i * 131 + 17) as u8;
+        }
+        if !PK_NCOLS.is_multiple_of(8) {
+            for row in pk.0.chunks_exact_mut(PK_ROW_BYTES) {
+                row[PK_ROW_BYTES - 1] &= (1 << (PK_NCOLS % 8)) - 1;
+            }
+        }
+
+        let columns = pk.to_columns();
+        // column 1 holds bit 1 of the rows
+        assert_eq!(
+            columns.0[SYND_BYTES] & 1,
+            (pk.0[0] >> 1) & 1,
+            "bit 1 of row 0"
+        );
+        assert!(PublicKeyColumns::try_from(columns.as_array().as_slice()).is_ok());
+        assert_eq!(columns.to_public_key(), pk);
+    }
+
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    #[test]
+    fn test_try_from_slice_padding() {
+        let mut ct = [0u8; CRYPTO_CIPHERTEXTBYTES];
+        assert!(Ciphertext::try_from(&ct[..]).is_ok());
+        ct[SYND_BYTES - 1] = 0x80;
+        assert!(matches!(
+            Ciphertext::try_from(&ct[..]),
+            Err(Error::InvalidPadding)
+        ));
+
+        let mut pk = vec![0u8; CRYPTO_PUBLICKEYBYTES];
+        assert!(PublicKeyRef::try_from(pk.as_slice()).is_ok());
+        pk[PK_ROW_BYTES - 1] = 0x80;
+        assert!(matches!(
+            PublicKeyRef::try_from(pk.as_slice()),
+            Err(Error::InvalidPadding)
+        ));
+        assert!(matches!(
+            PublicKey::try_from(pk.as_slice()),
+            Err(Error::InvalidPadding)
+        ));
+
+        // the conversions of arrays check the padding as well
+        assert!(matches!(
+            Ciphertext::try_from(ct),
+            Err(Error::InvalidPadding)
+        ));
+        let pk: &[u8; CRYPTO_PUBLICKEYBYTES] = pk.as_slice().try_into().unwrap();
+        assert!(matches!(
+            PublicKeyRef::try_from(pk),
+            Err(Error::InvalidPadding)
+        ));
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let ss = SharedSecret::from([0xABu8; CRYPTO_BYTES]);
+        assert!(!format!("{:?}", ss).contains("AB"));
+
+        let ct = Ciphertext::try_from([0x05u8; CRYPTO_CIPHERTEXTBYTES]).unwrap();
+        assert!(format!("{:?}", ct).starts_with("Ciphertext(0505"));
+    }
+
+    #[test]
+    fn test_shared_secret_ct_eq_and_zeroize() {
+        let mut ss = SharedSecret::from([0xABu8; CRYPTO_BYTES]);
+        let mut other = [0xABu8; CRYPTO_BYTES];
+
+        assert!(bool::from(ss.ct_eq(&SharedSecret::from(other))));
+        other[CRYPTO_BYTES - 1] ^= 1;
+        assert!(bool::from(!ss.ct_eq(&SharedSecret::from(other))));
+        assert!(ss != SharedSecret::from(other));
+
+        ss.zeroize();
+        assert_eq!(ss.as_array(), &[0u8; CRYPTO_BYTES]);
+    }
+
+    #[test]
+    fn test_secret_key_ct_eq() {
+        let sk = SecretKey::from([0xABu8; CRYPTO_SECRETKEYBYTES]);
+        let mut other = sk.clone();
+        assert!(bool::from(sk.ct_eq(&other)));
+        other.0[CRYPTO_SECRETKEYBYTES - 1] ^= 1;
+        assert!(sk != other);
+
+        let compact = CompactSecretKey::from([0xABu8; CRYPTO_COMPACTSECRETKEYBYTES]);
+        let mut other = compact.clone();
+        assert!(compact == other);
+        other.0[0] ^= 1;
+        assert!(!bool::from(compact.ct_eq(&other)));
+    }
+
+    #[test]
+    fn test_shared_secret_expand() -> Result<(), Error> {
+        let ss = SharedSecret::from([0xABu8; CRYPTO_BYTES]);
+
+        // SHAKE256(ss || "enc") computed independently
+        let mut okm = [0u8; 64];
+        ss.expand(b"enc", &mut okm)?;
+        assert_eq!(
+            hex::encode_upper(okm),
+            "3785B493476EFA4076A5C99C0AF5F3A82DD64ECFABBE9C8D486B310DED8A2E91\
+             87BC004B7DE194D1D9191B81FD20C2B7436886DDD5A3EB503084DB1B50C43C0D"
+        );
+
+        let mut short = [0u8; 16];
+        ss.expand(b"enc", &mut short)?;
+        assert_eq!(short, okm[..16]);
+
+        let mut mac = [0u8; 64];
+        ss.expand(b"mac", &mut mac)?;
+        assert_ne!(mac, okm);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn test_secret_key_zeroize() {
+        let mut sk: Box<SecretKey> = bytemuck::zeroed_box();
+        sk.0.fill(0xAB);
+
+        sk.zeroize();
+        assert!(sk.0.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_secret_key_accessors() -> Result<(), Error> {
+        let (_, sk) = crate::test_keypair();
+
+        assert_eq!(&sk.0[..32], sk.delta());
+        assert_eq!(load8(sub!(sk.0, 32, 8)), sk.pivots());
+        assert_eq!(&sk.0[SK_COND..SK_S], sk.control_bits());
+        assert_eq!(&sk.0[SK_S..], sk.s());
+
+        let g = sk.goppa_polynomial();
+        assert_eq!(load_gf(sub!(sk.0, 40, 2)), g[0]);
+        assert_eq!(load_gf(sub!(sk.0, SK_COND - 2, 2)), g[SYS_T - 1]);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_secret_key_public_key() -> Result<(), Error> {
+        let (pk, sk) = crate::test_keypair();
+        assert!(sk.public_key()? == *pk);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_public_key_validate() -> Result<(), Error> {
+        let (pk, _) = crate::test_keypair();
+        pk.validate()?;
+
+        let mut tampered = pk.clone();
+        tampered.0[..PK_ROW_BYTES].fill(0);
+        assert!(tampered.validate().is_err());
+
+        #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+        {
+            let mut pk = pk.clone();
+            pk.0[PK_ROW_BYTES - 1] |= 0x80;
+            assert!(pk.validate().is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_secret_key_validate() -> Result<(), Error> {
+        let (_, sk) = crate::test_keypair();
+        sk.validate()?;
+
+        // coefficient beyond GF(2^m)
+        let mut tampered = sk.clone();
+        tampered.0[SK_IRR + 1] |= 0x80;
+        assert!(tampered.validate().is_err());
+
+        // g(0) = 0, i.e. x divides g
+        let mut tampered = sk.clone();
+        tampered.0[SK_IRR..SK_IRR + 2].fill(0);
+        assert!(tampered.validate().is_err());
+
+        // control bits of a non-canonical encoding
+        let mut tampered = sk.clone();
+        tampered.0[SK_COND] ^= 1;
+        assert!(tampered.validate().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_public_key_ref() -> Result<(), Error> {
+        let (pk, _) = crate::test_keypair();
+        let stored = pk.as_array().to_vec();
+
+        let pk_ref = PublicKeyRef::try_from(stored.as_slice())?;
+        assert!(pk_ref.as_public_key() == &**pk);
+        assert!(PublicKeyRef::try_from(&stored[1..]).is_err());
+
+        // the same encapsulation as with the owned public key
+        let (c, ss_bob) = encapsulate(&pk_ref, &mut crate::test_rng(0x2B))?;
+        assert!((c, ss_bob) == encapsulate(pk, &mut crate::test_rng(0x2B))?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_compact_secret_key_expand() -> Result<(), Error> {
+        let (_, sk) = crate::test_keypair();
+        let compact = sk.compact();
+
+        assert!(compact.expand()? == **sk);
+
+        // altered pivots are detected
+        #[cfg(semi_systematic)]
+        {
+            let mut tampered = compact.into_array();
+            tampered[CRYPTO_COMPACTSECRETKEYBYTES - 1] ^= 1;
+            assert!(CompactSecretKey::from(tampered).expand().is_err());
+        }
+
+        Ok(())
+    }
+}
@@ -1,62 +1,70 @@
 //! This file is for loading/storing data in a little-endian fashion and a `bitrev` function
+//!
+//! The bit-matrix routines shared with other code-based schemes are public (see `transpose64`),
+//! like the loads and stores of 64-bit words (`load8`, `store8`, `load8_partial`) and the conversion
+//! between field elements and their bit planes (`pack_bits`, `unpack_bits`). The loads and stores
+//! are `from_le_bytes` and `to_le_bytes`, which compile to a single unaligned access (and a byte swap
+//! on big-endian targets).
 
 use crate::{gf::Gf, params::GFMASK};
 
 /// Store Gf element `a` in array `dest`
 pub(crate) fn store_gf(dest: &mut [u8; 2], a: Gf) {
-    dest[0] = (a & 0xFF) as u8;
-    dest[1] = a.overflowing_shr(8).0 as u8;
+    *dest = a.to_le_bytes();
 }
 
 /// Interpret 2 bytes from `src` as integer and return it as Gf element
 pub(crate) fn load_gf(src: &[u8; 2]) -> Gf {
-    let mut a: u16;
-
-    a = src[1] as u16;
-    a <<= 8;
-    a |= src[0] as u16;
-
-    a & (GFMASK as u16)
+    u16::from_le_bytes(*src) & (GFMASK as u16)
 }
 
 /// Interpret 4 bytes from `src` as integer and return it as u32
 pub(crate) fn load4(input: &[u8; 4]) -> u32 {
-    let mut ret: u32 = input[3] as u32;
-
-    for i in (0..=2).rev() {
-        ret <<= 8;
-        ret |= input[i] as u32;
-    }
-    ret
+    u32::from_le_bytes(*input)
 }
 
-/// Take `input` and store it in 8 bytes, `out` points to.
-pub(crate) fn store8(out: &mut [u8; 8], input: u64) {
-    out[0] = input.wrapping_shr(0x00) as u8;
-    out[1] = input.wrapping_shr(0x08) as u8;
-    out[2] = input.wrapping_shr(0x10) as u8;
-    out[3] = input.wrapping_shr(0x18) as u8;
-    out[4] = input.wrapping_shr(0x20) as u8;
-    out[5] = input.wrapping_shr(0x28) as u8;
-    out[6] = input.wrapping_shr(0x30) as u8;
-    out[7] = input.wrapping_shr(0x38) as u8;
+/// Take `input` and store it in 8 bytes, `out` points to (little-endian).
+pub fn store8(out: &mut [u8; 8], input: u64) {
+    *out = input.to_le_bytes();
 }
 
-/// Interpret 8 bytes from `input` as integer and return it as u64.
-pub(crate) fn load8(input: &[u8; 8]) -> u64 {
-    let mut ret: u64 = input[7] as u64;
+/// Interpret 8 bytes from `input` as little-endian integer and return it as u64.
+pub fn load8(input: &[u8; 8]) -> u64 {
+    u64::from_le_bytes(*input)
+}
 
-    for i in (0..=6).rev() {
-        ret <<= 8;
-        ret |= input[i] as u64;
+/// Interpret the first (at most 8) bytes of `input` as little-endian integer, missing bytes being zero,
+/// e.g. the last bytes of a row which is not a multiple of 8 bytes long.
+pub fn load8_partial(input: &[u8]) -> u64 {
+    match input.first_chunk::<8>() {
+        Some(word) => u64::from_le_bytes(*word),
+        None => input
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &byte| (acc << 8) | byte as u64),
     }
+}
 
-    ret
+/// Returns the bit plane `j` of (at most 64) `values`, i.e. bit `i` of the result is bit `j` of `values[i]`
+pub fn pack_bits(values: &[u16], j: usize) -> u64 {
+    values
+        .iter()
+        .take(64)
+        .enumerate()
+        .fold(0u64, |acc, (i, &x)| acc | ((x as u64 >> j) & 1) << i)
+}
+
+/// Sets bit `j` of (at most 64) `values` to the bit plane `word`, i.e. bit `j` of `values[i]` to bit `i` of `word`
+/// (the inverse of `pack_bits`)
+pub fn unpack_bits(values: &mut [u16], j: usize, word: u64) {
+    for (i, x) in values.iter_mut().take(64).enumerate() {
+        *x = (*x & !(1 << j)) | (((word >> i) & 1) as u16) << j;
+    }
 }
 
 /// Reverse the bits of Gf element `a`. The LSB becomes the MSB.
 /// The 2nd LSB becomes the 2nd MSB. etc …
-pub(crate) fn bitrev(mut a: Gf) -> Gf {
+pub(crate) const fn bitrev(mut a: Gf) -> Gf {
     a = ((a & 0x00FF) << 8) | ((a & 0xFF00) >> 8);
     a = ((a & 0x0F0F) << 4) | ((a & 0xF0F0) >> 4);
     a = ((a & 0x3333) << 2) | ((a & 0xCCCC) >> 2);
@@ -72,6 +80,15 @@ pub(crate) fn bitrev(mut a: Gf) -> Gf {
     }
 }
 
+/// Transposes the 64×64 matrix over GF(2) with rows `matrix` in-place,
+/// i.e. bit `j` of word `i` is swapped with bit `i` of word `j`.
+///
+/// This is the transpose of the bitsliced routines (e.g. the public key computation of the f-variants),
+/// which swaps blocks of bits with masks in 6 layers and takes the same time for any matrix.
+pub fn transpose64(matrix: &mut [u64; 64]) {
+    crate::transpose::transpose_64x64_inplace(matrix);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,6 +98,56 @@ mod tests {
         assert_eq!(load_gf(&[0xAB, 0x42]), 0x02AB);
     }
 
+    #[test]
+    fn test_load8_store8() {
+        let bytes = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+        assert_eq!(load8(&bytes), 0xEFCD_AB89_6745_2301);
+        assert_eq!(load4(&[0x01, 0x23, 0x45, 0x67]), 0x6745_2301);
+
+        let mut out = [0u8; 8];
+        store8(&mut out, 0xEFCD_AB89_6745_2301);
+        assert_eq!(out, bytes);
+
+        assert_eq!(load8_partial(&bytes[..3]), 0x45_2301);
+        assert_eq!(load8_partial(&[]), 0);
+        assert_eq!(load8_partial(&[0xFF; 9]), u64::MAX);
+    }
+
+    #[test]
+    fn test_pack_bits() {
+        let mut values: Vec<u16> = (0..64).map(|i| (i * 37 + 11) as u16).collect();
+        let plane = pack_bits(&values, 3);
+        for (i, &x) in values.iter().enumerate() {
+            assert_eq!((plane >> i) & 1, (x as u64 >> 3) & 1);
+        }
+        assert_eq!(pack_bits(&values[..2], 0), 0b01);
+
+        let words: Vec<u64> = (0..4).map(|j| pack_bits(&values, j)).collect();
+        unpack_bits(&mut values, 3, !plane);
+        unpack_bits(&mut values, 3, plane);
+        for (j, &w) in words.iter().enumerate() {
+            assert_eq!(pack_bits(&values, j), w);
+        }
+        unpack_bits(&mut values, 0, 0);
+        assert!(values.iter().all(|x| x & 1 == 0));
+    }
+
+    #[test]
+    fn test_transpose64() {
+        let mut matrix = [0u64; 64];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            *row = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        }
+        let original = matrix;
+
+        transpose64(&mut matrix);
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, column) in original.iter().enumerate() {
+                assert_eq!((row >> j) & 1, (column >> i) & 1);
+            }
+        }
+    }
+
     #[test]
     #[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
     fn test_bitrev() {
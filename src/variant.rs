@@ -0,0 +1,452 @@
+//! Runtime selection of the variant
+//!
+//! The parameters of Classic McEliece are compile-time constants of this crate, selected by feature flags.
+//! `Variant` names all ten variants, so that applications can pick one at runtime (e.g. from a config file).
+//! `Variant::kem` returns the KEM of the variant as `Box<dyn Kem>`, if the variant is part of this build.
+//! The `Kem` trait operates on byte slices instead of the fixed-size types, because their sizes
+//! differ between the variants. `Variant::params` provides these sizes for every variant,
+//! also for those which are not part of this build.
+
+use std::fmt;
+
+#[cfg(not(feature = "decap-only"))]
+use rand_core::CryptoRngCore;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use zeroize::Zeroizing;
+
+use crate::api::{
+    CRYPTO_BYTES, CRYPTO_CIPHERTEXTBYTES, CRYPTO_PUBLICKEYBYTES, CRYPTO_SECRETKEYBYTES,
+};
+use crate::error::Error;
+#[cfg(not(feature = "encap-only"))]
+use crate::operations::decap_into;
+#[cfg(not(feature = "decap-only"))]
+use crate::operations::encap_into;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::operations::keypair_into;
+use crate::types::SharedSecret;
+
+/// The ten variants (i.e. parameter sets) of Classic McEliece
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Variant {
+    Mceliece348864,
+    Mceliece348864f,
+    Mceliece460896,
+    Mceliece460896f,
+    Mceliece6688128,
+    Mceliece6688128f,
+    Mceliece6960119,
+    Mceliece6960119f,
+    Mceliece8192128,
+    Mceliece8192128f,
+}
+
+impl Variant {
+    /// All variants in the order of the specification
+    pub const ALL: [Variant; 10] = [
+        Variant::Mceliece348864,
+        Variant::Mceliece348864f,
+        Variant::Mceliece460896,
+        Variant::Mceliece460896f,
+        Variant::Mceliece6688128,
+        Variant::Mceliece6688128f,
+        Variant::Mceliece6960119,
+        Variant::Mceliece6960119f,
+        Variant::Mceliece8192128,
+        Variant::Mceliece8192128f,
+    ];
+
+    /// Returns the canonical name of this variant (e.g. `mceliece6688128f`),
+    /// which is also its feature flag and `CRYPTO_PRIMITIVE`
+    pub const fn name(self) -> &'static str {
+        match self {
+            Variant::Mceliece348864 => "mceliece348864",
+            Variant::Mceliece348864f => "mceliece348864f",
+            Variant::Mceliece460896 => "mceliece460896",
+            Variant::Mceliece460896f => "mceliece460896f",
+            Variant::Mceliece6688128 => "mceliece6688128",
+            Variant::Mceliece6688128f => "mceliece6688128f",
+            Variant::Mceliece6960119 => "mceliece6960119",
+            Variant::Mceliece6960119f => "mceliece6960119f",
+            Variant::Mceliece8192128 => "mceliece8192128",
+            Variant::Mceliece8192128f => "mceliece8192128f",
+        }
+    }
+
+    /// Returns the object identifier of this variant in dotted notation, i.e. `1.3.6.1.4.1.22554.5.1.k`
+    /// for the `k`-th variant of `Variant::ALL`. These are the draft OIDs of the Bouncy Castle arc,
+    /// also used by Botan and at the IETF hackathons, until OIDs are assigned by a standard.
+    pub const fn oid(self) -> &'static str {
+        match self {
+            Variant::Mceliece348864 => "1.3.6.1.4.1.22554.5.1.1",
+            Variant::Mceliece348864f => "1.3.6.1.4.1.22554.5.1.2",
+            Variant::Mceliece460896 => "1.3.6.1.4.1.22554.5.1.3",
+            Variant::Mceliece460896f => "1.3.6.1.4.1.22554.5.1.4",
+            Variant::Mceliece6688128 => "1.3.6.1.4.1.22554.5.1.5",
+            Variant::Mceliece6688128f => "1.3.6.1.4.1.22554.5.1.6",
+            Variant::Mceliece6960119 => "1.3.6.1.4.1.22554.5.1.7",
+            Variant::Mceliece6960119f => "1.3.6.1.4.1.22554.5.1.8",
+            Variant::Mceliece8192128 => "1.3.6.1.4.1.22554.5.1.9",
+            Variant::Mceliece8192128f => "1.3.6.1.4.1.22554.5.1.10",
+        }
+    }
+
+    /// Returns the variant with the canonical name `name` (see `Variant::name`)
+    pub fn from_name(name: &str) -> Option<Variant> {
+        Variant::ALL.into_iter().find(|v| v.name() == name)
+    }
+
+    /// The variant selected by the feature flags of this build
+    pub const fn compiled() -> Variant {
+        #[cfg(feature = "mceliece348864")]
+        return Variant::Mceliece348864;
+        #[cfg(feature = "mceliece348864f")]
+        return Variant::Mceliece348864f;
+        #[cfg(feature = "mceliece460896")]
+        return Variant::Mceliece460896;
+        #[cfg(feature = "mceliece460896f")]
+        return Variant::Mceliece460896f;
+        #[cfg(feature = "mceliece6688128")]
+        return Variant::Mceliece6688128;
+        #[cfg(feature = "mceliece6688128f")]
+        return Variant::Mceliece6688128f;
+        #[cfg(feature = "mceliece6960119")]
+        return Variant::Mceliece6960119;
+        #[cfg(feature = "mceliece6960119f")]
+        return Variant::Mceliece6960119f;
+        #[cfg(feature = "mceliece8192128")]
+        return Variant::Mceliece8192128;
+        #[cfg(feature = "mceliece8192128f")]
+        return Variant::Mceliece8192128f;
+    }
+
+    /// Returns the parameters of this variant
+    pub const fn params(self) -> Params {
+        let (n, m, t, f_variant) = match self {
+            Variant::Mceliece348864 => (3488, 12, 64, false),
+            Variant::Mceliece348864f => (3488, 12, 64, true),
+            Variant::Mceliece460896 => (4608, 13, 96, false),
+            Variant::Mceliece460896f => (4608, 13, 96, true),
+            Variant::Mceliece6688128 => (6688, 13, 128, false),
+            Variant::Mceliece6688128f => (6688, 13, 128, true),
+            Variant::Mceliece6960119 => (6960, 13, 119, false),
+            Variant::Mceliece6960119f => (6960, 13, 119, true),
+            Variant::Mceliece8192128 => (8192, 13, 128, false),
+            Variant::Mceliece8192128f => (8192, 13, 128, true),
+        };
+
+        // the same layouts as the constants in params.rs and api.rs
+        let pk_nrows = m * t;
+        let cond_bytes = (1 << (m - 4)) * (2 * m - 1);
+        Params {
+            variant: self,
+            n,
+            m,
+            t,
+            public_key_len: pk_nrows * (n - pk_nrows).div_ceil(8),
+            secret_key_len: 40 + 2 * t + cond_bytes + n / 8,
+            ciphertext_len: pk_nrows.div_ceil(8) + 32,
+            shared_secret_len: 32,
+            f_variant,
+        }
+    }
+
+    /// Returns the variants whose public keys have `len` bytes.
+    ///
+    /// The result is ambiguous by design: an f-variant and its non-f counterpart
+    /// share all encodings, since they only differ in the key generation.
+    /// Hence, either of them can encapsulate to (or decapsulate with) the keys of the other.
+    pub fn detect_from_public_key_len(len: usize) -> Vec<Variant> {
+        Variant::ALL
+            .into_iter()
+            .filter(|v| v.params().public_key_len == len)
+            .collect()
+    }
+
+    /// Returns the variants whose ciphertexts have `len` bytes.
+    ///
+    /// Besides the f-variants (see `Variant::detect_from_public_key_len`), mceliece6688128 and
+    /// mceliece8192128 share the ciphertext length. Hence, a ciphertext of 240 bytes does not determine
+    /// the parameter set and the result must be combined with other information (e.g. the secret key length).
+    pub fn detect_from_ciphertext_len(len: usize) -> Vec<Variant> {
+        Variant::ALL
+            .into_iter()
+            .filter(|v| v.params().ciphertext_len == len)
+            .collect()
+    }
+
+    /// Returns the KEM of this variant.
+    ///
+    /// Fails with `Error::UnsupportedVariant` if the variant is not part of this build.
+    pub fn kem(self) -> Result<Box<dyn Kem>, Error> {
+        if self == Variant::compiled() {
+            Ok(Box::new(CompiledKem))
+        } else {
+            Err(Error::UnsupportedVariant(self))
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The parameters of a variant, see `Variant::params`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Params {
+    /// The variant with these parameters
+    pub variant: Variant,
+    /// The code length n
+    pub n: usize,
+    /// The degree m of the field GF(2^m)
+    pub m: usize,
+    /// The number t of errors the code corrects
+    pub t: usize,
+    /// The number of bytes of a public key
+    pub public_key_len: usize,
+    /// The number of bytes of a secret key
+    pub secret_key_len: usize,
+    /// The number of bytes of a ciphertext
+    pub ciphertext_len: usize,
+    /// The number of bytes of a shared secret
+    pub shared_secret_len: usize,
+    /// Whether the key generation uses the semi-systematic form (i.e. the f-variants)
+    pub f_variant: bool,
+}
+
+/// Object-safe KEM interface of one variant, see `Variant::kem`.
+///
+/// Keys and ciphertexts are byte vectors (or slices) of the lengths returned by the `*_len` methods.
+pub trait Kem {
+    /// The variant implemented by this KEM
+    fn variant(&self) -> Variant;
+
+    /// The number of bytes of a public key
+    fn public_key_len(&self) -> usize;
+
+    /// The number of bytes of a secret key
+    fn secret_key_len(&self) -> usize;
+
+    /// The number of bytes of a ciphertext
+    fn ciphertext_len(&self) -> usize;
+
+    /// The number of bytes of a shared secret
+    fn shared_secret_len(&self) -> usize;
+
+    /// KEM Keypair generation returning the public key and the secret key.
+    /// The secret key is wiped from memory when dropped.
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn keypair(&self, rng: &mut dyn CryptoRngCore) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>), Error>;
+
+    /// KEM Encapsulation returning the ciphertext and the shared secret
+    #[cfg(not(feature = "decap-only"))]
+    fn encapsulate(
+        &self,
+        pk: &[u8],
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<(Vec<u8>, SharedSecret), Error>;
+
+    /// KEM Decapsulation returning the shared secret
+    #[cfg(not(feature = "encap-only"))]
+    fn decapsulate(&self, sk: &[u8], c: &[u8]) -> Result<SharedSecret, Error>;
+}
+
+/// The KEM of `Variant::compiled()`
+struct CompiledKem;
+
+impl Kem for CompiledKem {
+    fn variant(&self) -> Variant {
+        Variant::compiled()
+    }
+
+    fn public_key_len(&self) -> usize {
+        CRYPTO_PUBLICKEYBYTES
+    }
+
+    fn secret_key_len(&self) -> usize {
+        CRYPTO_SECRETKEYBYTES
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        CRYPTO_CIPHERTEXTBYTES
+    }
+
+    fn shared_secret_len(&self) -> usize {
+        CRYPTO_BYTES
+    }
+
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn keypair(
+        &self,
+        mut rng: &mut dyn CryptoRngCore,
+    ) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>), Error> {
+        let mut pk = vec![0u8; CRYPTO_PUBLICKEYBYTES];
+        let mut sk = Zeroizing::new(vec![0u8; CRYPTO_SECRETKEYBYTES]);
+        keypair_into(&mut pk, &mut sk, &mut rng)?;
+        Ok((pk, sk))
+    }
+
+    #[cfg(not(feature = "decap-only"))]
+    fn encapsulate(
+        &self,
+        pk: &[u8],
+        mut rng: &mut dyn CryptoRngCore,
+    ) -> Result<(Vec<u8>, SharedSecret), Error> {
+        let mut c = vec![0u8; CRYPTO_CIPHERTEXTBYTES];
+        let mut key = SharedSecret::zero();
+        encap_into(&mut c, &mut key.0, pk, &mut rng)?;
+        Ok((c, key))
+    }
+
+    #[cfg(not(feature = "encap-only"))]
+    fn decapsulate(&self, sk: &[u8], c: &[u8]) -> Result<SharedSecret, Error> {
+        let mut key = SharedSecret::zero();
+        decap_into(&mut key.0, c, sk)?;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::CRYPTO_PRIMITIVE;
+    use crate::params::{GFBITS, SYS_N, SYS_T};
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    use crate::params::{PK_ROW_BYTES, SYND_BYTES};
+
+    #[test]
+    fn test_compiled() {
+        assert_eq!(Variant::compiled().to_string(), CRYPTO_PRIMITIVE);
+        assert_eq!(
+            Variant::ALL
+                .iter()
+                .filter(|&&v| v.kem().is_ok())
+                .collect::<Vec<_>>(),
+            [&Variant::compiled()]
+        );
+    }
+
+    #[test]
+    fn test_oid() {
+        for (k, variant) in Variant::ALL.into_iter().enumerate() {
+            assert_eq!(variant.oid(), format!("1.3.6.1.4.1.22554.5.1.{}", k + 1));
+        }
+    }
+
+    #[test]
+    fn test_from_name() {
+        for variant in Variant::ALL {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+            assert_eq!(variant.to_string(), format!("{:?}", variant).to_lowercase());
+        }
+        assert_eq!(
+            Variant::from_name("mceliece6688128f"),
+            Some(Variant::Mceliece6688128f)
+        );
+        assert_eq!(Variant::from_name("Mceliece6688128f"), None);
+        assert_eq!(Variant::from_name("mceliece6688128F"), None);
+        assert_eq!(Variant::from_name(""), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert!(Variant::detect_from_public_key_len(CRYPTO_PUBLICKEYBYTES)
+            .contains(&Variant::compiled()));
+        assert!(Variant::detect_from_ciphertext_len(CRYPTO_CIPHERTEXTBYTES)
+            .contains(&Variant::compiled()));
+
+        assert_eq!(
+            Variant::detect_from_public_key_len(1047319),
+            [Variant::Mceliece6960119, Variant::Mceliece6960119f]
+        );
+        assert_eq!(
+            Variant::detect_from_ciphertext_len(240),
+            [
+                Variant::Mceliece6688128,
+                Variant::Mceliece6688128f,
+                Variant::Mceliece8192128,
+                Variant::Mceliece8192128f
+            ]
+        );
+        assert!(Variant::detect_from_public_key_len(CRYPTO_PUBLICKEYBYTES + 1).is_empty());
+        assert!(Variant::detect_from_ciphertext_len(0).is_empty());
+    }
+
+    #[test]
+    fn test_params() {
+        let params = Variant::compiled().params();
+        assert_eq!(params.variant, Variant::compiled());
+        assert_eq!((params.n, params.m, params.t), (SYS_N, GFBITS, SYS_T));
+        assert_eq!(params.public_key_len, CRYPTO_PUBLICKEYBYTES);
+        assert_eq!(params.secret_key_len, CRYPTO_SECRETKEYBYTES);
+        assert_eq!(params.ciphertext_len, CRYPTO_CIPHERTEXTBYTES);
+        assert_eq!(params.shared_secret_len, CRYPTO_BYTES);
+        assert_eq!(params.f_variant, CRYPTO_PRIMITIVE.ends_with('f'));
+
+        let params = Variant::Mceliece6960119f.params();
+        assert_eq!(
+            (
+                params.public_key_len,
+                params.secret_key_len,
+                params.ciphertext_len
+            ),
+            (1047319, 13948, 226)
+        );
+        assert!(params.f_variant);
+        assert!(!Variant::Mceliece8192128.params().f_variant);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_kem_roundtrip() -> Result<(), Error> {
+        let kem = Variant::compiled().kem()?;
+        assert_eq!(kem.variant(), Variant::compiled());
+
+        let mut rng = crate::test_rng(0x2A);
+        let (pk, sk) = kem.keypair(&mut rng)?;
+        assert_eq!(pk.len(), kem.public_key_len());
+        assert_eq!(sk.len(), kem.secret_key_len());
+
+        let (c, ss_bob) = kem.encapsulate(&pk, &mut rng)?;
+        assert_eq!(c.len(), kem.ciphertext_len());
+        let ss_alice = kem.decapsulate(&sk, &c)?;
+        assert_eq!(ss_alice.as_array().len(), kem.shared_secret_len());
+        assert!(ss_alice == ss_bob);
+
+        assert!(matches!(
+            kem.decapsulate(&sk, &c[1..]),
+            Err(Error::WrongLength { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(feature = "mceliece6960119", feature = "mceliece6960119f"))]
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    fn test_kem_padding() -> Result<(), Error> {
+        let kem = Variant::compiled().kem()?;
+        let (pk, sk) = crate::test_keypair();
+        let mut rng = crate::test_rng(0x2B);
+
+        let mut bad_pk = pk.as_array().to_vec();
+        bad_pk[PK_ROW_BYTES - 1] |= 0x80;
+        assert!(matches!(
+            kem.encapsulate(&bad_pk, &mut rng),
+            Err(Error::InvalidPadding)
+        ));
+
+        let (mut c, _) = kem.encapsulate(pk.as_array(), &mut rng)?;
+        c[SYND_BYTES - 1] |= 0x80;
+        assert!(matches!(
+            kem.decapsulate(sk.as_array(), &c),
+            Err(Error::InvalidPadding)
+        ));
+
+        Ok(())
+    }
+}
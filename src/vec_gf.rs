@@ -0,0 +1,159 @@
+//! Bitsliced arithmetic on 64 Galois field elements at once
+//!
+//! The 64 elements are packed across `GFBITS` words, where word `i` holds bit `i` of all elements
+//! (like the `vec` implementations of the submission). Each operation thus processes all 64 elements
+//! with word-wide logic operations, and runs in constant time. Addition is the XOR of the words.
+
+use crate::gf::Gf;
+use crate::params::GFBITS;
+use crate::transpose::transpose_64x64_inplace;
+
+/// 64 field elements in bitsliced form
+pub(crate) type VecGf = [u64; GFBITS];
+
+/// The exponents of the field polynomial below `GFBITS`, i.e. `x^GFBITS = Σ x^p`
+#[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+const POLY: [usize; 2] = [3, 0];
+/// The exponents of the field polynomial below `GFBITS`, i.e. `x^GFBITS = Σ x^p`
+#[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
+const POLY: [usize; 4] = [4, 3, 1, 0];
+
+/// Packs the field elements `a` (at most 64) into bitsliced form. Missing elements are zero.
+/// The bits are moved with a 64×64 bit matrix transposition instead of one by one.
+pub(crate) fn vec_pack(a: &[Gf]) -> VecGf {
+    let mut t = [0u64; 64];
+    for (w, &x) in t.iter_mut().zip(a) {
+        *w = x as u64;
+    }
+
+    transpose_64x64_inplace(&mut t);
+
+    let mut out = [0u64; GFBITS];
+    for (o, &w) in out.iter_mut().zip(t.iter()) {
+        *o = w;
+    }
+    out
+}
+
+/// Unpacks bitsliced `a` into the field elements `out` (at most 64)
+pub(crate) fn vec_unpack(a: &VecGf, out: &mut [Gf]) {
+    let mut t = [0u64; 64];
+    for (w, &x) in t.iter_mut().zip(a) {
+        *w = x;
+    }
+
+    transpose_64x64_inplace(&mut t);
+
+    for (o, &w) in out.iter_mut().zip(t.iter()) {
+        *o = w as Gf;
+    }
+}
+
+/// Reduces the unreduced product `buf` modulo the field polynomial
+fn vec_reduce(buf: &mut [u64; 2 * GFBITS - 1]) -> VecGf {
+    for i in (GFBITS..2 * GFBITS - 1).rev() {
+        let t = buf[i];
+        for p in POLY {
+            buf[i - GFBITS + p] ^= t;
+        }
+    }
+
+    let mut out = [0u64; GFBITS];
+    out.copy_from_slice(&buf[..GFBITS]);
+    out
+}
+
+/// Multiplies bitsliced `a` and `b` elementwise
+pub(crate) fn vec_mul(a: &VecGf, b: &VecGf) -> VecGf {
+    let mut buf = [0u64; 2 * GFBITS - 1];
+
+    for (i, &x) in a.iter().enumerate() {
+        for (t, &y) in buf.iter_mut().skip(i).zip(b) {
+            *t ^= x & y;
+        }
+    }
+
+    vec_reduce(&mut buf)
+}
+
+/// Squares bitsliced `a` elementwise. Squaring is linear over GF(2),
+/// hence bit `i` moves to bit `2i` before the reduction.
+pub(crate) fn vec_sq(a: &VecGf) -> VecGf {
+    let mut buf = [0u64; 2 * GFBITS - 1];
+
+    for (t, &x) in buf.iter_mut().step_by(2).zip(a) {
+        *t = x;
+    }
+
+    vec_reduce(&mut buf)
+}
+
+/// Squares bitsliced `a` elementwise `n` times, i.e. computes `a^(2^n)`
+fn vec_sq_n(a: &VecGf, n: usize) -> VecGf {
+    let mut out = *a;
+    for _ in 0..n {
+        out = vec_sq(&out);
+    }
+    out
+}
+
+/// Inverts bitsliced `a` elementwise as `a^(2^GFBITS - 2)`. Zero maps to zero like `gf_inv`.
+/// `a^(2^(GFBITS - 1) - 1)` takes the addition chain of `gf_inv`, i.e. 5 multiplications
+/// (1, 2, 4, 8, 10, 11) instead of 10 for 12 bits, and 4 (1, 2, 4, 8, 12) instead of 11 for 13 bits.
+pub(crate) fn vec_inv(a: &VecGf) -> VecGf {
+    let ones_2 = vec_mul(&vec_sq(a), a); // a^11
+    let ones_4 = vec_mul(&vec_sq_n(&ones_2, 2), &ones_2); // a^1111
+    let ones_8 = vec_mul(&vec_sq_n(&ones_4, 4), &ones_4); // a^11111111
+
+    #[cfg(any(feature = "mceliece348864", feature = "mceliece348864f"))]
+    let out = {
+        let ones_10 = vec_mul(&vec_sq_n(&ones_8, 2), &ones_2);
+        vec_mul(&vec_sq(&ones_10), a) // a^11111111111
+    };
+    #[cfg(not(any(feature = "mceliece348864", feature = "mceliece348864f")))]
+    let out = vec_mul(&vec_sq_n(&ones_8, 4), &ones_4); // a^111111111111
+
+    vec_sq(&out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gf::{gf_inv, gf_mul};
+    use crate::params::GFMASK;
+
+    fn elements(seed: usize) -> [Gf; 64] {
+        let mut a = [0 as Gf; 64];
+        for (i, x) in a.iter_mut().enumerate() {
+            *x = ((i * 2897 + seed * 131) & GFMASK) as Gf;
+        }
+        a
+    }
+
+    #[test]
+    fn test_vec_pack() {
+        let a = elements(1);
+        let mut out = [0 as Gf; 64];
+        vec_unpack(&vec_pack(&a), &mut out);
+        assert_eq!(a, out);
+    }
+
+    #[test]
+    fn test_vec_arithmetic() {
+        let (a, b) = (elements(3), elements(7));
+        let (va, vb) = (vec_pack(&a), vec_pack(&b));
+
+        let mut prod = [0 as Gf; 64];
+        vec_unpack(&vec_mul(&va, &vb), &mut prod);
+        let mut sq = [0 as Gf; 64];
+        vec_unpack(&vec_sq(&va), &mut sq);
+        let mut inv = [0 as Gf; 64];
+        vec_unpack(&vec_inv(&va), &mut inv);
+
+        for i in 0..64 {
+            assert_eq!(prod[i], gf_mul(a[i], b[i]));
+            assert_eq!(sq[i], gf_mul(a[i], a[i]));
+            assert_eq!(inv[i], gf_inv(a[i]));
+        }
+    }
+}
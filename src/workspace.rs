@@ -0,0 +1,91 @@
+//! Reusable memory for the large temporaries of the operations
+//!
+//! The key generation needs the columns of the identity of the matrix of the public key computation
+//! (up to 340 KB, see `pk_from_permutation`), the decapsulation the inverted squares of the Goppa polynomial
+//! and a buffer for the evaluations at all field elements (up to 32 KB).
+//! The `_with` functions (e.g. `keypair_with`, `decap_with`) take a `Workspace` and allocate this memory
+//! at its first use only, so that loops of many operations do not allocate it each time.
+//! The encapsulation has no large temporaries and thus no such function.
+
+use std::fmt;
+
+use crate::decrypt::DecryptionKey;
+use crate::gf::Gf;
+use crate::params::GFBITS;
+#[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+use crate::{params::PK_NROWS, pk_gen::MAT_LEFT_BYTES};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Memory for the temporaries of the key generation and the decapsulation.
+///
+/// A new workspace allocates nothing, the buffers are allocated when an operation needs them
+/// and kept for later operations. A workspace holds intermediate secret values,
+/// which are overwritten by the next operation (and zeroized on drop with feature `zeroize`).
+#[derive(Default)]
+pub struct Workspace {
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    matrix: Option<Box<[[u8; MAT_LEFT_BYTES]; PK_NROWS]>>,
+    decryption_key: Option<Box<DecryptionKey>>,
+    buf: Option<Box<[Gf; 1 << GFBITS]>>,
+}
+
+impl Workspace {
+    /// Creates a workspace without allocating its buffers yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The left parts of the rows of the matrix of the public key computation
+    #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+    pub(crate) fn matrix(&mut self) -> &mut [[u8; MAT_LEFT_BYTES]; PK_NROWS] {
+        self.matrix.get_or_insert_with(bytemuck::zeroed_box)
+    }
+
+    /// The buffer of the decryption only, for decapsulations with an expanded secret key (see `DecapPool`)
+    pub(crate) fn buffer(&mut self) -> &mut [Gf; 1 << GFBITS] {
+        self.buf.get_or_insert_with(bytemuck::zeroed_box)
+    }
+
+    /// The memory of a decryption key and the buffer of the decryption (see `DecryptionKey::decrypt`)
+    pub(crate) fn decryption(&mut self) -> (&mut DecryptionKey, &mut [Gf; 1 << GFBITS]) {
+        let key = self.decryption_key.get_or_insert_with(bytemuck::zeroed_box);
+        let buf = self.buf.get_or_insert_with(bytemuck::zeroed_box);
+        (key, buf)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        #[cfg(not(any(feature = "encap-only", feature = "decap-only")))]
+        if let Some(matrix) = self.matrix.as_mut() {
+            matrix.iter_mut().for_each(|row| row.zeroize());
+        }
+        if let Some(buf) = self.buf.as_mut() {
+            buf.zeroize();
+        }
+    }
+}
+
+/// Secret data is not revealed by the `Debug` representation
+impl fmt::Debug for Workspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Workspace(…)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_allocates_lazily() {
+        let mut ws = Workspace::new();
+        assert!(ws.buf.is_none() && ws.decryption_key.is_none());
+
+        ws.decryption().1[0] = 1;
+        assert_eq!(ws.decryption().1[0], 1);
+        assert!(ws.buf.is_some() && ws.decryption_key.is_some());
+    }
+}